@@ -0,0 +1,197 @@
+//! # Bitstring Status List
+//!
+//! Support for the W3C [Bitstring Status List] used to convey the status of
+//! W3C Verifiable Credentials.
+//!
+//! Bitstring Status Lists are derived from the issuer's managed (IETF) Status
+//! Lists: a `BitstringStatusListCredential` is published for each status
+//! purpose, with the bit for a credential set when the credential has the
+//! corresponding status.
+//!
+//! [Bitstring Status List]: https://www.w3.org/TR/vc-bitstring-status-list
+
+use std::fmt::{self, Display};
+use std::io::{Read, Write};
+
+use anyhow::{Result, anyhow};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use bitvec::order::Msb0;
+use bitvec::vec::BitVec;
+use bitvec::view::BitView;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+
+use crate::issue::inflate;
+use crate::verify::status_at;
+use crate::{StatusList, StatusListEntry, StatusType};
+
+/// The minimum number of entries in a Bitstring Status List (16KB), used to
+/// provide group privacy.
+pub const MIN_BITSTRING_LENGTH: usize = 131_072;
+
+/// The `type` of the `credentialSubject` of a Bitstring Status List
+/// Credential.
+pub const BITSTRING_STATUS_LIST: &str = "BitstringStatusList";
+
+/// The `type` of a Bitstring Status List Credential.
+pub const BITSTRING_STATUS_LIST_CREDENTIAL: &str = "BitstringStatusListCredential";
+
+/// The purpose of a Bitstring Status List entry.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum StatusPurpose {
+    /// Used to cancel the validity of a credential. This status is not
+    /// reversible.
+    #[default]
+    Revocation,
+
+    /// Used to temporarily prevent the acceptance of a credential. This
+    /// status is reversible.
+    Suspension,
+}
+
+impl StatusPurpose {
+    /// The status a credential has when its bit is set in a list with this
+    /// purpose.
+    #[must_use]
+    pub const fn status(self) -> StatusType {
+        match self {
+            Self::Revocation => StatusType::Invalid,
+            Self::Suspension => StatusType::Suspended,
+        }
+    }
+}
+
+impl Display for StatusPurpose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Revocation => write!(f, "revocation"),
+            Self::Suspension => write!(f, "suspension"),
+        }
+    }
+}
+
+/// A `BitstringStatusListEntry` credential status entry, referencing a
+/// credential's status in a Bitstring Status List Credential.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BitstringStatusListEntry {
+    /// The purpose of the status entry.
+    pub status_purpose: StatusPurpose,
+
+    /// The index of the credential's status in the list, as a base-10
+    /// string.
+    pub status_list_index: String,
+
+    /// The URL of the Bitstring Status List Credential.
+    pub status_list_credential: String,
+}
+
+impl BitstringStatusListEntry {
+    /// Create a Bitstring Status List entry for the `purpose` from the
+    /// credential's managed Status List entry.
+    #[must_use]
+    pub fn new(entry: &StatusListEntry, purpose: StatusPurpose) -> Self {
+        Self {
+            status_purpose: purpose,
+            status_list_index: entry.idx.to_string(),
+            status_list_credential: credential_uri(&entry.uri, purpose),
+        }
+    }
+
+    /// The index of the credential's status in the list.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `statusListIndex` is not a base-10 integer.
+    pub fn index(&self) -> Result<usize> {
+        self.status_list_index.parse().map_err(|_| anyhow!("invalid `statusListIndex`"))
+    }
+}
+
+/// The `credentialSubject` of a Bitstring Status List Credential.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BitstringStatusList {
+    /// The identifier of the list, e.g. `https://example.com/status/3#list`.
+    pub id: String,
+
+    /// Always `BitstringStatusList`.
+    #[serde(rename = "type")]
+    pub r#type: String,
+
+    /// The purpose of the list.
+    pub status_purpose: StatusPurpose,
+
+    /// The GZIP-compressed, multibase (base64url) encoded bitstring.
+    pub encoded_list: String,
+}
+
+impl BitstringStatusList {
+    /// Derive the Bitstring Status List for the `purpose` from the managed
+    /// Status List published at `uri`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Status List cannot be decompressed or the
+    /// bitstring cannot be compressed.
+    pub fn new(uri: &str, status_list: &StatusList, purpose: StatusPurpose) -> Result<Self> {
+        let inflated = inflate(&status_list.lst)?;
+        let len = inflated.len() * 8 / status_list.bits.width();
+
+        let mut bitstring = BitVec::<u8, Msb0>::repeat(false, len.max(MIN_BITSTRING_LENGTH));
+        for idx in 0..len {
+            if status_at(&inflated, status_list.bits, idx)? == purpose.status() {
+                bitstring.set(idx, true);
+            }
+        }
+
+        let mut encoder = GzEncoder::new(vec![], Compression::best());
+        encoder.write_all(&bitstring.into_vec())?;
+        let compressed = encoder.finish()?;
+
+        Ok(Self {
+            id: format!("{}#list", credential_uri(uri, purpose)),
+            r#type: BITSTRING_STATUS_LIST.to_string(),
+            status_purpose: purpose,
+            encoded_list: format!("u{}", Base64UrlUnpadded::encode_string(&compressed)),
+        })
+    }
+
+    /// The URL of the Bitstring Status List Credential.
+    #[must_use]
+    pub fn credential_id(&self) -> &str {
+        self.id.split_once('#').map_or(&self.id, |(id, _)| id)
+    }
+
+    /// Whether the bit at `idx` is set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the encoded list cannot be decoded or if the index
+    /// is out of bounds.
+    pub fn is_set(&self, idx: usize) -> Result<bool> {
+        let Some(encoded) = self.encoded_list.strip_prefix('u') else {
+            return Err(anyhow!("`encodedList` is not base64url multibase-encoded"));
+        };
+        let compressed = Base64UrlUnpadded::decode_vec(encoded)
+            .map_err(|_| anyhow!("invalid base64url-encoded `encodedList`"))?;
+
+        let mut decoder = GzDecoder::new(compressed.as_slice());
+        let mut bitstring = Vec::new();
+        decoder.read_to_end(&mut bitstring)?;
+
+        let Some(bit) = bitstring.view_bits::<Msb0>().get(idx).map(|b| *b) else {
+            return Err(anyhow!("index {idx} is out of bounds"));
+        };
+        Ok(bit)
+    }
+}
+
+// The URL of the Bitstring Status List Credential for the `purpose`, derived
+// from the managed Status List's URI.
+fn credential_uri(uri: &str, purpose: StatusPurpose) -> String {
+    format!("{uri}/{purpose}")
+}
@@ -4,11 +4,16 @@
 //! to the appropriate handler for processing, returning a reply that can be
 //! serialized to a JSON object.
 
+mod aggregation;
+mod bitstring;
 mod statuslist;
+mod update_status;
 
 use std::fmt::Debug;
 
 pub use credibil_core::api::{Body, Handler, Headers, Request, Response};
+use http::HeaderMap;
+use http::header::ACCEPT;
 use tracing::instrument;
 
 use crate::error::Error;
@@ -40,3 +45,18 @@ where
     let request: Request<B, H> = request.into();
     Ok(request.handle(issuer, provider).await?.into())
 }
+
+/// Status List request headers, used to negotiate the format of the returned
+/// Status List Token.
+#[derive(Clone, Debug, Default)]
+pub struct StatusListHeaders {
+    /// The `accept` header.
+    pub accept: Option<String>,
+}
+
+impl From<HeaderMap> for StatusListHeaders {
+    fn from(headers: HeaderMap) -> Self {
+        let accept = headers.get(ACCEPT).and_then(|h| h.to_str().ok()).map(ToString::to_string);
+        Self { accept }
+    }
+}
@@ -0,0 +1,36 @@
+//! # Status List Aggregation Endpoint
+//!
+//! Used by relying parties to discover all Status List Tokens published by
+//! an issuer, for example, to pre-fetch them for offline status checking.
+
+use anyhow::Context;
+
+use crate::handlers::{Body, Error, Handler, Request, Response, Result};
+use crate::provider::{Provider, StatusStore};
+use crate::{StatusListAggregation, StatusListAggregationRequest};
+
+/// Status List Aggregation request handler.
+///
+/// # Errors
+///
+/// Returns an error if the issuer's Status Lists cannot be retrieved.
+async fn aggregation(issuer: &str, provider: &impl Provider) -> Result<StatusListAggregation> {
+    let records = StatusStore::lists(provider, issuer).await.context("retrieving status lists")?;
+
+    let mut status_lists = records.into_iter().map(|r| r.uri).collect::<Vec<_>>();
+    status_lists.sort_unstable();
+
+    Ok(StatusListAggregation { status_lists })
+}
+
+impl<P: Provider> Handler<StatusListAggregation, P> for Request<StatusListAggregationRequest> {
+    type Error = Error;
+
+    async fn handle(
+        self, issuer: &str, provider: &P,
+    ) -> Result<impl Into<Response<StatusListAggregation>>, Self::Error> {
+        aggregation(issuer, provider).await
+    }
+}
+
+impl Body for StatusListAggregationRequest {}
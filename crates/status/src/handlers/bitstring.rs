@@ -0,0 +1,41 @@
+//! # Bitstring Status List Endpoint
+//!
+//! Serves the W3C Bitstring Status List Credentials referenced by
+//! `BitstringStatusListEntry` credential status entries.
+
+use anyhow::Context;
+
+use crate::error::invalid;
+use crate::handlers::{Body, Error, Handler, Request, Response, Result};
+use crate::provider::{Provider, StatusStore};
+use crate::{BitstringStatusListRequest, BitstringStatusListResponse};
+
+/// Bitstring Status List request handler.
+///
+/// # Errors
+///
+/// Returns an error if the Bitstring Status List Credential is not found or
+/// the provider is not available.
+async fn bitstring(
+    issuer: &str, provider: &impl Provider, request: BitstringStatusListRequest,
+) -> Result<BitstringStatusListResponse> {
+    let Some(credential) = StatusStore::get_bitstring(provider, issuer, &request.id)
+        .await
+        .context("getting status list credential")?
+    else {
+        return Err(invalid!("status list credential not found"));
+    };
+    Ok(BitstringStatusListResponse(credential))
+}
+
+impl<P: Provider> Handler<BitstringStatusListResponse, P> for Request<BitstringStatusListRequest> {
+    type Error = Error;
+
+    async fn handle(
+        self, issuer: &str, provider: &P,
+    ) -> Result<impl Into<Response<BitstringStatusListResponse>>, Self::Error> {
+        bitstring(issuer, provider, self.body).await
+    }
+}
+
+impl Body for BitstringStatusListRequest {}
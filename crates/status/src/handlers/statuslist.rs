@@ -3,29 +3,62 @@
 use anyhow::Context;
 
 use crate::error::invalid;
-use crate::handlers::{Body, Error, Handler, Request, Response, Result};
+use crate::handlers::{
+    Body, Error, Handler, Headers, Request, Response, Result, StatusListHeaders,
+};
 use crate::provider::{Provider, StatusStore};
-use crate::{StatusListRequest, StatusListResponse};
+use crate::{STATUSLIST_CWT, StatusListRequest, StatusListResponse};
 
 /// Status List request handler.
 ///
+/// Returns the Status List Token in CWT format when requested using the
+/// `accept` header, otherwise in JWT format. When the request specifies a
+/// `time`, the token that was valid at that time is returned.
+///
 /// # Errors
 ///
 /// Returns an `OpenID4VP` error if the request is invalid or if the provider is
 /// not available.
 async fn statuslist(
-    issuer: &str, provider: &impl Provider, request: StatusListRequest,
+    issuer: &str, provider: &impl Provider, request: StatusListRequest, headers: &StatusListHeaders,
 ) -> Result<StatusListResponse> {
     let Some(id) = request.id else {
         return Err(invalid!("missing id"));
     };
+    let cwt = headers.accept.as_ref().is_some_and(|accept| accept.contains(STATUSLIST_CWT));
+
+    // historical resolution
+    if let Some(time) = request.time {
+        let Some(version) = StatusStore::get_version(provider, issuer, &id, time)
+            .await
+            .context("getting status token version")?
+        else {
+            return Err(invalid!("no status token valid at {time}"));
+        };
+        if !cwt {
+            return Ok(StatusListResponse::Jwt(version.jwt));
+        }
+        let Some(token) = version.cwt else {
+            return Err(invalid!("no status token valid at {time}"));
+        };
+        return Ok(StatusListResponse::Cwt(token));
+    }
+
+    if cwt {
+        let Some(token) =
+            StatusStore::get_cwt(provider, issuer, &id).await.context("getting status token")?
+        else {
+            return Err(invalid!("status token not found"));
+        };
+        return Ok(StatusListResponse::Cwt(token));
+    }
 
     let Some(token) = StatusStore::get(provider, issuer, &id).await.context("getting metadata")?
     else {
         return Err(invalid!("status token not found"));
     };
 
-    Ok(StatusListResponse(token))
+    Ok(StatusListResponse::Jwt(token))
 }
 
 impl<P: Provider> Handler<StatusListResponse, P> for Request<StatusListRequest> {
@@ -34,8 +67,20 @@ impl<P: Provider> Handler<StatusListResponse, P> for Request<StatusListRequest>
     async fn handle(
         self, issuer: &str, provider: &P,
     ) -> Result<impl Into<Response<StatusListResponse>>, Self::Error> {
-        statuslist(issuer, provider, self.body).await
+        statuslist(issuer, provider, self.body, &StatusListHeaders::default()).await
+    }
+}
+
+impl<P: Provider> Handler<StatusListResponse, P> for Request<StatusListRequest, StatusListHeaders> {
+    type Error = Error;
+
+    async fn handle(
+        self, issuer: &str, provider: &P,
+    ) -> Result<impl Into<Response<StatusListResponse>>, Self::Error> {
+        statuslist(issuer, provider, self.body, &self.headers).await
     }
 }
 
 impl Body for StatusListRequest {}
+
+impl Headers for StatusListHeaders {}
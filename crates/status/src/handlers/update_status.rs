@@ -0,0 +1,105 @@
+//! # Update Status Endpoints
+//!
+//! Used by issuers to revoke, suspend, or reinstate previously issued
+//! credentials. Affected Status Lists are updated and their Status List
+//! Tokens re-signed.
+
+use std::collections::HashMap;
+
+use anyhow::Context;
+use credibil_proof::Signature;
+
+use crate::error::invalid;
+use crate::handlers::{Body, Error, Handler, Request, Response, Result};
+use crate::provider::{BitstringEncoder, CwtEncoder, Provider, Registry, StatusStore};
+use crate::{
+    CredentialSelector, ReinstateRequest, RevokeRequest, StatusType, SuspendRequest,
+    UpdateStatusResponse,
+};
+
+/// Update status request handler.
+///
+/// # Errors
+///
+/// Returns an error if no issued credentials match the selector, or if the
+/// affected Status Lists cannot be updated.
+async fn update_status<P: Provider + Signature + CwtEncoder + BitstringEncoder>(
+    issuer: &str, provider: &P, credentials: &CredentialSelector, status: StatusType,
+) -> Result<UpdateStatusResponse> {
+    let selected = Registry::issued(provider, issuer, credentials)
+        .await
+        .context("retrieving issued credentials")?;
+    if selected.is_empty() {
+        return Err(invalid!("no issued credentials match {credentials:?}"));
+    }
+
+    // hold the lock while lists are updated so concurrent allocations and
+    // status changes are not lost
+    let _guard = StatusStore::lock(provider, issuer).await.context("locking status lists")?;
+
+    // group entries by status list so each list is only re-signed once
+    let mut by_list = HashMap::<String, Vec<usize>>::new();
+    for issued in &selected {
+        by_list.entry(issued.status.uri.clone()).or_default().push(issued.status.idx);
+    }
+
+    for (uri, indices) in by_list {
+        let Some(mut record) = StatusStore::get_list(provider, issuer, &uri)
+            .await
+            .context("retrieving status list")?
+        else {
+            return Err(invalid!("status list {uri} not found"));
+        };
+
+        record.status_list.set_statuses(&indices, &status).context("setting status")?;
+
+        StatusStore::put_list(provider, issuer, &record).await.context("saving status list")?;
+        record.publish(issuer, provider).await?;
+    }
+
+    Ok(UpdateStatusResponse {
+        updated: selected.into_iter().map(|i| i.status).collect(),
+    })
+}
+
+impl<P: Provider + Signature + CwtEncoder + BitstringEncoder> Handler<UpdateStatusResponse, P>
+    for Request<RevokeRequest>
+{
+    type Error = Error;
+
+    async fn handle(
+        self, issuer: &str, provider: &P,
+    ) -> Result<impl Into<Response<UpdateStatusResponse>>, Self::Error> {
+        update_status(issuer, provider, &self.body.credentials, StatusType::Invalid).await
+    }
+}
+
+impl Body for RevokeRequest {}
+
+impl<P: Provider + Signature + CwtEncoder + BitstringEncoder> Handler<UpdateStatusResponse, P>
+    for Request<SuspendRequest>
+{
+    type Error = Error;
+
+    async fn handle(
+        self, issuer: &str, provider: &P,
+    ) -> Result<impl Into<Response<UpdateStatusResponse>>, Self::Error> {
+        update_status(issuer, provider, &self.body.credentials, StatusType::Suspended).await
+    }
+}
+
+impl Body for SuspendRequest {}
+
+impl<P: Provider + Signature + CwtEncoder + BitstringEncoder> Handler<UpdateStatusResponse, P>
+    for Request<ReinstateRequest>
+{
+    type Error = Error;
+
+    async fn handle(
+        self, issuer: &str, provider: &P,
+    ) -> Result<impl Into<Response<UpdateStatusResponse>>, Self::Error> {
+        update_status(issuer, provider, &self.body.credentials, StatusType::Valid).await
+    }
+}
+
+impl Body for ReinstateRequest {}
@@ -5,9 +5,8 @@ use std::io::{Read, Write};
 
 use anyhow::{Context, Result, anyhow};
 use base64ct::{Base64UrlUnpadded, Encoding};
+use bitvec::field::BitField;
 use bitvec::order::Lsb0;
-use bitvec::slice::BitSlice;
-use bitvec::vec::BitVec;
 use bitvec::view::BitView;
 use chrono::{DateTime, Utc};
 use credibil_jose::Jws;
@@ -15,9 +14,8 @@ use credibil_proof::Signature;
 use flate2::Compression;
 use flate2::read::ZlibDecoder;
 use flate2::write::ZlibEncoder;
-use serde::{Deserialize, Serialize};
 
-use crate::{BitsPerToken, StatusClaim, StatusList, StatusListClaims, StatusListEntry};
+use crate::{BitsPerToken, StatusList, StatusListClaims, StatusType};
 
 impl StatusList {
     /// Create a new Status List with one bit per referenced token and all
@@ -27,64 +25,104 @@ impl StatusList {
     ///
     /// Returns an error if the Zlib compression fails.
     pub fn new() -> Result<Self> {
-        let bitslice = BitSlice::<u8>::from_slice(&[u8::MIN]);
-        let mut bitvec: BitVec<u8> = bitslice.to_bitvec();
-
-        let mut encoder = ZlibEncoder::new(vec![], Compression::best());
-        encoder.write_all(bitvec.as_raw_mut_slice())?;
-        let deflated = encoder.finish()?;
+        Self::with_capacity(8, BitsPerToken::One)
+    }
 
+    /// Create a new Status List able to hold `capacity` referenced tokens
+    /// using `bits` bits per token, with all entries set to a default of
+    /// `StatusType::Valid`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the capacity is too large or the Zlib compression
+    /// fails.
+    pub fn with_capacity(capacity: usize, bits: BitsPerToken) -> Result<Self> {
+        let Some(len) = capacity.checked_mul(bits.width()) else {
+            return Err(anyhow!("capacity {capacity} is too large"));
+        };
+        let bytes = vec![u8::MIN; len.div_ceil(8)];
         Ok(Self {
-            bits: BitsPerToken::One,
-            lst: Base64UrlUnpadded::encode_string(&deflated),
+            bits,
+            lst: deflate(&bytes)?,
             aggregation_uri: None,
         })
     }
 
-    /// Add an entry to the Status List, returning the claim to use in the
-    /// referenced token/credential.
+    /// Set the status of the referenced token at `idx`.
     ///
     /// # Errors
     ///
-    /// Returns an error if the Zlib decompression fails.
-    pub fn add_entry(&mut self, uri: impl Into<String>) -> Result<StatusClaim> {
-        // inflate the list
-        let deflated = Base64UrlUnpadded::decode_vec(&self.lst)
-            .map_err(|_| anyhow!("Invalid base64url-encoded status list"))?;
-        let mut decoder = ZlibDecoder::new(deflated.as_slice());
-        let mut inflated = Vec::new();
-        decoder.read_to_end(&mut inflated)?;
-
-        let idx = 0;
-
-        // resize
-        // inflated.resize(1, 0);
-        let bitslice = inflated.view_bits_mut::<Lsb0>();
-        bitslice.set(idx, true);
+    /// Returns an error if the Zlib (de)compression fails, if the index is
+    /// out of bounds, or if the status cannot be represented using the list's
+    /// bits per token.
+    pub fn set_status(&mut self, idx: usize, status: &StatusType) -> Result<()> {
+        self.set_statuses(&[idx], status)
+    }
 
-        // compress and update the list
-        let mut encoder = ZlibEncoder::new(vec![], Compression::best());
-        encoder.write_all(inflated.as_slice())?;
-        let deflated = encoder.finish()?;
-        self.lst = Base64UrlUnpadded::encode_string(&deflated);
+    /// Set the status of the referenced tokens at each of `indices`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Zlib (de)compression fails, if any index is
+    /// out of bounds, or if the status cannot be represented using the list's
+    /// bits per token.
+    pub fn set_statuses(&mut self, indices: &[usize], status: &StatusType) -> Result<()> {
+        let width = self.bits.width();
+        let value = u8::from(status);
+        if width < 8 && value >> width != 0 {
+            return Err(anyhow!("status {status:?} requires more than {width} bit(s)"));
+        }
 
-        Ok(StatusClaim {
-            status_list: StatusListEntry { idx, uri: uri.into() },
-        })
+        let mut inflated = inflate(&self.lst)?;
+        let bitslice = inflated.view_bits_mut::<Lsb0>();
+        for &idx in indices {
+            let Some(entry) = bit_range(idx, width).and_then(|range| bitslice.get_mut(range))
+            else {
+                return Err(anyhow!("index {idx} is out of bounds"));
+            };
+            entry.store_le(value);
+        }
+
+        self.lst = deflate(&inflated)?;
+        Ok(())
     }
 }
 
-/// Used to query the Status List endpoint in order to return Status List
-/// Token(s).
-#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
-pub struct StatusListResponse(pub String);
+// The range of bits holding the status at `idx`, or `None` if the range
+// cannot be represented.
+pub(crate) fn bit_range(idx: usize, width: usize) -> Option<std::ops::Range<usize>> {
+    let start = idx.checked_mul(width)?;
+    let end = idx.checked_add(1)?.checked_mul(width)?;
+    Some(start..end)
+}
+
+/// Decode and decompress a base64url-encoded, Zlib-compressed byte array.
+pub(crate) fn inflate(lst: &str) -> Result<Vec<u8>> {
+    let deflated = Base64UrlUnpadded::decode_vec(lst)
+        .map_err(|_| anyhow!("Invalid base64url-encoded status list"))?;
+    let mut decoder = ZlibDecoder::new(deflated.as_slice());
+    let mut inflated = Vec::new();
+    decoder.read_to_end(&mut inflated)?;
+    Ok(inflated)
+}
+
+/// Compress and base64url-encode a byte array.
+pub(crate) fn deflate(bytes: &[u8]) -> Result<String> {
+    let mut encoder = ZlibEncoder::new(vec![], Compression::best());
+    encoder.write_all(bytes)?;
+    let deflated = encoder.finish()?;
+    Ok(Base64UrlUnpadded::encode_string(&deflated))
+}
 
 /// Generate a Status List token.
 #[derive(Debug)]
 pub struct TokenBuilder<L, U, S> {
     status_list: L,
     uri: U,
+    issuer: Option<String>,
     expiry: Option<DateTime<Utc>>,
+    ttl: Option<usize>,
+    aggregation_uri: Option<String>,
     signer: S,
 }
 
@@ -122,7 +160,10 @@ impl TokenBuilder<NoList, NoUri, NoSigner> {
         Self {
             status_list: NoList,
             uri: NoUri,
+            issuer: None,
             expiry: None,
+            ttl: None,
+            aggregation_uri: None,
             signer: NoSigner,
         }
     }
@@ -134,7 +175,10 @@ impl<U, S> TokenBuilder<NoList, U, S> {
         TokenBuilder {
             status_list: HasList(status_list),
             uri: self.uri,
+            issuer: self.issuer,
             expiry: self.expiry,
+            ttl: self.ttl,
+            aggregation_uri: self.aggregation_uri,
             signer: self.signer,
         }
     }
@@ -146,19 +190,49 @@ impl<L, S> TokenBuilder<L, NoUri, S> {
         TokenBuilder {
             status_list: self.status_list,
             uri: HasUri(uri.into()),
+            issuer: self.issuer,
             expiry: self.expiry,
+            ttl: self.ttl,
+            aggregation_uri: self.aggregation_uri,
             signer: self.signer,
         }
     }
 }
 
 impl<L, U, S> TokenBuilder<L, U, S> {
+    /// Set the issuer of the Status List Token (`iss` claim).
+    #[must_use]
+    pub fn issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
     /// Set the expiration date for the status list.
     #[must_use]
     pub const fn expiry(mut self, expiry: DateTime<Utc>) -> Self {
         self.expiry = Some(expiry);
         self
     }
+
+    /// Set the maximum time, in seconds, consumers can cache the Status List
+    /// Token before fetching a fresh copy.
+    #[must_use]
+    pub const fn ttl(mut self, ttl: usize) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Set the Status List Aggregation URI (`aggregation_uri`) for the
+    /// status list.
+    ///
+    /// When not set, the URI defaults to the parent of the Status List Token
+    /// URI. For example, `https://example.com/statuslists` for a token
+    /// published at `https://example.com/statuslists/1`.
+    #[must_use]
+    pub fn aggregation_uri(mut self, aggregation_uri: impl Into<String>) -> Self {
+        self.aggregation_uri = Some(aggregation_uri.into());
+        self
+    }
 }
 
 impl<L, U> TokenBuilder<L, U, NoSigner> {
@@ -168,12 +242,38 @@ impl<L, U> TokenBuilder<L, U, NoSigner> {
         TokenBuilder {
             status_list: self.status_list,
             uri: self.uri,
+            issuer: self.issuer,
             expiry: self.expiry,
+            ttl: self.ttl,
+            aggregation_uri: self.aggregation_uri,
             signer: HasSigner(signer),
         }
     }
 }
 
+impl<S> TokenBuilder<HasList, HasUri, S> {
+    /// The (unsigned) claims the token will be built from. Used when the
+    /// same claims need to be signed in another format, e.g. CWT.
+    #[must_use]
+    pub fn claims(&self) -> StatusListClaims {
+        let uri = &self.uri.0;
+
+        let mut status_list = self.status_list.0.clone();
+        let aggregation_uri = self.aggregation_uri.clone().or(status_list.aggregation_uri);
+        status_list.aggregation_uri =
+            aggregation_uri.or_else(|| uri.rsplit_once('/').map(|(parent, _)| parent.to_string()));
+
+        StatusListClaims {
+            iss: self.issuer.clone(),
+            sub: uri.clone(),
+            iat: Utc::now(),
+            exp: self.expiry,
+            ttl: self.ttl,
+            status_list,
+        }
+    }
+}
+
 impl<S: Signature> TokenBuilder<HasList, HasUri, HasSigner<'_, S>> {
     /// Build the token.
     ///
@@ -181,13 +281,7 @@ impl<S: Signature> TokenBuilder<HasList, HasUri, HasSigner<'_, S>> {
     ///
     /// Returns an error if the token JWS cannot be built.
     pub async fn build(self) -> Result<String> {
-        let claims = StatusListClaims {
-            sub: self.uri.0,
-            iat: Utc::now(),
-            exp: self.expiry,
-            ttl: None,
-            status_list: self.status_list.0,
-        };
+        let claims = self.claims();
 
         let key_ref = self.signer.0.verification_method().await?.try_into()?;
         let jws = Jws::builder()
@@ -7,9 +7,11 @@
 //! issuer and/or verifier implementations, and helper functions for dealing
 //! with supported status endpoint formats.
 
+mod bitstring;
 mod error;
 mod handlers;
 mod issue;
+mod manager;
 mod provider;
 mod verify;
 
@@ -21,9 +23,12 @@ pub use handlers::*;
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
+pub use self::bitstring::*;
 pub use self::error::*;
 pub use self::issue::*;
+pub use self::manager::*;
 pub use self::provider::*;
+pub use self::verify::*;
 
 // `typ` statuslist+jwt
 // https://example.com/statuslists/1
@@ -36,6 +41,11 @@ pub use self::provider::*;
 /// third parties or be transferred for offline use cases.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct StatusListClaims {
+    /// The issuer of the Status List Token. When present, this should match
+    /// the issuer of referenced tokens.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub iss: Option<String>,
+
     /// The URI of the Status List Token. This must be the `uri` claim
     /// contained in the `status_list` claim of the referenced token.
     pub sub: String,
@@ -78,6 +88,11 @@ pub struct StatusList {
     pub aggregation_uri: Option<String>,
 }
 
+/// Used to query the Status List Aggregation endpoint for the URIs of all
+/// Status List Tokens published by the issuer.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StatusListAggregationRequest;
+
 /// Returned by the status list aggregation endpoint.
 #[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct StatusListAggregation {
@@ -86,7 +101,7 @@ pub struct StatusListAggregation {
 }
 
 /// Allowed values for  bits per referenced token in the compressed byte array.
-#[derive(Clone, Debug, Default, Deserialize_repr, Serialize_repr)]
+#[derive(Clone, Copy, Debug, Default, Deserialize_repr, Serialize_repr, PartialEq, Eq)]
 #[repr(i64)]
 pub enum BitsPerToken {
     /// 1 bit per token
@@ -103,6 +118,19 @@ pub enum BitsPerToken {
     Eight = 8,
 }
 
+impl BitsPerToken {
+    /// The number of bits used to hold each referenced token's status.
+    #[must_use]
+    pub const fn width(self) -> usize {
+        match self {
+            Self::One => 1,
+            Self::Two => 2,
+            Self::Four => 4,
+            Self::Eight => 8,
+        }
+    }
+}
+
 /// Used by credential (Referenced Token) issuers to specify how to retrieve
 /// status information about the Referenced Token.
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
@@ -126,33 +154,179 @@ pub struct StatusListEntry {
 
 /// Valid credential status types.
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
-#[repr(i64)]
 pub enum StatusType {
-    /// The credential is valid.
+    /// The credential is valid (`0x00`).
     #[default]
-    Valid = 0,
+    Valid,
 
-    /// The credential is revoked.
-    Invalid = 1,
+    /// The credential is revoked (`0x01`).
+    Invalid,
 
-    /// The credential is suspended.
-    Suspended = 2,
+    /// The credential is suspended (`0x02`).
+    Suspended,
+
+    /// An application-specific status value (`0x03` and `0x0B` to `0x0F`),
+    /// or a value reserved for future registration.
+    ApplicationSpecific(u8),
+}
+
+impl From<u8> for StatusType {
+    fn from(value: u8) -> Self {
+        match value {
+            0x00 => Self::Valid,
+            0x01 => Self::Invalid,
+            0x02 => Self::Suspended,
+            _ => Self::ApplicationSpecific(value),
+        }
+    }
+}
+
+impl From<&StatusType> for u8 {
+    fn from(status: &StatusType) -> Self {
+        match status {
+            StatusType::Valid => 0x00,
+            StatusType::Invalid => 0x01,
+            StatusType::Suspended => 0x02,
+            StatusType::ApplicationSpecific(value) => *value,
+        }
+    }
 }
 
 /// Used to query the Status List endpoint in order to return Status List
 /// Token(s).
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub struct StatusListRequest {
-    /// The index of the Status List to retrieve. When not specified, all
-    /// status lists should be returned.
+    /// The URI of the Status List Token to retrieve. When not specified,
+    /// all status lists should be returned.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<String>,
+
+    /// Retrieve the Status List Token that was valid at this time rather
+    /// than the current token. Used to resolve the status of a referenced
+    /// token at a point in time, e.g. when it was presented.
+    #[serde(with = "ts_seconds_option")]
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub time: Option<DateTime<Utc>>,
 }
 
-/// Used to query the Status List endpoint in order to return Status List
-/// Token(s).
+/// Used to retrieve a W3C Bitstring Status List Credential.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BitstringStatusListRequest {
+    /// The URL of the Bitstring Status List Credential to retrieve.
+    pub id: String,
+}
+
+/// The Bitstring Status List Credential, in `jwt_vc_json` format, returned by
+/// the Bitstring Status List endpoint.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct BitstringStatusListResponse(pub String);
+
+/// The Status List Token returned by the Status List endpoint, in the format
+/// negotiated with the client.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum StatusListResponse {
+    /// A Status List Token in JWT format.
+    Jwt(String),
+
+    /// A Status List Token in CWT format.
+    Cwt(Vec<u8>),
+}
+
+impl StatusListResponse {
+    /// The media type of the Status List Token.
+    #[must_use]
+    pub const fn content_type(&self) -> &'static str {
+        match self {
+            Self::Jwt(_) => STATUSLIST_JWT,
+            Self::Cwt(_) => STATUSLIST_CWT,
+        }
+    }
+}
+
+/// Media type of Status List Tokens in JWT format.
+pub const STATUSLIST_JWT: &str = "application/statuslist+jwt";
+
+/// Media type of Status List Tokens in CWT format.
+pub const STATUSLIST_CWT: &str = "application/statuslist+cwt";
+
+/// A credential (Referenced Token) issued with a Status List entry, as
+/// recorded in the issuer's issued-credential registry.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct IssuedCredential {
+    /// The credential configuration the credential was issued under.
+    pub credential_configuration_id: String,
+
+    /// The subject (holder) the credential was issued to.
+    pub subject_id: String,
+
+    /// The notification id returned to the Wallet with the credential.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notification_id: Option<String>,
+
+    /// The credential's Status List entry.
+    pub status: StatusListEntry,
+}
+
+/// Selects issued credentials by one of the keys recorded in the
+/// issued-credential registry.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialSelector {
+    /// All credentials issued under the credential configuration.
+    CredentialConfigurationId(String),
+
+    /// All credentials issued to the subject.
+    SubjectId(String),
+
+    /// Credentials issued with the notification id.
+    NotificationId(String),
+
+    /// The credential referencing the Status List entry.
+    StatusListEntry(StatusListEntry),
+}
+
+impl CredentialSelector {
+    /// Whether the issued credential is selected.
+    #[must_use]
+    pub fn matches(&self, issued: &IssuedCredential) -> bool {
+        match self {
+            Self::CredentialConfigurationId(id) => &issued.credential_configuration_id == id,
+            Self::SubjectId(id) => &issued.subject_id == id,
+            Self::NotificationId(id) => issued.notification_id.as_ref() == Some(id),
+            Self::StatusListEntry(entry) => &issued.status == entry,
+        }
+    }
+}
+
+/// Request to revoke issued credentials.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct RevokeRequest {
+    /// The credentials to revoke.
+    pub credentials: CredentialSelector,
+}
+
+/// Request to suspend issued credentials.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SuspendRequest {
+    /// The credentials to suspend.
+    pub credentials: CredentialSelector,
+}
+
+/// Request to reinstate revoked or suspended credentials.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ReinstateRequest {
+    /// The credentials to reinstate.
+    pub credentials: CredentialSelector,
+}
+
+/// Returned by the revoke, suspend, and reinstate endpoints.
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
-pub struct StatusListResponse(pub String);
+pub struct UpdateStatusResponse {
+    /// The Status List entries updated.
+    pub updated: Vec<StatusListEntry>,
+}
 
 #[cfg(test)]
 mod tests {
@@ -165,10 +339,103 @@ mod tests {
     }
 
     #[test]
-    fn add_entry() {
+    fn allocate() {
+        let mut record = StatusListRecord::new("https://example.com/status", 16, BitsPerToken::One)
+            .expect("should create record");
+
+        let mut indices = vec![];
+        for _ in 0..16 {
+            let status_claim = record.allocate().expect("should allocate entry");
+            assert_eq!(status_claim.status_list.uri, "https://example.com/status");
+            assert!(record.status_list.is_valid(status_claim.status_list.idx).unwrap());
+            indices.push(status_claim.status_list.idx);
+        }
+
+        indices.sort_unstable();
+        indices.dedup();
+        assert_eq!(indices.len(), 16);
+        assert!(record.is_full());
+        assert!(record.allocate().is_err());
+    }
+
+    #[test]
+    fn decoys() {
+        let mut record = StatusListRecord::new("https://example.com/status", 64, BitsPerToken::One)
+            .expect("should create record");
+        record.add_decoys(8).expect("should add decoys");
+
+        let decoys =
+            (0..64).filter(|idx| !record.status_list.is_valid(*idx).unwrap()).collect::<Vec<_>>();
+        assert_eq!(decoys.len(), 8);
+        assert_eq!(record.allocated, 8);
+
+        // decoys are never allocated, so allocated entries are always valid
+        for _ in 0..56 {
+            let status_claim = record.allocate().expect("should allocate entry");
+            assert!(!decoys.contains(&status_claim.status_list.idx));
+            assert!(record.status_list.is_valid(status_claim.status_list.idx).unwrap());
+        }
+
+        // decoys remain invalid and count towards capacity
+        assert!(decoys.iter().all(|idx| !record.status_list.is_valid(*idx).unwrap()));
+        assert!(record.is_full());
+        assert!(record.allocate().is_err());
+    }
+
+    #[test]
+    fn multi_bit() {
+        let mut status_list =
+            StatusList::with_capacity(4, BitsPerToken::Two).expect("should create status list");
+        status_list.set_status(0, &StatusType::Invalid).unwrap();
+        status_list.set_status(1, &StatusType::Suspended).unwrap();
+        status_list.set_status(3, &StatusType::ApplicationSpecific(3)).unwrap();
+
+        assert_eq!(status_list.status(0).unwrap(), StatusType::Invalid);
+        assert_eq!(status_list.status(1).unwrap(), StatusType::Suspended);
+        assert_eq!(status_list.status(2).unwrap(), StatusType::Valid);
+        assert_eq!(status_list.status(3).unwrap(), StatusType::ApplicationSpecific(3));
+        assert!(status_list.status(4).is_err());
+
+        // indices that overflow are out of bounds
+        assert!(status_list.status(usize::MAX).is_err());
+        assert!(status_list.set_status(usize::MAX, &StatusType::Invalid).is_err());
+        assert!(StatusList::with_capacity(usize::MAX, BitsPerToken::Two).is_err());
+
+        // suspension cannot be represented with a single bit
         let mut status_list = StatusList::new().expect("should create status list");
-        let status_claim = status_list.add_entry("https://example.com/status").unwrap();
+        assert!(status_list.set_status(0, &StatusType::Suspended).is_err());
+    }
+
+    #[test]
+    fn bitstring() {
+        let uri = "https://example.com/statuslists/1";
+        let mut status_list =
+            StatusList::with_capacity(4, BitsPerToken::Two).expect("should create status list");
+        status_list.set_status(0, &StatusType::Invalid).unwrap();
+        status_list.set_status(1, &StatusType::Suspended).unwrap();
+
+        let revocation = BitstringStatusList::new(uri, &status_list, StatusPurpose::Revocation)
+            .expect("should create bitstring");
+        assert_eq!(revocation.credential_id(), "https://example.com/statuslists/1/revocation");
+        assert!(revocation.encoded_list.starts_with('u'));
+        assert!(revocation.is_set(0).unwrap());
+        assert!(!revocation.is_set(1).unwrap());
+        assert!(!revocation.is_set(MIN_BITSTRING_LENGTH - 1).unwrap());
+        assert!(revocation.is_set(MIN_BITSTRING_LENGTH).is_err());
+
+        let suspension = BitstringStatusList::new(uri, &status_list, StatusPurpose::Suspension)
+            .expect("should create bitstring");
+        assert!(!suspension.is_set(0).unwrap());
+        assert!(suspension.is_set(1).unwrap());
 
-        assert_eq!(status_claim.status_list.uri, "https://example.com/status");
+        let entry = BitstringStatusListEntry::new(
+            &StatusListEntry {
+                idx: 1,
+                uri: uri.to_string(),
+            },
+            StatusPurpose::Suspension,
+        );
+        assert_eq!(entry.status_list_credential, suspension.credential_id());
+        assert_eq!(entry.index().unwrap(), 1);
     }
 }
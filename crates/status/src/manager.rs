@@ -0,0 +1,417 @@
+//! # Status List Manager
+//!
+//! Issuer-side allocation of Status List entries. Each referenced token is
+//! assigned a unique, randomly chosen index in a managed Status List. When
+//! the current list is full, a new list is created (sharded) under the same
+//! base URI.
+//!
+//! To provide herd privacy, lists are pre-sized to a large capacity and
+//! seeded with decoy entries so verifiers cannot infer how many tokens have
+//! been issued or revoked, or correlate holders by index.
+
+use anyhow::{Context, Result, anyhow};
+use bitvec::order::Lsb0;
+use bitvec::view::BitView;
+use chrono::{DateTime, TimeDelta, Utc};
+use credibil_proof::Signature;
+use serde::{Deserialize, Serialize};
+
+use crate::issue::{deflate, inflate};
+use crate::provider::{BitstringEncoder, CwtEncoder, StatusStore};
+use crate::{
+    BitsPerToken, BitstringStatusList, StatusClaim, StatusList, StatusListEntry, StatusPurpose,
+    StatusType, TokenBuilder,
+};
+
+/// The default number of referenced tokens a managed Status List can hold.
+pub const DEFAULT_CAPACITY: usize = 100_000;
+
+/// The default time, in seconds, a published Status List Token is valid for
+/// (`exp`). Tokens should be refreshed well before they expire.
+pub const TOKEN_LIFETIME: i64 = 86_400;
+
+/// The default time, in seconds, consumers may cache a published Status List
+/// Token (`ttl`).
+pub const TOKEN_TTL: usize = 3_600;
+
+/// The default time, in seconds, superseded Status List Token versions are
+/// kept in a list's history, allowing status to be resolved at a point in
+/// time.
+pub const HISTORY_RETENTION: i64 = 30 * 86_400;
+
+/// The default number of unallocated entries marked as invalid (decoys) in
+/// each new managed Status List.
+pub const DEFAULT_DECOYS: usize = DEFAULT_CAPACITY / 100;
+
+/// Allocates Status List entries for referenced tokens (credentials),
+/// persisting allocation state using the `StatusStore` provider.
+#[derive(Clone, Debug)]
+pub struct StatusListManager {
+    base_uri: String,
+    capacity: usize,
+    decoys: usize,
+    bits: BitsPerToken,
+    lifetime: i64,
+    ttl: usize,
+    retention: i64,
+}
+
+impl StatusListManager {
+    /// Create a new `StatusListManager` for Status Lists published under
+    /// `base_uri`. Individual lists are identified as `{base_uri}/{n}`.
+    #[must_use]
+    pub fn new(base_uri: impl Into<String>) -> Self {
+        Self {
+            base_uri: base_uri.into(),
+            capacity: DEFAULT_CAPACITY,
+            decoys: DEFAULT_DECOYS,
+            bits: BitsPerToken::Two,
+            lifetime: TOKEN_LIFETIME,
+            ttl: TOKEN_TTL,
+            retention: HISTORY_RETENTION,
+        }
+    }
+
+    /// Set the number of referenced tokens each new Status List can hold.
+    ///
+    /// Lists are created at full size so their length does not reveal the
+    /// number of tokens issued. Small lists weaken herd privacy.
+    #[must_use]
+    pub const fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Set the number of unallocated entries in each new Status List to mark
+    /// as invalid, obscuring the number of tokens actually revoked.
+    #[must_use]
+    pub const fn decoys(mut self, decoys: usize) -> Self {
+        self.decoys = decoys;
+        self
+    }
+
+    /// Set the number of bits used to hold each referenced token's status in
+    /// new Status Lists. Defaults to two bits so credentials can be both
+    /// revoked and suspended.
+    #[must_use]
+    pub const fn bits(mut self, bits: BitsPerToken) -> Self {
+        self.bits = bits;
+        self
+    }
+
+    /// Set the time, in seconds, Status List Tokens published for new Status
+    /// Lists are valid for (`exp`). Defaults to [`TOKEN_LIFETIME`].
+    #[must_use]
+    pub const fn lifetime(mut self, lifetime: i64) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    /// Set the time, in seconds, consumers may cache Status List Tokens
+    /// published for new Status Lists (`ttl`). Defaults to [`TOKEN_TTL`].
+    #[must_use]
+    pub const fn ttl(mut self, ttl: usize) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Set the time, in seconds, superseded Status List Token versions are
+    /// kept in the history of new Status Lists. Defaults to
+    /// [`HISTORY_RETENTION`].
+    #[must_use]
+    pub const fn retention(mut self, retention: i64) -> Self {
+        self.retention = retention;
+        self
+    }
+
+    /// Allocate an unused Status List entry, returning the claim to use in
+    /// the referenced token/credential.
+    ///
+    /// A new Status List is created, and its Status List Token published,
+    /// when all existing lists are full.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if allocation state cannot be retrieved or saved, or
+    /// if a new Status List Token cannot be signed.
+    pub async fn allocate<P>(&self, owner: &str, provider: &P) -> Result<StatusClaim>
+    where
+        P: StatusStore + Signature + CwtEncoder + BitstringEncoder,
+    {
+        let _guard = StatusStore::lock(provider, owner).await.context("locking status lists")?;
+
+        // the manager's lists are identified as `{base_uri}/{n}`
+        let prefix = format!("{}/", self.base_uri);
+        let shards: Vec<(usize, StatusListRecord)> = StatusStore::lists(provider, owner)
+            .await
+            .context("retrieving status lists")?
+            .into_iter()
+            .filter_map(|r| Some((r.uri.strip_prefix(&prefix)?.parse().ok()?, r)))
+            .collect();
+
+        let last_shard = shards.iter().map(|(n, _)| *n).max().unwrap_or_default();
+        let (mut record, is_new) = match shards.into_iter().find(|(_, r)| !r.is_full()) {
+            Some((_, record)) => (record, false),
+            None => {
+                let uri = format!("{prefix}{}", last_shard + 1);
+                let mut record = StatusListRecord::new(uri, self.capacity, self.bits)?;
+                record.lifetime = Some(self.lifetime);
+                record.ttl = Some(self.ttl);
+                record.retention = Some(self.retention);
+                record.add_decoys(self.decoys)?;
+                (record, true)
+            }
+        };
+
+        let claim = record.allocate()?;
+        StatusStore::put_list(provider, owner, &record).await.context("saving status list")?;
+
+        // publish an initial token for new lists
+        if is_new {
+            record.publish(owner, provider).await?;
+        }
+
+        Ok(claim)
+    }
+}
+
+/// Re-sign every Status List Token owned by the issuer with a fresh `iat`,
+/// `exp`, and `ttl`, returning the URIs of the refreshed lists.
+///
+/// Intended to be called on a schedule, more frequently than the lists'
+/// token lifetime, so tokens do not expire between status changes.
+///
+/// # Errors
+///
+/// Returns an error if the issuer's Status Lists cannot be retrieved or a
+/// token cannot be signed or saved.
+pub async fn refresh_status_lists<P>(issuer: &str, provider: &P) -> Result<Vec<String>>
+where
+    P: StatusStore + Signature + CwtEncoder + BitstringEncoder,
+{
+    let _guard = StatusStore::lock(provider, issuer).await.context("locking status lists")?;
+    let records = StatusStore::lists(provider, issuer).await.context("retrieving status lists")?;
+
+    let mut refreshed = vec![];
+    for record in records {
+        record.publish(issuer, provider).await?;
+        refreshed.push(record.uri);
+    }
+    Ok(refreshed)
+}
+
+/// Issuer-side record of a managed Status List and the entries allocated
+/// from it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StatusListRecord {
+    /// The URI of the Status List Token (the token's `sub` claim).
+    pub uri: String,
+
+    /// The number of referenced tokens the list can hold.
+    pub capacity: usize,
+
+    /// The number of entries allocated so far, including decoys.
+    pub allocated: usize,
+
+    /// Allocated entries as a base64url-encoded, compressed bitmap.
+    pub allocation: String,
+
+    /// The status list.
+    pub status_list: StatusList,
+
+    /// The time, in seconds, published Status List Tokens are valid for.
+    /// Defaults to [`TOKEN_LIFETIME`] when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lifetime: Option<i64>,
+
+    /// The time, in seconds, consumers may cache published Status List
+    /// Tokens. Defaults to [`TOKEN_TTL`] when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl: Option<usize>,
+
+    /// The time, in seconds, superseded Status List Token versions are kept
+    /// in the list's history. Defaults to [`HISTORY_RETENTION`] when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention: Option<i64>,
+}
+
+impl StatusListRecord {
+    /// Create a new, empty record for the Status List identified by `uri`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Zlib compression fails.
+    pub fn new(uri: impl Into<String>, capacity: usize, bits: BitsPerToken) -> Result<Self> {
+        Ok(Self {
+            uri: uri.into(),
+            capacity,
+            allocated: 0,
+            allocation: deflate(&vec![u8::MIN; capacity.div_ceil(8)])?,
+            status_list: StatusList::with_capacity(capacity, bits)?,
+            lifetime: None,
+            ttl: None,
+            retention: None,
+        })
+    }
+
+    /// Whether all entries in the list have been allocated.
+    #[must_use]
+    pub const fn is_full(&self) -> bool {
+        self.allocated >= self.capacity
+    }
+
+    /// Mark `count` randomly chosen, unallocated entries as invalid. Decoy
+    /// entries are marked as allocated so they are never assigned to a
+    /// referenced token, and count towards the list's capacity.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Zlib (de)compression fails.
+    pub fn add_decoys(&mut self, count: usize) -> Result<()> {
+        let mut bitmap = inflate(&self.allocation)?;
+        let bitslice = &mut bitmap.view_bits_mut::<Lsb0>()[..self.capacity];
+
+        let mut unused = bitslice.iter_zeros().collect::<Vec<_>>();
+        fastrand::shuffle(&mut unused);
+        unused.truncate(count);
+        for idx in &unused {
+            bitslice.set(*idx, true);
+        }
+
+        self.status_list.set_statuses(&unused, &StatusType::Invalid)?;
+        self.allocation = deflate(&bitmap)?;
+        self.allocated += unused.len();
+        Ok(())
+    }
+
+    /// Allocate a random, unused entry from the list. Unused entries are
+    /// always valid as decoys are never allocated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the list is full or if the Zlib (de)compression
+    /// fails.
+    pub fn allocate(&mut self) -> Result<StatusClaim> {
+        if self.is_full() {
+            return Err(anyhow!("status list {} is full", self.uri));
+        }
+
+        let mut bitmap = inflate(&self.allocation)?;
+        let bitslice = &mut bitmap.view_bits_mut::<Lsb0>()[..self.capacity];
+
+        // pick the n-th unused entry so allocation always succeeds
+        let nth = fastrand::usize(..self.capacity - self.allocated);
+        let Some(idx) = bitslice.iter_zeros().nth(nth) else {
+            return Err(anyhow!("status list {} is full", self.uri));
+        };
+        bitslice.set(idx, true);
+
+        self.allocation = deflate(&bitmap)?;
+        self.allocated += 1;
+
+        Ok(StatusClaim {
+            status_list: StatusListEntry {
+                idx,
+                uri: self.uri.clone(),
+            },
+        })
+    }
+
+    /// Sign the list's current state as a Status List Token and save it using
+    /// the `StatusStore` provider. A CWT-formatted token is also saved when
+    /// the provider's `CwtEncoder` supports it, as are W3C Bitstring Status
+    /// List Credentials when the provider's `BitstringEncoder` supports them.
+    ///
+    /// Tokens are issued with the record's `lifetime` and `ttl`. Each signed
+    /// token is also kept as a version in the list's history so the list can
+    /// be resolved at a point in time, with versions superseded before the
+    /// record's `retention` period removed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the token lifetime or retention is out of range,
+    /// or if the token cannot be signed or saved.
+    pub async fn publish<P>(&self, owner: &str, provider: &P) -> Result<()>
+    where
+        P: StatusStore + Signature + CwtEncoder + BitstringEncoder,
+    {
+        let lifetime = self.lifetime.unwrap_or(TOKEN_LIFETIME);
+        let Some(expiry) =
+            TimeDelta::try_seconds(lifetime).and_then(|delta| Utc::now().checked_add_signed(delta))
+        else {
+            return Err(anyhow!("token lifetime {lifetime} is out of range"));
+        };
+
+        let builder = TokenBuilder::new()
+            .status_list(self.status_list.clone())
+            .uri(&self.uri)
+            .issuer(owner)
+            .expiry(expiry)
+            .ttl(self.ttl.unwrap_or(TOKEN_TTL))
+            .signer(provider);
+        let claims = builder.claims();
+
+        let token = builder.build().await.context("building status list token")?;
+        StatusStore::put(provider, owner, &self.uri, &token)
+            .await
+            .context("saving status token")?;
+
+        let cwt = CwtEncoder::encode_cwt(provider, &claims).await?;
+        if let Some(cwt) = &cwt {
+            StatusStore::put_cwt(provider, owner, &self.uri, cwt)
+                .await
+                .context("saving status token")?;
+        }
+
+        for purpose in [StatusPurpose::Revocation, StatusPurpose::Suspension] {
+            let bitstring = BitstringStatusList::new(&self.uri, &self.status_list, purpose)?;
+            if let Some(credential) =
+                BitstringEncoder::encode_bitstring(provider, owner, &bitstring).await?
+            {
+                StatusStore::put_bitstring(provider, owner, bitstring.credential_id(), &credential)
+                    .await
+                    .context("saving status list credential")?;
+            }
+        }
+
+        let version = StatusTokenVersion {
+            uri: self.uri.clone(),
+            iat: claims.iat,
+            jwt: token,
+            cwt,
+        };
+        StatusStore::put_version(provider, owner, &version)
+            .await
+            .context("saving status token version")?;
+
+        // drop versions superseded before the retention period
+        let retention = self.retention.unwrap_or(HISTORY_RETENTION);
+        let Some(before) = TimeDelta::try_seconds(retention)
+            .and_then(|delta| version.iat.checked_sub_signed(delta))
+        else {
+            return Err(anyhow!("history retention {retention} is out of range"));
+        };
+        StatusStore::prune_versions(provider, owner, &self.uri, before)
+            .await
+            .context("pruning status token versions")
+    }
+}
+
+/// A previously signed Status List Token, kept so the status of referenced
+/// tokens can be resolved at a point in time.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct StatusTokenVersion {
+    /// The URI of the Status List Token.
+    pub uri: String,
+
+    /// The time at which the Status List Token was issued. The token is
+    /// valid from this time until the next version is issued.
+    pub iat: DateTime<Utc>,
+
+    /// The Status List Token in JWT format.
+    pub jwt: String,
+
+    /// The Status List Token in CWT format, when published.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwt: Option<Vec<u8>>,
+}
@@ -1,19 +1,36 @@
 //! # Status Store Provider
 
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
 use std::future::Future;
 use std::str::FromStr;
+use std::sync::{Arc, LazyLock, Mutex};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
 use credibil_core::datastore::Datastore;
 
+use crate::manager::{StatusListRecord, StatusTokenVersion};
+use crate::{BitstringStatusList, CredentialSelector, IssuedCredential, StatusListClaims};
+
 /// Verifier Provider trait.
-pub trait Provider: StatusStore + Clone {}
+pub trait Provider: StatusStore + Registry + Clone {}
 
 const STATUSTOKEN: &str = "STATUSTOKEN";
+const STATUSCWT: &str = "STATUSCWT";
+const STATUSBITSTRING: &str = "STATUSBITSTRING";
+const STATUSLIST: &str = "STATUSLIST";
+const STATUSHISTORY: &str = "STATUSHISTORY";
+const ISSUED: &str = "ISSUED";
+
+// Per-owner locks used to serialize updates to managed Status Lists.
+static LOCKS: LazyLock<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
 /// A blanket implementation for `Provider` trait so that any type implementing
 /// the required super traits is considered a `Provider`.
-impl<T> Provider for T where T: StatusStore + Clone {}
+impl<T> Provider for T where T: StatusStore + Registry + Clone {}
 
 /// `StatusStore` is used to store and retrieve Status Tokens.
 pub trait StatusStore: Send + Sync {
@@ -22,12 +39,150 @@ pub trait StatusStore: Send + Sync {
 
     /// Retrieve the specified Status Token.
     fn get(&self, owner: &str, id: &str) -> impl Future<Output = Result<Option<String>>> + Send;
+
+    /// Store the CWT-formatted Status Token using the provided key.
+    fn put_cwt(
+        &self, owner: &str, id: &str, token: &[u8],
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Retrieve the specified CWT-formatted Status Token.
+    fn get_cwt(
+        &self, owner: &str, id: &str,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send;
+
+    /// Store the Bitstring Status List Credential using the provided key.
+    fn put_bitstring(
+        &self, owner: &str, id: &str, credential: &str,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Retrieve the specified Bitstring Status List Credential.
+    fn get_bitstring(
+        &self, owner: &str, id: &str,
+    ) -> impl Future<Output = Result<Option<String>>> + Send;
+
+    /// Add a signed Status List Token to the list's version history.
+    fn put_version(
+        &self, owner: &str, version: &StatusTokenVersion,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Retrieve the version of the specified Status List Token that was
+    /// valid at `time`. That is, the most recent version issued at or before
+    /// `time`.
+    fn get_version(
+        &self, owner: &str, id: &str, time: DateTime<Utc>,
+    ) -> impl Future<Output = Result<Option<StatusTokenVersion>>> + Send;
+
+    /// Remove versions of the specified Status List Token that were
+    /// superseded by a newer version before `before`. Versions still valid
+    /// at `before` are kept.
+    fn prune_versions(
+        &self, owner: &str, id: &str, before: DateTime<Utc>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Store the allocation record for a managed Status List.
+    fn put_list(
+        &self, owner: &str, record: &StatusListRecord,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Retrieve the allocation record for the specified Status List.
+    fn get_list(
+        &self, owner: &str, uri: &str,
+    ) -> impl Future<Output = Result<Option<StatusListRecord>>> + Send;
+
+    /// Retrieve allocation records for all Status Lists managed by the owner.
+    fn lists(&self, owner: &str) -> impl Future<Output = Result<Vec<StatusListRecord>>> + Send;
+
+    /// Acquire exclusive access to the owner's managed Status Lists, held
+    /// until the returned guard is dropped. Used to serialize the
+    /// read-modify-write of allocation records and statuses.
+    ///
+    /// The default implementation only serializes access within the current
+    /// process. Implementations sharing a store across processes should
+    /// override it to use a distributed lock.
+    fn lock(&self, owner: &str) -> impl Future<Output = Result<StatusListGuard>> + Send {
+        let lock = LOCKS
+            .lock()
+            .map(|mut locks| locks.entry(owner.to_string()).or_default().clone())
+            .map_err(|_| anyhow!("status list locks are poisoned"));
+        async move { Ok(StatusListGuard::new(lock?.lock_owned().await)) }
+    }
+}
+
+/// Exclusive access to an owner's managed Status Lists, released when
+/// dropped.
+pub struct StatusListGuard {
+    _guard: Box<dyn Any + Send + Sync>,
+}
+
+impl StatusListGuard {
+    /// Create a guard from the underlying lock's guard.
+    #[must_use]
+    pub fn new(guard: impl Any + Send + Sync) -> Self {
+        Self {
+            _guard: Box::new(guard),
+        }
+    }
+}
+
+impl Debug for StatusListGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StatusListGuard")
+    }
+}
+
+/// `Registry` is used by issuers to record credentials issued with a Status
+/// List entry so their status can later be changed.
+pub trait Registry: Send + Sync {
+    /// Record an issued credential.
+    fn register(
+        &self, owner: &str, issued: &IssuedCredential,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Retrieve the credentials issued by the owner that match `selector`.
+    fn issued(
+        &self, owner: &str, selector: &CredentialSelector,
+    ) -> impl Future<Output = Result<Vec<IssuedCredential>>> + Send;
+}
+
+/// `CwtEncoder` is used by issuers to sign Status List Tokens in CWT format
+/// (`application/statuslist+cwt`) for ISO mdoc credentials. Implementations
+/// will typically use `credibil_vdc::mso_mdoc::StatusListCwtBuilder`.
+pub trait CwtEncoder: Send + Sync {
+    /// Sign the Status List claims as a CWT, returning `None` when CWT Status
+    /// List Tokens are not published.
+    fn encode_cwt(
+        &self, claims: &StatusListClaims,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>>> + Send {
+        let _ = claims;
+        async { Ok(None) }
+    }
+}
+
+/// `BitstringEncoder` is used by issuers to sign W3C Bitstring Status List
+/// Credentials for `jwt_vc_json` credentials. Implementations will typically
+/// use `credibil_vdc::w3c_vc::BitstringCredentialBuilder`.
+pub trait BitstringEncoder: Send + Sync {
+    /// Sign the Bitstring Status List as a `BitstringStatusListCredential`
+    /// issued by `issuer`, returning `None` when Bitstring Status Lists are
+    /// not published.
+    fn encode_bitstring(
+        &self, issuer: &str, status_list: &BitstringStatusList,
+    ) -> impl Future<Output = Result<Option<String>>> + Send {
+        let _ = (issuer, status_list);
+        async { Ok(None) }
+    }
 }
 
 /// `StatusToken` is used to store and retrieve Status Tokens.
 pub trait StatusToken: Send + Sync {
     /// Fetch the specified status list.
     fn fetch(&self, uri: &str) -> impl Future<Output = Result<String>> + Send;
+
+    /// Fetch the specified status list in CWT format.
+    fn fetch_cwt(&self, uri: &str) -> impl Future<Output = Result<Vec<u8>>> + Send;
+
+    /// Fetch the specified Bitstring Status List Credential.
+    fn fetch_bitstring(&self, uri: &str) -> impl Future<Output = Result<String>> + Send;
 }
 
 impl<T: Datastore> StatusStore for T {
@@ -44,23 +199,197 @@ impl<T: Datastore> StatusStore for T {
         };
         Ok(Some(serde_json::from_slice(&data)?))
     }
+
+    async fn put_cwt(&self, owner: &str, id: &str, token: &[u8]) -> Result<()> {
+        Datastore::delete(self, owner, STATUSCWT, id).await?;
+        Datastore::put(self, owner, STATUSCWT, id, token).await
+    }
+
+    async fn get_cwt(&self, owner: &str, id: &str) -> Result<Option<Vec<u8>>> {
+        Datastore::get(self, owner, STATUSCWT, id).await
+    }
+
+    async fn put_bitstring(&self, owner: &str, id: &str, credential: &str) -> Result<()> {
+        Datastore::delete(self, owner, STATUSBITSTRING, id).await?;
+        Datastore::put(self, owner, STATUSBITSTRING, id, credential.as_bytes()).await
+    }
+
+    async fn get_bitstring(&self, owner: &str, id: &str) -> Result<Option<String>> {
+        let Some(data) = Datastore::get(self, owner, STATUSBITSTRING, id).await? else {
+            return Ok(None);
+        };
+        Ok(Some(String::from_utf8(data)?))
+    }
+
+    // versions are kept in a partition per Status List, keyed by `iat`
+    async fn put_version(&self, owner: &str, version: &StatusTokenVersion) -> Result<()> {
+        let partition = history_partition(&version.uri);
+        let key = version.iat.timestamp_micros().to_string();
+        let data = serde_json::to_vec(version)?;
+        Datastore::delete(self, owner, &partition, &key).await?;
+        Datastore::put(self, owner, &partition, &key, &data).await
+    }
+
+    async fn get_version(
+        &self, owner: &str, id: &str, time: DateTime<Utc>,
+    ) -> Result<Option<StatusTokenVersion>> {
+        let all = Datastore::get_all(self, owner, &history_partition(id)).await?;
+        let mut latest: Option<StatusTokenVersion> = None;
+        for (_, data) in all {
+            let version: StatusTokenVersion = serde_json::from_slice(&data)?;
+            // `time` has a precision of seconds
+            if version.uri != id || version.iat.timestamp() > time.timestamp() {
+                continue;
+            }
+            if latest.as_ref().is_none_or(|l| version.iat > l.iat) {
+                latest = Some(version);
+            }
+        }
+        Ok(latest)
+    }
+
+    async fn prune_versions(&self, owner: &str, id: &str, before: DateTime<Utc>) -> Result<()> {
+        let partition = history_partition(id);
+        let all = Datastore::get_all(self, owner, &partition).await?;
+
+        let mut versions = all
+            .iter()
+            .map(|(_, data)| serde_json::from_slice::<StatusTokenVersion>(data))
+            .collect::<Result<Vec<_>, _>>()?;
+        versions.sort_unstable_by_key(|v| v.iat);
+
+        // a version is superseded when the next version was issued before the
+        // cut-off
+        for pair in versions.windows(2) {
+            if pair[1].iat >= before {
+                break;
+            }
+            let key = pair[0].iat.timestamp_micros().to_string();
+            Datastore::delete(self, owner, &partition, &key).await?;
+        }
+        Ok(())
+    }
+
+    async fn put_list(&self, owner: &str, record: &StatusListRecord) -> Result<()> {
+        let data = serde_json::to_vec(record)?;
+        Datastore::delete(self, owner, STATUSLIST, &record.uri).await?;
+        Datastore::put(self, owner, STATUSLIST, &record.uri, &data).await
+    }
+
+    async fn get_list(&self, owner: &str, uri: &str) -> Result<Option<StatusListRecord>> {
+        let Some(data) = Datastore::get(self, owner, STATUSLIST, uri).await? else {
+            return Ok(None);
+        };
+        Ok(Some(serde_json::from_slice(&data)?))
+    }
+
+    async fn lists(&self, owner: &str) -> Result<Vec<StatusListRecord>> {
+        let all = Datastore::get_all(self, owner, STATUSLIST).await?;
+        all.iter().map(|(_, data)| Ok(serde_json::from_slice(data)?)).collect()
+    }
+}
+
+impl<T: Datastore> Registry for T {
+    // Issued credentials are keyed by Status List entry and also saved to a
+    // partition for each selectable key so selection does not scan the
+    // whole registry.
+    async fn register(&self, owner: &str, issued: &IssuedCredential) -> Result<()> {
+        let key = issued_key(&issued.status.uri, issued.status.idx);
+        let data = serde_json::to_vec(issued)?;
+
+        let mut partitions = vec![
+            ISSUED.to_string(),
+            issued_partition(&CredentialSelector::CredentialConfigurationId(
+                issued.credential_configuration_id.clone(),
+            )),
+            issued_partition(&CredentialSelector::SubjectId(issued.subject_id.clone())),
+        ];
+        if let Some(id) = &issued.notification_id {
+            partitions.push(issued_partition(&CredentialSelector::NotificationId(id.clone())));
+        }
+        for partition in partitions {
+            Datastore::delete(self, owner, &partition, &key).await?;
+            Datastore::put(self, owner, &partition, &key, &data).await?;
+        }
+        Ok(())
+    }
+
+    async fn issued(
+        &self, owner: &str, selector: &CredentialSelector,
+    ) -> Result<Vec<IssuedCredential>> {
+        let all = if let CredentialSelector::StatusListEntry(entry) = selector {
+            let key = issued_key(&entry.uri, entry.idx);
+            Datastore::get(self, owner, ISSUED, &key).await?.into_iter().collect::<Vec<_>>()
+        } else {
+            let all = Datastore::get_all(self, owner, &issued_partition(selector)).await?;
+            all.into_iter().map(|(_, data)| data).collect()
+        };
+
+        let mut issued = vec![];
+        for data in all {
+            let credential: IssuedCredential = serde_json::from_slice(&data)?;
+            if selector.matches(&credential) {
+                issued.push(credential);
+            }
+        }
+        Ok(issued)
+    }
+}
+
+// The registry key for the credential issued with the Status List entry.
+fn issued_key(uri: &str, idx: usize) -> String {
+    format!("{uri}#{idx}")
+}
+
+// The registry partition holding credentials selected by `selector`.
+fn issued_partition(selector: &CredentialSelector) -> String {
+    match selector {
+        CredentialSelector::CredentialConfigurationId(id) => {
+            format!("{ISSUED}#credential_configuration_id#{id}")
+        }
+        CredentialSelector::SubjectId(id) => format!("{ISSUED}#subject_id#{id}"),
+        CredentialSelector::NotificationId(id) => format!("{ISSUED}#notification_id#{id}"),
+        CredentialSelector::StatusListEntry(_) => ISSUED.to_string(),
+    }
 }
 
 impl<T: Datastore> StatusToken for T {
     async fn fetch(&self, uri: &str) -> Result<String> {
-        let http_uri =
-            http::Uri::from_str(uri).map_err(|_| anyhow::anyhow!("invalid status token URI"))?;
-        let Some(scheme) = http_uri.scheme_str() else {
-            return Err(anyhow::anyhow!("invalid scheme"));
-        };
-        let Some(authority) = http_uri.authority() else {
-            return Err(anyhow::anyhow!("invalid  authority"));
+        let Some(data) = Datastore::get(self, &owner(uri)?, STATUSTOKEN, uri).await? else {
+            return Err(anyhow::anyhow!("could not find status token"));
         };
-        let owner = format!("{scheme}://{authority}");
+        Ok(serde_json::from_slice(&data)?)
+    }
 
-        let Some(data) = Datastore::get(self, &owner, STATUSTOKEN, uri).await? else {
+    async fn fetch_cwt(&self, uri: &str) -> Result<Vec<u8>> {
+        let Some(data) = Datastore::get(self, &owner(uri)?, STATUSCWT, uri).await? else {
             return Err(anyhow::anyhow!("could not find status token"));
         };
-        Ok(serde_json::from_slice(&data)?)
+        Ok(data)
+    }
+
+    async fn fetch_bitstring(&self, uri: &str) -> Result<String> {
+        let Some(data) = Datastore::get(self, &owner(uri)?, STATUSBITSTRING, uri).await? else {
+            return Err(anyhow::anyhow!("could not find status list credential"));
+        };
+        Ok(String::from_utf8(data)?)
     }
 }
+
+// The partition holding the version history of the Status List Token.
+fn history_partition(id: &str) -> String {
+    format!("{STATUSHISTORY}#{id}")
+}
+
+// Derive the Status List Token owner (`scheme://authority`) from its URI.
+fn owner(uri: &str) -> Result<String> {
+    let http_uri =
+        http::Uri::from_str(uri).map_err(|_| anyhow::anyhow!("invalid status token URI"))?;
+    let Some(scheme) = http_uri.scheme_str() else {
+        return Err(anyhow::anyhow!("invalid scheme"));
+    };
+    let Some(authority) = http_uri.authority() else {
+        return Err(anyhow::anyhow!("invalid  authority"));
+    };
+    Ok(format!("{scheme}://{authority}"))
+}
@@ -1,16 +1,41 @@
 //! Token Status for Verifiers
 
-use std::io::Read;
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
 
-use anyhow::{Result, anyhow};
-use base64ct::{Base64UrlUnpadded, Encoding};
+use anyhow::{Context, Result, anyhow};
+use bitvec::field::BitField;
 use bitvec::order::Lsb0;
 use bitvec::view::BitView;
-use flate2::read::ZlibDecoder;
+use chrono::{DateTime, TimeDelta, Utc};
+use credibil_jose::{Jwt, decode_jws};
+use credibil_proof::{Resolver, resolve_jwk};
 
-use crate::StatusList;
+use crate::issue::{bit_range, inflate};
+use crate::provider::StatusToken;
+use crate::{BitsPerToken, StatusClaim, StatusList, StatusListClaims, StatusType};
+
+/// The default time, in seconds, a Status List is cached for when its token
+/// does not specify a `ttl`.
+pub const DEFAULT_TTL: i64 = 300;
+
+/// The default maximum number of Status Lists held in a `StatusChecker`'s
+/// cache.
+pub const DEFAULT_CACHE_SIZE: usize = 1_000;
+
+static SHARED: OnceLock<StatusChecker> = OnceLock::new();
 
 impl StatusList {
+    /// Get the status of the referenced token at the given index.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Zlib decompression fails or if the index is
+    /// out of bounds.
+    pub fn status(&self, idx: usize) -> Result<StatusType> {
+        status_at(&inflate(&self.lst)?, self.bits, idx)
+    }
+
     /// Check if the status list contains a valid status for the given index.
     ///
     /// # Errors
@@ -18,13 +43,248 @@ impl StatusList {
     /// Returns an error if the Zlib decompression fails or if the index is
     /// out of bounds.
     pub fn is_valid(&self, idx: usize) -> Result<bool> {
-        let deflated = Base64UrlUnpadded::decode_vec(&self.lst)
-            .map_err(|_| anyhow!("Invalid base64url-encoded status list"))?;
-        let mut decoder = ZlibDecoder::new(deflated.as_slice());
-        let mut inflated = Vec::new();
-        decoder.read_to_end(&mut inflated)?;
+        Ok(self.status(idx)? == StatusType::Valid)
+    }
+}
+
+// Get the status at `idx` in an inflated status list.
+pub(crate) fn status_at(inflated: &[u8], bits: BitsPerToken, idx: usize) -> Result<StatusType> {
+    let width = bits.width();
+    let bitslice = inflated.view_bits::<Lsb0>();
+    let Some(entry) = bit_range(idx, width).and_then(|range| bitslice.get(range)) else {
+        return Err(anyhow!("index {idx} is out of bounds"));
+    };
+    Ok(StatusType::from(entry.load_le::<u8>()))
+}
+
+/// Checks the status of referenced tokens (credentials) for verifiers.
+///
+/// Status List Tokens are fetched using the `StatusToken` provider and
+/// validated before use. Decoded lists are cached in memory until the token's
+/// `ttl` or `exp` elapses, whichever comes first. When the cache is full, the
+/// list closest to expiry is evicted.
+#[derive(Debug)]
+pub struct StatusChecker {
+    cache: RwLock<HashMap<String, CachedList>>,
+    default_ttl: TimeDelta,
+    cache_size: usize,
+}
+
+// A validated, inflated Status List.
+#[derive(Debug)]
+struct CachedList {
+    iss: String,
+    bits: BitsPerToken,
+    inflated: Vec<u8>,
+    expires: DateTime<Utc>,
+}
+
+impl Default for StatusChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatusChecker {
+    /// Create a new `StatusChecker` with an empty cache.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            cache: RwLock::new(HashMap::new()),
+            default_ttl: TimeDelta::seconds(DEFAULT_TTL),
+            cache_size: DEFAULT_CACHE_SIZE,
+        }
+    }
+
+    /// The process-wide `StatusChecker` used when verifying presentations.
+    ///
+    /// Unless a checker has been installed using [`StatusChecker::install`],
+    /// a checker with default settings is used.
+    #[must_use]
+    pub fn shared() -> &'static Self {
+        SHARED.get_or_init(Self::new)
+    }
+
+    /// Install this checker as the process-wide shared `StatusChecker`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the checker if the shared checker has already been installed
+    /// or used.
+    pub fn install(self) -> Result<(), Self> {
+        SHARED.set(self)
+    }
+
+    /// Set how long Status Lists are cached for when their token does not
+    /// specify a `ttl`.
+    #[must_use]
+    pub const fn default_ttl(mut self, default_ttl: TimeDelta) -> Self {
+        self.default_ttl = default_ttl;
+        self
+    }
 
-        let bitslice = inflated.view_bits::<Lsb0>();
-        Ok(bitslice.get(idx).is_some_and(|x| *x))
+    /// Set the maximum number of Status Lists held in the cache.
+    #[must_use]
+    pub const fn cache_size(mut self, cache_size: usize) -> Self {
+        self.cache_size = cache_size;
+        self
     }
+
+    /// Get the status of the referenced token (credential) issued by
+    /// `issuer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the Status List Token cannot be fetched, is not
+    /// valid for the referenced token, or if the index is out of bounds.
+    pub async fn status<R>(
+        &self, claim: &StatusClaim, issuer: &str, resolver: &R,
+    ) -> Result<StatusType>
+    where
+        R: Resolver + StatusToken,
+    {
+        let entry = &claim.status_list;
+
+        if let Some(status) = self.cached(&entry.uri, issuer, entry.idx) {
+            return status;
+        }
+
+        let list = self.fetch(&entry.uri, issuer, resolver).await?;
+        let status = status_at(&list.inflated, list.bits, entry.idx);
+        self.insert(entry.uri.clone(), list)?;
+
+        status
+    }
+
+    /// Check the referenced token (credential) issued by `issuer` is valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the referenced token has been revoked or
+    /// suspended, or if its status cannot be determined.
+    pub async fn check<R>(&self, claim: &StatusClaim, issuer: &str, resolver: &R) -> Result<()>
+    where
+        R: Resolver + StatusToken,
+    {
+        match self.status(claim, issuer, resolver).await? {
+            StatusType::Valid => Ok(()),
+            StatusType::Invalid => Err(anyhow!("credential has been revoked")),
+            StatusType::Suspended => Err(anyhow!("credential is suspended")),
+            StatusType::ApplicationSpecific(value) => {
+                Err(anyhow!("credential has application-specific status {value:#04x}"))
+            }
+        }
+    }
+
+    /// Remove all cached Status Lists.
+    pub fn clear(&self) {
+        if let Ok(mut cache) = self.cache.write() {
+            cache.clear();
+        }
+    }
+
+    /// The number of Status Lists currently cached.
+    #[must_use]
+    pub fn cached_lists(&self) -> usize {
+        self.cache.read().map_or(0, |cache| cache.len())
+    }
+
+    // Look up the status using an unexpired, cached list for the issuer.
+    fn cached(&self, uri: &str, issuer: &str, idx: usize) -> Option<Result<StatusType>> {
+        let cache = self.cache.read().ok()?;
+        let list = cache.get(uri)?;
+        if list.expires <= Utc::now() || list.iss != issuer {
+            return None;
+        }
+        Some(status_at(&list.inflated, list.bits, idx))
+    }
+
+    // Cache the list, first evicting expired lists and then, if the cache is
+    // still full, the list closest to expiry.
+    fn insert(&self, uri: String, list: CachedList) -> Result<()> {
+        if self.cache_size == 0 {
+            return Ok(());
+        }
+        let mut cache = self.cache.write().map_err(|_| anyhow!("status list cache is poisoned"))?;
+
+        if !cache.contains_key(&uri) && cache.len() >= self.cache_size {
+            let now = Utc::now();
+            cache.retain(|_, cached| cached.expires > now);
+        }
+        while !cache.contains_key(&uri) && cache.len() >= self.cache_size {
+            let Some(oldest) =
+                cache.iter().min_by_key(|(_, cached)| cached.expires).map(|(k, _)| k.clone())
+            else {
+                break;
+            };
+            cache.remove(&oldest);
+        }
+
+        cache.insert(uri, list);
+        Ok(())
+    }
+
+    // Fetch and validate the Status List Token.
+    async fn fetch<R>(&self, uri: &str, issuer: &str, resolver: &R) -> Result<CachedList>
+    where
+        R: Resolver + StatusToken,
+    {
+        let token =
+            StatusToken::fetch(resolver, uri).await.context("fetching status list token")?;
+        let jwk = async |kid: String| resolve_jwk(&kid, resolver).await;
+        let jwt: Jwt<StatusListClaims> =
+            decode_jws(&token, jwk).await.context("verifying status list token")?;
+
+        if jwt.header.typ != "statuslist+jwt" {
+            return Err(anyhow!("invalid status list token type"));
+        }
+
+        let claims = jwt.claims;
+        if claims.sub != uri {
+            return Err(anyhow!("status list token `sub` does not match {uri}"));
+        }
+        let Some(iss) = claims.iss else {
+            return Err(anyhow!("status list token is missing `iss`"));
+        };
+        if iss != issuer {
+            return Err(anyhow!("status list token `iss` does not match {issuer}"));
+        }
+
+        let now = Utc::now();
+        if claims.exp.is_some_and(|exp| exp <= now) {
+            return Err(anyhow!("status list token has expired"));
+        }
+
+        // cache until `ttl` or `exp`, whichever is sooner
+        let ttl = claims
+            .ttl
+            .and_then(|ttl| i64::try_from(ttl).ok())
+            .and_then(TimeDelta::try_seconds)
+            .unwrap_or(self.default_ttl);
+        let mut expires = now.checked_add_signed(ttl).unwrap_or(DateTime::<Utc>::MAX_UTC);
+        if let Some(exp) = claims.exp {
+            expires = expires.min(exp);
+        }
+
+        Ok(CachedList {
+            iss,
+            bits: claims.status_list.bits,
+            inflated: inflate(&claims.status_list.lst)?,
+            expires,
+        })
+    }
+}
+
+/// Check the status of a referenced token (credential) issued by `issuer`
+/// using the shared `StatusChecker`.
+///
+/// # Errors
+///
+/// Returns an error if the referenced token has been revoked or suspended,
+/// or if its status cannot be determined.
+pub async fn check_status<R>(claim: &StatusClaim, issuer: &str, resolver: &R) -> Result<()>
+where
+    R: Resolver + StatusToken,
+{
+    StatusChecker::shared().check(claim, issuer, resolver).await
 }
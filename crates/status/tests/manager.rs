@@ -0,0 +1,152 @@
+//! Status List Manager Tests
+
+use chrono::{TimeDelta, Utc};
+use credibil_jose::{Jwt, decode_jws};
+use credibil_proof::resolve_jwk;
+use credibil_status::{
+    StatusListClaims, StatusListManager, StatusListRequest, StatusListResponse, StatusStore,
+    StatusTokenVersion, TOKEN_TTL, refresh_status_lists,
+};
+use test_utils::issuer::Issuer;
+
+const ISSUER: &str = "http://localhost:8080";
+
+// Should re-sign all of an issuer's Status List Tokens with a fresh `exp` and
+// `ttl`.
+#[tokio::test]
+async fn refresh() {
+    let provider = Issuer::new(ISSUER).await;
+
+    let status_claim = StatusListManager::new(format!("{ISSUER}/refresh"))
+        .capacity(16)
+        .decoys(0)
+        .allocate(ISSUER, &provider)
+        .await
+        .expect("should allocate");
+    let uri = status_claim.status_list.uri;
+
+    let refreshed = refresh_status_lists(ISSUER, &provider).await.expect("should refresh");
+    assert!(refreshed.contains(&uri));
+
+    let token = status_token(&provider, &uri).await;
+    assert_eq!(token.claims.sub, uri);
+    assert_eq!(token.claims.ttl, Some(TOKEN_TTL));
+    assert!(token.claims.exp.expect("should have exp") > Utc::now());
+}
+
+// Should publish, and refresh, Status List Tokens using the manager's
+// configured lifetime and `ttl`.
+#[tokio::test]
+async fn lifetime() {
+    let provider = Issuer::new(ISSUER).await;
+
+    let status_claim = StatusListManager::new(format!("{ISSUER}/lifetime"))
+        .capacity(16)
+        .decoys(0)
+        .lifetime(600)
+        .ttl(60)
+        .allocate(ISSUER, &provider)
+        .await
+        .expect("should allocate");
+    let uri = status_claim.status_list.uri;
+
+    let token = status_token(&provider, &uri).await;
+    assert_eq!(token.claims.ttl, Some(60));
+    let exp = token.claims.exp.expect("should have exp");
+    assert!(exp <= Utc::now() + TimeDelta::seconds(600));
+
+    // refreshed tokens keep the list's configuration
+    refresh_status_lists(ISSUER, &provider).await.expect("should refresh");
+    let token = status_token(&provider, &uri).await;
+    assert_eq!(token.claims.ttl, Some(60));
+    assert!(token.claims.exp.expect("should have exp") <= Utc::now() + TimeDelta::seconds(600));
+}
+
+async fn status_token(provider: &Issuer, uri: &str) -> Jwt<StatusListClaims> {
+    let request = StatusListRequest {
+        id: Some(uri.to_string()),
+        time: None,
+    };
+    let response =
+        credibil_status::handle(ISSUER, request, provider).await.expect("should return token");
+    let StatusListResponse::Jwt(token) = &*response else {
+        panic!("should be a JWT");
+    };
+    let resolver = async |kid: String| resolve_jwk(&kid, provider).await;
+    decode_jws(token, resolver).await.expect("should decode")
+}
+
+// Should allocate a unique entry to each of many concurrent requests.
+#[tokio::test(flavor = "multi_thread")]
+async fn concurrent() {
+    let provider = Issuer::new(ISSUER).await;
+    let manager = StatusListManager::new(format!("{ISSUER}/concurrent")).capacity(64).decoys(0);
+
+    let mut tasks = vec![];
+    for _ in 0..32 {
+        let provider = provider.clone();
+        let manager = manager.clone();
+        tasks.push(tokio::spawn(async move { manager.allocate(ISSUER, &provider).await }));
+    }
+
+    let mut indices = vec![];
+    for task in tasks {
+        let status_claim = task.await.expect("should join").expect("should allocate");
+        indices.push(status_claim.status_list.idx);
+    }
+    indices.sort_unstable();
+    indices.dedup();
+    assert_eq!(indices.len(), 32);
+
+    let records = StatusStore::lists(&provider, ISSUER).await.expect("should get lists");
+    let record = records
+        .iter()
+        .find(|r| r.uri == format!("{ISSUER}/concurrent/1"))
+        .expect("should have list");
+    assert_eq!(record.allocated, 32);
+}
+
+// Should only allocate from the manager's own lists, ignoring lists whose URI
+// shares the manager's base URI as a prefix.
+#[tokio::test]
+async fn sibling_lists() {
+    let provider = Issuer::new(ISSUER).await;
+
+    let sibling = StatusListManager::new(format!("{ISSUER}/sibling-mdoc")).capacity(16).decoys(0);
+    let status_claim = sibling.allocate(ISSUER, &provider).await.expect("should allocate");
+    assert_eq!(status_claim.status_list.uri, format!("{ISSUER}/sibling-mdoc/1"));
+
+    let manager = StatusListManager::new(format!("{ISSUER}/sibling")).capacity(16).decoys(0);
+    let status_claim = manager.allocate(ISSUER, &provider).await.expect("should allocate");
+    assert_eq!(status_claim.status_list.uri, format!("{ISSUER}/sibling/1"));
+}
+
+// Should remove Status List Token versions superseded before the cut-off while
+// keeping the version still valid at the cut-off.
+#[tokio::test]
+async fn prune_versions() {
+    let provider = Issuer::new(ISSUER).await;
+    let uri = format!("{ISSUER}/prune/1");
+    let now = Utc::now();
+
+    for hours in [3, 2, 1] {
+        let version = StatusTokenVersion {
+            uri: uri.clone(),
+            iat: now - TimeDelta::hours(hours),
+            jwt: format!("token-{hours}"),
+            cwt: None,
+        };
+        StatusStore::put_version(&provider, ISSUER, &version).await.expect("should save version");
+    }
+
+    let before = now - TimeDelta::minutes(90);
+    StatusStore::prune_versions(&provider, ISSUER, &uri, before).await.expect("should prune");
+
+    let version =
+        |hours| StatusStore::get_version(&provider, ISSUER, &uri, now - TimeDelta::hours(hours));
+    assert!(version(3).await.expect("should get version").is_none());
+    let kept = version(2).await.expect("should get version").expect("should have version");
+    assert_eq!(kept.jwt, "token-2");
+    let latest = version(0).await.expect("should get version").expect("should have version");
+    assert_eq!(latest.jwt, "token-1");
+}
@@ -0,0 +1,105 @@
+//! Status Checker Tests
+
+use chrono::TimeDelta;
+use credibil_status::{
+    StatusChecker, StatusClaim, StatusListManager, StatusStore, StatusType, TokenBuilder,
+};
+use test_utils::issuer::Issuer;
+
+const ISSUER: &str = "http://localhost:8080";
+
+// Should use cached Status Lists until their `ttl` elapses.
+#[tokio::test]
+async fn cache() {
+    let provider = Issuer::new(ISSUER).await;
+    let checker = StatusChecker::new();
+
+    let claim = allocate(&provider, "cache", 3_600).await;
+    assert_eq!(checker.status(&claim, ISSUER, &provider).await.unwrap(), StatusType::Valid);
+
+    set_status(&provider, &claim, &StatusType::Invalid).await;
+
+    // the cached list is used until cleared
+    assert_eq!(checker.status(&claim, ISSUER, &provider).await.unwrap(), StatusType::Valid);
+    checker.clear();
+    assert_eq!(checker.status(&claim, ISSUER, &provider).await.unwrap(), StatusType::Invalid);
+}
+
+// Should re-fetch Status Lists once their `ttl` has elapsed.
+#[tokio::test]
+async fn ttl() {
+    let provider = Issuer::new(ISSUER).await;
+    let checker = StatusChecker::new().default_ttl(TimeDelta::hours(1));
+
+    let claim = allocate(&provider, "ttl", 0).await;
+    assert_eq!(checker.status(&claim, ISSUER, &provider).await.unwrap(), StatusType::Valid);
+
+    set_status(&provider, &claim, &StatusType::Suspended).await;
+    assert_eq!(checker.status(&claim, ISSUER, &provider).await.unwrap(), StatusType::Suspended);
+}
+
+// Should hold no more than the configured number of Status Lists.
+#[tokio::test]
+async fn cache_size() {
+    let provider = Issuer::new(ISSUER).await;
+    let checker = StatusChecker::new().cache_size(2);
+
+    for name in ["size-a", "size-b", "size-c"] {
+        let claim = allocate(&provider, name, 3_600).await;
+        checker.check(&claim, ISSUER, &provider).await.expect("should be valid");
+        assert!(checker.cached_lists() <= 2);
+    }
+    assert_eq!(checker.cached_lists(), 2);
+}
+
+// Should reject Status List Tokens not issued by the referenced token's
+// issuer, including tokens without an `iss` claim.
+#[tokio::test]
+async fn issuer() {
+    let provider = Issuer::new(ISSUER).await;
+    let checker = StatusChecker::new();
+
+    let claim = allocate(&provider, "issuer", 3_600).await;
+    assert!(checker.check(&claim, "http://localhost:9999", &provider).await.is_err());
+
+    // re-publish the token without `iss`
+    let uri = &claim.status_list.uri;
+    let record = StatusStore::get_list(&provider, ISSUER, uri)
+        .await
+        .expect("should get list")
+        .expect("should have list");
+    let token = TokenBuilder::new()
+        .status_list(record.status_list)
+        .uri(uri)
+        .signer(&provider)
+        .build()
+        .await
+        .expect("should build token");
+    StatusStore::put(&provider, ISSUER, uri, &token).await.expect("should save token");
+
+    let Err(e) = checker.check(&claim, ISSUER, &provider).await else {
+        panic!("should reject token without `iss`");
+    };
+    assert!(e.to_string().contains("missing `iss`"));
+}
+
+async fn allocate(provider: &Issuer, name: &str, ttl: usize) -> StatusClaim {
+    StatusListManager::new(format!("{ISSUER}/checker/{name}"))
+        .capacity(16)
+        .decoys(0)
+        .ttl(ttl)
+        .allocate(ISSUER, provider)
+        .await
+        .expect("should allocate")
+}
+
+async fn set_status(provider: &Issuer, claim: &StatusClaim, status: &StatusType) {
+    let entry = &claim.status_list;
+    let mut record = StatusStore::get_list(provider, ISSUER, &entry.uri)
+        .await
+        .expect("should get list")
+        .expect("should have list");
+    record.status_list.set_status(entry.idx, status).expect("should set status");
+    StatusStore::put_list(provider, ISSUER, &record).await.expect("should save list");
+    record.publish(ISSUER, provider).await.expect("should publish");
+}
@@ -1,11 +1,12 @@
 use std::thread;
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use credibil_proof::did::{Document, DocumentBuilder, KeyId, VerificationMethod};
 use credibil_proof::ecc::Curve::Ed25519;
 use credibil_proof::ecc::{Entry, Keyring, Signer};
 use credibil_proof::jose::PublicKeyJwk;
 use credibil_proof::{DocumentRequest, VerifyBy};
+use serde_json::json;
 
 use crate::store::Store;
 // use crate::vault::KeyVault as Vault;
@@ -51,6 +52,17 @@ impl Identity {
         serde_json::to_vec(&doc).map_err(|e| e.into())
     }
 
+    // The identity's signing key as a JWK Set.
+    pub async fn jwks(&self) -> Result<Vec<u8>> {
+        let key = self.signer.verifying_key().await?;
+        let mut jwk = serde_json::to_value(PublicKeyJwk::from_bytes(&key.to_bytes())?)?;
+        let VerifyBy::KeyId(kid) = self.verification_method().await? else {
+            return Err(anyhow!("verification method should be a key id"));
+        };
+        jwk["kid"] = json!(kid);
+        serde_json::to_vec(&json!({"keys": [jwk]})).map_err(|e| e.into())
+    }
+
     pub async fn verification_method(&self) -> Result<VerifyBy> {
         let doc = self.document(&format!("{}/.well-known/did.json", self.owner)).await?;
         let vm = &doc.verification_method.as_ref().unwrap()[0];
@@ -1,7 +1,15 @@
-use anyhow::Result;
+use std::sync::{LazyLock, Mutex, Once};
+
+use anyhow::{Result, anyhow};
 use credibil_core::datastore::Datastore;
 use credibil_ecc::{Algorithm, PublicKey, Signer};
+use credibil_oid4vci::provider::{IssuanceEvents, observe_issuance};
+use credibil_oid4vci::types::IssuanceEvent;
 use credibil_proof::{Resolver, Signature, VerifyBy};
+use credibil_status::{BitstringEncoder, BitstringStatusList, CwtEncoder, StatusListClaims};
+use credibil_vdc::mso_mdoc::StatusListCwtBuilder;
+use credibil_vdc::w3c_vc::BitstringCredentialBuilder;
+use serde_json::{Value, json};
 
 use crate::identity::Identity;
 use crate::store::Store;
@@ -14,7 +22,11 @@ const CLIENT_METADATA: &[u8] = include_bytes!("../data/client-metadata.json");
 const METADATA: &str = "METADATA";
 const ISSUER: &str = "ISSUER";
 const SERVER: &str = "SERVER";
+const CONFIGURATION: &str = "CONFIGURATION";
 const SUBJECT: &str = "SUBJECT";
+const WALLET_PROVIDER: &str = "WALLETPROVIDER";
+const JWKS_PATH: &str = "/.well-known/jwks.json";
+const SERVER_PATH: &str = "/.well-known/oauth-authorization-server";
 
 #[derive(Clone)]
 pub struct Issuer {
@@ -32,13 +44,61 @@ impl Issuer {
         datastore.put(issuer, SUBJECT, "pending_user", PENDING_USER).await.unwrap();
 
         let identity = Identity::new(issuer).await;
+        OBSERVE.call_once(|| observe_issuance(Recorder).expect("should observe issuance"));
 
         Self { identity }
     }
+
+    // Trust the Wallet Provider identified by `did` to attest to Wallet keys.
+    pub async fn trust_wallet_provider(&self, issuer: &str, did: &str) -> Result<()> {
+        Store.put(issuer, WALLET_PROVIDER, did, &[]).await
+    }
+
+    // Issue self-contained JWT access tokens, verified using the issuer's JWK
+    // Set.
+    pub async fn use_jwt_access_tokens(&self, issuer: &str) -> Result<()> {
+        let mut server: Value = serde_json::from_slice(SERVER_METADATA)?;
+        server["jwks_uri"] = json!(format!("{issuer}{JWKS_PATH}"));
+        Store.put(issuer, METADATA, SERVER, &serde_json::to_vec(&server)?).await?;
+        self.configure(issuer, "jwt_access_tokens", json!(true)).await
+    }
+
+    // The issuance events recorded for the issuer, in the order they occurred.
+    pub fn events(&self, issuer: &str) -> Result<Vec<IssuanceEvent>> {
+        let events = EVENTS.lock().map_err(|_| anyhow!("issuance events are poisoned"))?;
+        Ok(events.iter().filter(|(owner, _)| owner == issuer).map(|(_, e)| e.clone()).collect())
+    }
+
+    // Rely on the listed authorization servers to issue access tokens.
+    pub async fn use_authorization_servers(&self, issuer: &str, servers: &[&str]) -> Result<()> {
+        let mut metadata: Value = serde_json::from_slice(ISSUER_METADATA)?;
+        metadata["authorization_servers"] = json!(servers);
+        Store.put(issuer, METADATA, ISSUER, &serde_json::to_vec(&metadata)?).await
+    }
+
+    // Change an issuer setting that is not published as metadata.
+    pub async fn configure(&self, issuer: &str, setting: &str, value: Value) -> Result<()> {
+        let mut configuration = match Store.get(issuer, METADATA, CONFIGURATION).await? {
+            Some(data) => serde_json::from_slice(&data)?,
+            None => json!({}),
+        };
+        configuration[setting] = value;
+        Store.put(issuer, METADATA, CONFIGURATION, &serde_json::to_vec(&configuration)?).await
+    }
 }
 
 impl Resolver for Issuer {
     async fn resolve(&self, url: &str) -> Result<Vec<u8>> {
+        if url.ends_with(JWKS_PATH) {
+            return self.identity.jwks().await;
+        }
+        // serve metadata for any (external) authorization server
+        if let Some(server) = url.strip_suffix(SERVER_PATH) {
+            let mut metadata: Value = serde_json::from_slice(SERVER_METADATA)?;
+            metadata["issuer"] = json!(server);
+            metadata["jwks_uri"] = json!(format!("{server}{JWKS_PATH}"));
+            return Ok(serde_json::to_vec(&metadata)?);
+        }
         self.identity.resolve(url).await
     }
 }
@@ -63,6 +123,42 @@ impl Signature for Issuer {
     }
 }
 
+impl CwtEncoder for Issuer {
+    async fn encode_cwt(&self, claims: &StatusListClaims) -> Result<Option<Vec<u8>>> {
+        let cwt = StatusListCwtBuilder::new().claims(claims.clone()).signer(self).build().await?;
+        Ok(Some(cwt))
+    }
+}
+
+impl BitstringEncoder for Issuer {
+    async fn encode_bitstring(
+        &self, issuer: &str, status_list: &BitstringStatusList,
+    ) -> Result<Option<String>> {
+        let credential = BitstringCredentialBuilder::new()
+            .issuer(issuer)
+            .status_list(status_list.clone())
+            .signer(self)
+            .build()
+            .await?;
+        Ok(Some(credential))
+    }
+}
+
+// Issuance events recorded for all issuers, in the order they occurred.
+static EVENTS: LazyLock<Mutex<Vec<(String, IssuanceEvent)>>> = LazyLock::new(Mutex::default);
+static OBSERVE: Once = Once::new();
+
+// Records issuance events so tests can check them.
+struct Recorder;
+
+impl IssuanceEvents for Recorder {
+    fn record(&self, owner: &str, event: &IssuanceEvent) -> Result<()> {
+        let mut events = EVENTS.lock().map_err(|_| anyhow!("issuance events are poisoned"))?;
+        events.push((owner.to_string(), event.clone()));
+        Ok(())
+    }
+}
+
 impl Datastore for Issuer {
     async fn put(&self, owner: &str, partition: &str, key: &str, data: &[u8]) -> Result<()> {
         Store.put(owner, partition, key, data).await
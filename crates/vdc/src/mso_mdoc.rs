@@ -12,6 +12,7 @@
 pub mod cose;
 mod issue;
 mod present;
+mod status;
 mod store;
 mod verify;
 
@@ -23,6 +24,7 @@ use anyhow::Result;
 use chrono::{DateTime, Duration, Utc};
 use ciborium::{Value, cbor};
 use coset::{AsCborValue, CoseMac0, CoseSign1};
+use credibil_status::StatusClaim;
 use rand::Rng;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Deserializer, Serialize, Serializer, de, ser};
@@ -31,6 +33,7 @@ use serde_repr::{Deserialize_repr, Serialize_repr};
 pub use self::cose::{CoseKey, Curve, KeyType};
 pub use self::issue::MdocBuilder;
 pub use self::present::DeviceResponseBuilder;
+pub use self::status::{StatusListCwtBuilder, decode_cwt};
 pub use self::store::to_queryable;
 pub use self::verify::verify_vp;
 use crate::serde_cbor;
@@ -769,6 +772,10 @@ pub struct MobileSecurityObject {
 
     /// Validity information for the MSO
     pub validity_info: ValidityInfo,
+
+    /// Information on how to retrieve the status of the mdoc.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub status: Option<StatusClaim>,
 }
 
 impl MobileSecurityObject {
@@ -787,6 +794,7 @@ impl MobileSecurityObject {
                 valid_until: Utc::now() + Duration::days(365),
                 expected_update: None,
             },
+            status: None,
         }
     }
 
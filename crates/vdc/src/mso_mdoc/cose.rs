@@ -9,7 +9,7 @@ use anyhow::{Result, anyhow, bail};
 use base64ct::{Base64UrlUnpadded, Encoding};
 use ciborium::{Value, cbor};
 use coset::{
-    CoseSign1, CoseSign1Builder, HeaderBuilder, ProtectedHeader, SignatureContext, iana,
+    CoseSign1, CoseSign1Builder, HeaderBuilder, Label, ProtectedHeader, SignatureContext, iana,
     sig_structure_data,
 };
 use credibil_ecc::PublicKey;
@@ -24,6 +24,7 @@ const KTY: i64 = 1;
 const CRV: i64 = -1;
 const X: i64 = -2;
 const Y: i64 = -3;
+const TYP: i64 = 16;
 
 /// Signs the provided payload using the provided signer.
 ///
@@ -31,6 +32,32 @@ const Y: i64 = -3;
 ///
 /// Returns an error if the signing fails or if the algorithm is unsupported.
 pub async fn sign(payload: Vec<u8>, signer: &impl Signature) -> Result<CoseSign1> {
+    sign_cose(payload, HeaderBuilder::new(), signer).await
+}
+
+/// Signs the provided payload using the provided signer, setting the `typ`
+/// protected header parameter to the specified media type.
+///
+/// # Errors
+///
+/// Returns an error if the signing fails or if the algorithm is unsupported.
+pub async fn sign_typed(payload: Vec<u8>, typ: &str, signer: &impl Signature) -> Result<CoseSign1> {
+    let header = HeaderBuilder::new().value(TYP, Value::Text(typ.to_string()));
+    sign_cose(payload, header, signer).await
+}
+
+/// Returns the `typ` protected header parameter of a signed COSE object.
+#[must_use]
+pub fn typ(cose: &CoseSign1) -> Option<&str> {
+    cose.protected.header.rest.iter().find_map(|(label, value)| match label {
+        Label::Int(TYP) => value.as_text(),
+        _ => None,
+    })
+}
+
+async fn sign_cose(
+    payload: Vec<u8>, header: HeaderBuilder, signer: &impl Signature,
+) -> Result<CoseSign1> {
     // header
     let algorithm = match signer.algorithm().await? {
         credibil_ecc::Algorithm::EdDSA => iana::Algorithm::EdDSA,
@@ -39,7 +66,7 @@ pub async fn sign(payload: Vec<u8>, signer: &impl Signature) -> Result<CoseSign1
     let VerifyBy::KeyId(key_id) = signer.verification_method().await? else {
         return Err(anyhow!("invalid verification method"));
     };
-    let protected = HeaderBuilder::new().algorithm(algorithm).key_id(key_id.into_bytes()).build();
+    let protected = header.algorithm(algorithm).key_id(key_id.into_bytes()).build();
 
     let sig_data = sig_structure_data(
         SignatureContext::CoseSign1,
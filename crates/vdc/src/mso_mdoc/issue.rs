@@ -6,6 +6,7 @@ use anyhow::anyhow;
 use base64ct::{Base64UrlUnpadded, Encoding};
 use ciborium::cbor;
 use credibil_proof::Signature;
+use credibil_status::StatusClaim;
 use rand::{Rng, rng};
 use serde_json::{Map, Value};
 use sha2::{Digest, Sha256};
@@ -22,6 +23,7 @@ pub struct MdocBuilder<D, K, C, S> {
     doctype: D,
     device_key: K,
     claims: C,
+    status: Option<StatusClaim>,
     signer: S,
 }
 
@@ -61,6 +63,7 @@ impl MdocBuilder<NoDocType, NoDeviceKey, NoClaims, NoSigner> {
             doctype: NoDocType,
             device_key: NoDeviceKey,
             claims: NoClaims,
+            status: None,
             signer: NoSigner,
         }
     }
@@ -79,6 +82,7 @@ impl<K, C, S> MdocBuilder<NoDocType, K, C, S> {
             doctype: HasDocType(doctype.into()),
             device_key: self.device_key,
             claims: self.claims,
+            status: self.status,
             signer: self.signer,
         }
     }
@@ -91,6 +95,7 @@ impl<D, C, S> MdocBuilder<D, NoDeviceKey, C, S> {
             doctype: self.doctype,
             device_key: HasDeviceKey(device_key.into()),
             claims: self.claims,
+            status: self.status,
             signer: self.signer,
         }
     }
@@ -103,6 +108,7 @@ impl<D, K, S> MdocBuilder<D, K, NoClaims, S> {
             doctype: self.doctype,
             device_key: self.device_key,
             claims: HasClaims(claims),
+            status: self.status,
             signer: self.signer,
         }
     }
@@ -115,11 +121,21 @@ impl<D, K, C> MdocBuilder<D, K, C, NoSigner> {
             doctype: self.doctype,
             device_key: self.device_key,
             claims: self.claims,
+            status: self.status,
             signer: HasSigner(signer),
         }
     }
 }
 
+impl<D, K, C, S> MdocBuilder<D, K, C, S> {
+    /// Set the status of the ISO mDL credential.
+    #[must_use]
+    pub fn status(mut self, status: StatusClaim) -> Self {
+        self.status = Some(status);
+        self
+    }
+}
+
 impl<S: Signature> MdocBuilder<HasDocType, HasDeviceKey, HasClaims, HasSigner<'_, S>> {
     /// Build the ISO mDL credential, returning a base64url-encoded,
     /// CBOR-encoded, ISO mDL.
@@ -132,6 +148,7 @@ impl<S: Signature> MdocBuilder<HasDocType, HasDeviceKey, HasClaims, HasSigner<'_
         let mut mso = MobileSecurityObject::new();
         mso.doc_type = self.doctype.0;
         mso.device_key_info.device_key = self.device_key.0;
+        mso.status = self.status;
 
         for (name_space, value) in self.claims.0 {
             // namespace is a root-level claim
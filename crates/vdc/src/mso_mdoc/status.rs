@@ -0,0 +1,268 @@
+//! # Status List Token (CWT)
+//!
+//! Support for Status List Tokens in CWT format (`application/statuslist+cwt`)
+//! as used by ISO mdoc credentials.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Result, anyhow};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::{DateTime, Utc};
+use ciborium::Value;
+use coset::{CborSerializable, CoseSign1};
+use credibil_proof::{Resolver, Signature};
+use credibil_status::{BitsPerToken, STATUSLIST_CWT, StatusList, StatusListClaims};
+
+use crate::mso_mdoc::cose;
+use crate::mso_mdoc::verify::verify_signature;
+use crate::serde_cbor;
+
+// CWT claim keys
+const ISS: i64 = 1;
+const SUB: i64 = 2;
+const EXP: i64 = 4;
+const IAT: i64 = 6;
+const STATUS_LIST: i64 = 65533;
+const TTL: i64 = 65534;
+
+/// Generate a Status List Token in CWT format.
+#[derive(Debug)]
+pub struct StatusListCwtBuilder<C, S> {
+    claims: C,
+    signer: S,
+}
+
+/// Builder has no claims.
+#[doc(hidden)]
+pub struct NoClaims;
+/// Builder has claims.
+#[doc(hidden)]
+pub struct HasClaims(StatusListClaims);
+
+/// Builder has no signer.
+#[doc(hidden)]
+pub struct NoSigner;
+/// Builder state has a signer.
+#[doc(hidden)]
+pub struct HasSigner<'a, S: Signature>(pub &'a S);
+
+impl StatusListCwtBuilder<NoClaims, NoSigner> {
+    /// Create a new `StatusListCwtBuilder`.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            claims: NoClaims,
+            signer: NoSigner,
+        }
+    }
+}
+
+impl Default for StatusListCwtBuilder<NoClaims, NoSigner> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S> StatusListCwtBuilder<NoClaims, S> {
+    /// Set the Status List Token claims.
+    pub fn claims(self, claims: StatusListClaims) -> StatusListCwtBuilder<HasClaims, S> {
+        StatusListCwtBuilder {
+            claims: HasClaims(claims),
+            signer: self.signer,
+        }
+    }
+}
+
+impl<C> StatusListCwtBuilder<C, NoSigner> {
+    /// Set the token signer.
+    pub fn signer<S: Signature>(self, signer: &'_ S) -> StatusListCwtBuilder<C, HasSigner<'_, S>> {
+        StatusListCwtBuilder {
+            claims: self.claims,
+            signer: HasSigner(signer),
+        }
+    }
+}
+
+impl<S: Signature> StatusListCwtBuilder<HasClaims, HasSigner<'_, S>> {
+    /// Build the token, returning the CBOR-encoded `COSE_Sign1` structure.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the claims cannot be encoded or the token cannot
+    /// be signed.
+    pub async fn build(self) -> Result<Vec<u8>> {
+        let claims = self.claims.0;
+        let status_list = claims.status_list;
+
+        let lst = Base64UrlUnpadded::decode_vec(&status_list.lst)
+            .map_err(|_| anyhow!("invalid base64url-encoded status list"))?;
+        let mut list = vec![
+            (Value::from("bits"), Value::from(u64::try_from(status_list.bits.width())?)),
+            (Value::from("lst"), Value::Bytes(lst)),
+        ];
+        if let Some(aggregation_uri) = status_list.aggregation_uri {
+            list.push((Value::from("aggregation_uri"), Value::Text(aggregation_uri)));
+        }
+
+        let mut cwt = BTreeMap::<i64, Value>::new();
+        if let Some(iss) = claims.iss {
+            cwt.insert(ISS, Value::Text(iss));
+        }
+        cwt.insert(SUB, Value::Text(claims.sub));
+        cwt.insert(IAT, Value::from(claims.iat.timestamp()));
+        if let Some(exp) = claims.exp {
+            cwt.insert(EXP, Value::from(exp.timestamp()));
+        }
+        if let Some(ttl) = claims.ttl {
+            cwt.insert(TTL, Value::from(u64::try_from(ttl)?));
+        }
+        cwt.insert(STATUS_LIST, Value::Map(list));
+
+        let payload = serde_cbor::to_vec(&cwt)?;
+        let cose = cose::sign_typed(payload, STATUSLIST_CWT, self.signer.0).await?;
+        cose.to_vec().map_err(|e| anyhow!("issue encoding status list token: {e}"))
+    }
+}
+
+/// Verify and decode a Status List Token in CWT format, published at `uri`.
+///
+/// # Errors
+///
+/// Returns an error if the token cannot be decoded, its signature is invalid,
+/// it is not a Status List Token, its `sub` does not match `uri`, or it has
+/// expired.
+pub async fn decode_cwt(
+    token: &[u8], uri: &str, resolver: &impl Resolver,
+) -> Result<StatusListClaims> {
+    let cose = CoseSign1::from_slice(token)
+        .map_err(|e| anyhow!("issue decoding status list token: {e}"))?;
+    if cose::typ(&cose) != Some(STATUSLIST_CWT) {
+        return Err(anyhow!("invalid status list token type"));
+    }
+    verify_signature(&cose, resolver).await?;
+
+    let Some(payload) = &cose.payload else {
+        return Err(anyhow!("status list token has no payload"));
+    };
+    let cwt = serde_cbor::from_slice::<BTreeMap<i64, Value>>(payload)?;
+
+    let Some(Value::Text(sub)) = cwt.get(&SUB) else {
+        return Err(anyhow!("status list token `sub` claim is missing"));
+    };
+    if sub != uri {
+        return Err(anyhow!("status list token `sub` does not match {uri}"));
+    }
+    let Some(iat) = cwt.get(&IAT).and_then(timestamp) else {
+        return Err(anyhow!("status list token `iat` claim is missing"));
+    };
+    let exp = cwt.get(&EXP).and_then(timestamp);
+    if exp.is_some_and(|exp| exp <= Utc::now()) {
+        return Err(anyhow!("status list token has expired"));
+    }
+    let Some(list) = cwt.get(&STATUS_LIST) else {
+        return Err(anyhow!("status list token `status_list` claim is missing"));
+    };
+    let list = list.deserialized::<BTreeMap<String, Value>>()?;
+
+    let bits = match list.get("bits").and_then(Value::as_integer).map(i128::from) {
+        Some(1) => BitsPerToken::One,
+        Some(2) => BitsPerToken::Two,
+        Some(4) => BitsPerToken::Four,
+        Some(8) => BitsPerToken::Eight,
+        _ => return Err(anyhow!("invalid status list `bits`")),
+    };
+    let Some(Value::Bytes(lst)) = list.get("lst") else {
+        return Err(anyhow!("status list `lst` is missing"));
+    };
+
+    Ok(StatusListClaims {
+        iss: cwt.get(&ISS).and_then(Value::as_text).map(Into::into),
+        sub: sub.clone(),
+        iat,
+        exp,
+        ttl: cwt
+            .get(&TTL)
+            .and_then(Value::as_integer)
+            .and_then(|v| u64::try_from(v).ok())
+            .and_then(|v| usize::try_from(v).ok()),
+        status_list: StatusList {
+            bits,
+            lst: Base64UrlUnpadded::encode_string(lst),
+            aggregation_uri: list.get("aggregation_uri").and_then(Value::as_text).map(Into::into),
+        },
+    })
+}
+
+fn timestamp(value: &Value) -> Option<DateTime<Utc>> {
+    let secs = i64::try_from(value.as_integer()?).ok()?;
+    DateTime::from_timestamp(secs, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use credibil_status::StatusType;
+    use test_utils::issuer::Issuer;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn round_trip() {
+        let issuer = Issuer::new("https://mso_mdoc.io/issuer").await;
+
+        let mut status_list =
+            StatusList::with_capacity(16, BitsPerToken::Two).expect("should create status list");
+        status_list.set_status(3, &StatusType::Suspended).expect("should set status");
+
+        let claims = StatusListClaims {
+            iss: Some("https://mso_mdoc.io/issuer".to_string()),
+            sub: "https://mso_mdoc.io/statuslists/1".to_string(),
+            iat: Utc::now(),
+            exp: None,
+            ttl: Some(300),
+            status_list,
+        };
+        let token = StatusListCwtBuilder::new()
+            .claims(claims.clone())
+            .signer(&issuer)
+            .build()
+            .await
+            .expect("should build");
+
+        let decoded = decode_cwt(&token, &claims.sub, &issuer).await.expect("should decode");
+        assert_eq!(decoded.iss, claims.iss);
+        assert_eq!(decoded.sub, claims.sub);
+        assert_eq!(decoded.iat.timestamp(), claims.iat.timestamp());
+        assert_eq!(decoded.ttl, Some(300));
+        assert_eq!(decoded.status_list.status(3).unwrap(), StatusType::Suspended);
+        assert_eq!(decoded.status_list.status(4).unwrap(), StatusType::Valid);
+
+        // the token must be for the referenced status list
+        assert!(decode_cwt(&token, "https://mso_mdoc.io/statuslists/2", &issuer).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn expired() {
+        let issuer = Issuer::new("https://mso_mdoc.io/issuer").await;
+
+        let claims = StatusListClaims {
+            iss: Some("https://mso_mdoc.io/issuer".to_string()),
+            sub: "https://mso_mdoc.io/statuslists/1".to_string(),
+            iat: Utc::now() - TimeDelta::hours(2),
+            exp: Some(Utc::now() - TimeDelta::hours(1)),
+            ttl: None,
+            status_list: StatusList::new().expect("should create status list"),
+        };
+        let token = StatusListCwtBuilder::new()
+            .claims(claims.clone())
+            .signer(&issuer)
+            .build()
+            .await
+            .expect("should build");
+
+        let Err(e) = decode_cwt(&token, &claims.sub, &issuer).await else {
+            panic!("should reject expired token");
+        };
+        assert!(e.to_string().contains("expired"));
+    }
+}
@@ -4,9 +4,12 @@ use anyhow::{Result, anyhow};
 use base64ct::{Base64UrlUnpadded, Encoding};
 use coset::CoseSign1;
 use credibil_proof::{Resolver, resolve_jwk};
+use credibil_status::{StatusToken, StatusType};
 
 use crate::dcql::Claim;
-use crate::mso_mdoc::{CoseKey, DeviceAuth, DeviceResponse};
+use crate::mso_mdoc::{
+    CoseKey, DataItem, DeviceAuth, DeviceResponse, MobileSecurityObject, decode_cwt,
+};
 use crate::serde_cbor;
 
 /// Verifies an ISO mdoc presentation.
@@ -14,7 +17,10 @@ use crate::serde_cbor;
 /// # Errors
 ///
 /// Returns an error if the presentation is invalid or if verification fails.
-pub async fn verify_vp(vp: &str, resolver: &impl Resolver) -> Result<Vec<Claim>> {
+pub async fn verify_vp<R>(vp: &str, resolver: &R) -> Result<Vec<Claim>>
+where
+    R: Resolver + StatusToken,
+{
     // extract components of the mdoc presentation
     let cbor = Base64UrlUnpadded::decode_vec(vp)?;
     let response = serde_cbor::from_slice::<DeviceResponse>(&cbor)?;
@@ -32,6 +38,25 @@ pub async fn verify_vp(vp: &str, resolver: &impl Resolver) -> Result<Vec<Claim>>
     verify_signature(device_sig, resolver).await?;
     verify_signature(&doc.issuer_signed.issuer_auth, resolver).await?;
 
+    // verify the credential's status
+    let Some(payload) = &doc.issuer_signed.issuer_auth.0.payload else {
+        return Err(anyhow!("missing mobile security object"));
+    };
+    let mso = serde_cbor::from_slice::<DataItem<MobileSecurityObject>>(payload)?;
+    if let Some(status_claim) = &mso.status {
+        let uri = &status_claim.status_list.uri;
+        let token = StatusToken::fetch_cwt(resolver, uri).await?;
+        let decoded = decode_cwt(&token, uri, resolver).await?;
+        match decoded.status_list.status(status_claim.status_list.idx)? {
+            StatusType::Valid => {}
+            StatusType::Invalid => return Err(anyhow!("credential has been revoked")),
+            StatusType::Suspended => return Err(anyhow!("credential is suspended")),
+            StatusType::ApplicationSpecific(value) => {
+                return Err(anyhow!("credential has application-specific status {value:#04x}"));
+            }
+        }
+    }
+
     // verify and return presented claims
     let mut claims = vec![];
     for (name_space, items) in doc.device_signed.name_spaces.iter() {
@@ -3,7 +3,7 @@
 use anyhow::{Result, anyhow};
 use credibil_jose::{Jwt, decode_jws};
 use credibil_proof::{Resolver, resolve_jwk};
-use credibil_status::{StatusListClaims, StatusToken};
+use credibil_status::{StatusToken, check_status};
 
 use crate::dcql::Claim;
 use crate::sd_jwt::{Disclosure, KbJwtClaims, KeyBinding, SdJwtClaims};
@@ -51,13 +51,7 @@ where
 
     // ..verify credential's status
     if let Some(status_claim) = &sd_jwt.claims.status {
-        // FIXME: move this code  to `StatusList`
-        let token = StatusToken::fetch(resolver, &status_claim.status_list.uri).await?;
-        let jwk = async |kid: String| resolve_jwk(&kid, resolver).await;
-        let decoded: Jwt<StatusListClaims> = decode_jws(&token, jwk).await?;
-        if !decoded.claims.status_list.is_valid(status_claim.status_list.idx)? {
-            return Err(anyhow!("credential status is invalid"));
-        }
+        check_status(status_claim, &sd_jwt.claims.iss, resolver).await?;
     }
 
     // verify and unpack the kb-jwt:
@@ -9,6 +9,7 @@
 
 mod issue;
 mod present;
+mod status;
 mod store;
 mod verify;
 
@@ -18,13 +19,14 @@ use std::ops::Deref;
 use chrono::serde::{ts_seconds, ts_seconds_option};
 use chrono::{DateTime, TimeDelta, Utc};
 use credibil_core::{Kind, OneMany};
-use credibil_status::StatusClaim;
+use credibil_status::{BitstringStatusListEntry, StatusClaim};
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 pub use verify::verify_vp;
 
 pub use self::issue::W3cVcBuilder;
 pub use self::present::W3cVpBuilder;
+pub use self::status::{BitstringCredentialBuilder, decode_bitstring};
 pub use self::store::to_queryable;
 
 /// `VerifiableCredential` represents a naive implementation of the W3C
@@ -198,6 +200,10 @@ pub enum CredentialStatusType {
     /// status.
     #[serde(rename = "tokenStatusListEntry")]
     TokenStatus(StatusClaim),
+
+    /// A W3C Bitstring Status List entry.
+    #[serde(rename = "BitstringStatusListEntry")]
+    BitstringStatus(BitstringStatusListEntry),
 }
 
 impl Default for CredentialStatusType {
@@ -11,7 +11,7 @@ use anyhow::Context as _;
 use credibil_core::{Kind, OneMany};
 use credibil_jose::encode_jws;
 use credibil_proof::Signature;
-use credibil_status::StatusClaim;
+use credibil_status::{BitstringStatusListEntry, StatusClaim};
 use serde_json::{Map, Value};
 
 use crate::w3c_vc::{
@@ -26,6 +26,7 @@ pub struct W3cVcBuilder<T, I, H, C, S> {
     holder: H,
     claims: C,
     status: Option<StatusClaim>,
+    bitstring_status: Vec<BitstringStatusListEntry>,
     signer: S,
 }
 
@@ -80,6 +81,7 @@ impl W3cVcBuilder<NoType, NoIssuer, NoHolder, NoClaims, NoSigner> {
             holder: NoHolder,
             claims: NoClaims,
             status: None,
+            bitstring_status: vec![],
             signer: NoSigner,
         }
     }
@@ -96,6 +98,7 @@ impl<I, H, C, S> W3cVcBuilder<NoType, I, H, C, S> {
             holder: self.holder,
             claims: self.claims,
             status: self.status,
+            bitstring_status: self.bitstring_status,
             signer: self.signer,
         }
     }
@@ -112,6 +115,7 @@ impl<G, H, C, S> W3cVcBuilder<G, NoIssuer, H, C, S> {
             holder: self.holder,
             claims: self.claims,
             status: self.status,
+            bitstring_status: self.bitstring_status,
             signer: self.signer,
         }
     }
@@ -128,6 +132,7 @@ impl<G, I, C, S> W3cVcBuilder<G, I, NoHolder, C, S> {
             holder: HasHolder(holder.into()),
             claims: self.claims,
             status: self.status,
+            bitstring_status: self.bitstring_status,
             signer: self.signer,
         }
     }
@@ -144,6 +149,7 @@ impl<G, I, H, S> W3cVcBuilder<G, I, H, NoClaims, S> {
             holder: self.holder,
             claims: HasClaims(claims),
             status: self.status,
+            bitstring_status: self.bitstring_status,
             signer: self.signer,
         }
     }
@@ -160,6 +166,7 @@ impl<G, I, H, C> W3cVcBuilder<G, I, H, C, NoSigner> {
             holder: self.holder,
             claims: self.claims,
             status: self.status,
+            bitstring_status: self.bitstring_status,
             signer: HasSigner(signer),
         }
     }
@@ -172,6 +179,13 @@ impl<G, I, H, C, S> W3cVcBuilder<G, I, H, C, S> {
         self.status = Some(status);
         self
     }
+
+    /// Adds a W3C Bitstring Status List entry to the status property.
+    #[must_use]
+    pub fn bitstring_status(mut self, entry: BitstringStatusListEntry) -> Self {
+        self.bitstring_status.push(entry);
+        self
+    }
 }
 
 impl<S: Signature> W3cVcBuilder<HasType, HasIssuer, HasHolder, HasClaims, HasSigner<'_, S>> {
@@ -180,13 +194,23 @@ impl<S: Signature> W3cVcBuilder<HasType, HasIssuer, HasHolder, HasClaims, HasSig
     /// # Errors
     /// TODO: Document errors
     pub async fn build(self) -> anyhow::Result<String> {
-        let credential_status = if let Some(status_claim) = self.status {
-            Some(OneMany::One(CredentialStatus {
+        let mut statuses = vec![];
+        if let Some(status_claim) = self.status {
+            statuses.push(CredentialStatus {
                 id: Some(status_claim.status_list.uri.clone()),
                 credential_status_type: CredentialStatusType::TokenStatus(status_claim),
-            }))
-        } else {
-            None
+            });
+        }
+        for entry in self.bitstring_status {
+            statuses.push(CredentialStatus {
+                id: Some(format!("{}#{}", entry.status_list_credential, entry.status_list_index)),
+                credential_status_type: CredentialStatusType::BitstringStatus(entry),
+            });
+        }
+        let credential_status = match statuses.len() {
+            0 => None,
+            1 => Some(OneMany::One(statuses.remove(0))),
+            _ => Some(OneMany::Many(statuses)),
         };
 
         let vc = VerifiableCredential {
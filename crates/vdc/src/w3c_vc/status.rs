@@ -0,0 +1,170 @@
+//! # Bitstring Status List Credential
+//!
+//! Support for publishing W3C Bitstring Status Lists as
+//! `BitstringStatusListCredential`s in `jwt_vc_json` format.
+
+use anyhow::{Context as _, Result, anyhow};
+use chrono::Utc;
+use credibil_core::{Kind, OneMany};
+use credibil_jose::{Jwt, decode_jws, encode_jws};
+use credibil_proof::{Resolver, Signature, resolve_jwk};
+use credibil_status::{BITSTRING_STATUS_LIST_CREDENTIAL, BitstringStatusList};
+use serde_json::Value;
+
+use crate::w3c_vc::{CredentialSubject, VerifiableCredential, W3cVcClaims};
+
+/// Generate a W3C `BitstringStatusListCredential`.
+#[derive(Debug)]
+pub struct BitstringCredentialBuilder<I, L, S> {
+    issuer: I,
+    status_list: L,
+    signer: S,
+}
+
+/// Builder has no issuer.
+#[doc(hidden)]
+pub struct NoIssuer;
+/// Builder has issuer.
+#[doc(hidden)]
+pub struct HasIssuer(String);
+
+/// Builder has no status list.
+#[doc(hidden)]
+pub struct NoList;
+/// Builder has a status list.
+#[doc(hidden)]
+pub struct HasList(BitstringStatusList);
+
+/// Builder has no signer.
+#[doc(hidden)]
+pub struct NoSigner;
+/// Builder state has a signer.
+#[doc(hidden)]
+pub struct HasSigner<'a, S: Signature>(pub &'a S);
+
+impl Default for BitstringCredentialBuilder<NoIssuer, NoList, NoSigner> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BitstringCredentialBuilder<NoIssuer, NoList, NoSigner> {
+    /// Create a new `BitstringCredentialBuilder`.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            issuer: NoIssuer,
+            status_list: NoList,
+            signer: NoSigner,
+        }
+    }
+}
+
+impl<L, S> BitstringCredentialBuilder<NoIssuer, L, S> {
+    /// Set the credential issuer.
+    #[must_use]
+    pub fn issuer(self, issuer: impl Into<String>) -> BitstringCredentialBuilder<HasIssuer, L, S> {
+        BitstringCredentialBuilder {
+            issuer: HasIssuer(issuer.into()),
+            status_list: self.status_list,
+            signer: self.signer,
+        }
+    }
+}
+
+impl<I, S> BitstringCredentialBuilder<I, NoList, S> {
+    /// Set the Bitstring Status List (the credential subject).
+    #[must_use]
+    pub fn status_list(
+        self, status_list: BitstringStatusList,
+    ) -> BitstringCredentialBuilder<I, HasList, S> {
+        BitstringCredentialBuilder {
+            issuer: self.issuer,
+            status_list: HasList(status_list),
+            signer: self.signer,
+        }
+    }
+}
+
+impl<I, L> BitstringCredentialBuilder<I, L, NoSigner> {
+    /// Set the credential `Signature`.
+    #[must_use]
+    pub fn signer<S: Signature>(
+        self, signer: &'_ S,
+    ) -> BitstringCredentialBuilder<I, L, HasSigner<'_, S>> {
+        BitstringCredentialBuilder {
+            issuer: self.issuer,
+            status_list: self.status_list,
+            signer: HasSigner(signer),
+        }
+    }
+}
+
+impl<S: Signature> BitstringCredentialBuilder<HasIssuer, HasList, HasSigner<'_, S>> {
+    /// Build the credential, returning a base64url-encoded JSON JWT.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the status list cannot be serialized or the
+    /// credential cannot be signed.
+    pub async fn build(self) -> Result<String> {
+        let status_list = self.status_list.0;
+
+        let Value::Object(mut claims) = serde_json::to_value(&status_list)? else {
+            return Err(anyhow!("invalid status list"));
+        };
+        claims.remove("id");
+
+        let vc = VerifiableCredential {
+            id: Some(status_list.credential_id().to_string()),
+            r#type: vec![
+                "VerifiableCredential".to_string(),
+                BITSTRING_STATUS_LIST_CREDENTIAL.to_string(),
+            ],
+            issuer: Kind::String(self.issuer.0),
+            credential_subject: OneMany::One(CredentialSubject {
+                id: Some(status_list.id.clone()),
+                claims,
+            }),
+            valid_from: Some(Utc::now()),
+            ..VerifiableCredential::default()
+        };
+
+        let key = self.signer.0.verification_method().await?;
+        encode_jws(&W3cVcClaims::from(vc), &key.try_into()?, self.signer.0)
+            .await
+            .context("generating status list credential")
+    }
+}
+
+/// Verify and decode a `BitstringStatusListCredential`, returning the
+/// credential's issuer and Bitstring Status List.
+///
+/// # Errors
+///
+/// Returns an error if the credential's signature is invalid, it is not a
+/// Bitstring Status List Credential, or it is no longer valid.
+pub async fn decode_bitstring(
+    credential: &str, resolver: &impl Resolver,
+) -> Result<(String, BitstringStatusList)> {
+    let jwk = async |kid: String| resolve_jwk(&kid, resolver).await;
+    let jwt: Jwt<W3cVcClaims> =
+        decode_jws(credential, jwk).await.context("verifying status list credential")?;
+    let vc = jwt.claims.vc;
+
+    if !vc.r#type.iter().any(|t| t == BITSTRING_STATUS_LIST_CREDENTIAL) {
+        return Err(anyhow!("credential is not a {BITSTRING_STATUS_LIST_CREDENTIAL}"));
+    }
+    if vc.valid_until.is_some_and(|until| until <= Utc::now()) {
+        return Err(anyhow!("status list credential has expired"));
+    }
+
+    let OneMany::One(subject) = vc.credential_subject else {
+        return Err(anyhow!("status list credential should have a single subject"));
+    };
+    let mut claims = subject.claims;
+    claims.insert("id".to_string(), Value::from(subject.id.unwrap_or_default()));
+    let status_list = serde_json::from_value(Value::Object(claims))?;
+
+    Ok((jwt.claims.iss, status_list))
+}
@@ -1,12 +1,18 @@
 //! # W3C Identity
 
+use std::str::FromStr;
+
 use anyhow::{Result, anyhow};
-use credibil_jose::{Jwt, decode_jws};
+use credibil_core::Kind;
+use credibil_jose::{Jws, Jwt, decode_jws};
 use credibil_proof::{Resolver, resolve_jwk};
+use credibil_status::{BitstringStatusListEntry, StatusPurpose, StatusToken, check_status};
 
 use super::W3cVpClaims;
 use crate::dcql::Claim;
-use crate::w3c_vc::store;
+use crate::w3c_vc::{
+    CredentialStatusType, VerifiableCredential, W3cVcClaims, decode_bitstring, store,
+};
 
 /// Verifies an SD-JWT presentation (KB-JWT, and associated disclosures).
 ///
@@ -14,9 +20,12 @@ use crate::w3c_vc::store;
 ///
 /// Returns an error if the SD-JWT presentation is invalid or if verification
 /// fails.
-pub async fn verify_vp(
-    vp: &str, nonce: &str, client_id: &str, resolver: &impl Resolver,
-) -> Result<Vec<Claim>> {
+pub async fn verify_vp<R>(
+    vp: &str, nonce: &str, client_id: &str, resolver: &R,
+) -> Result<Vec<Claim>>
+where
+    R: Resolver + StatusToken,
+{
     // verify and unpack jwt:
     //  1. it should be signed by the holder
     //  2. the `nonce` should contain the authorization request nonce
@@ -33,15 +42,6 @@ pub async fn verify_vp(
     }
     // FIXME: verify `iss` claim
 
-    // FIXME: verify credential's status
-    // if let Some(status_claim) = &sd_jwt.claims.status {
-    //     let jwt = StatusToken::fetch(resolver, &status_claim.status_list.uri).await?;
-    //     let status_list = StatusList::from_jwt(&jwt)?;
-    //     if !status_list.is_valid(status_claim.status_list.idx)? {
-    //         return Err(anyhow!("credential status is invalid"));
-    //     }
-    // }
-
     //  unpack claims
     let Some(vcs) = vp_jwt.claims.vp.verifiable_credential else {
         return Ok(vec![]);
@@ -49,9 +49,70 @@ pub async fn verify_vp(
 
     let mut claims = vec![];
     for vc in vcs {
+        verify_status(&vc, resolver).await?;
         let c = store::to_queryable(vc, resolver).await?;
         claims.extend(c.claims);
     }
 
     Ok(claims)
 }
+
+// Verify the status of each of the credential's status entries.
+async fn verify_status<R>(vc: &Kind<VerifiableCredential>, resolver: &R) -> Result<()>
+where
+    R: Resolver + StatusToken,
+{
+    let vc = match vc {
+        Kind::String(encoded) => Jws::from_str(encoded)?.payload::<W3cVcClaims>()?.vc,
+        Kind::Object(vc) => vc.clone(),
+    };
+    let Some(credential_status) = &vc.credential_status else {
+        return Ok(());
+    };
+    let issuer = match &vc.issuer {
+        Kind::String(id) => id,
+        Kind::Object(issuer) => &issuer.id,
+    };
+
+    for status in credential_status.to_vec() {
+        match &status.credential_status_type {
+            CredentialStatusType::TokenStatus(status_claim) => {
+                check_status(status_claim, issuer, resolver).await?;
+            }
+            CredentialStatusType::BitstringStatus(entry) => {
+                check_bitstring(entry, issuer, resolver).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Verify the credential's bit is not set in the Bitstring Status List.
+async fn check_bitstring<R>(
+    entry: &BitstringStatusListEntry, issuer: &str, resolver: &R,
+) -> Result<()>
+where
+    R: Resolver + StatusToken,
+{
+    let uri = &entry.status_list_credential;
+    let credential = StatusToken::fetch_bitstring(resolver, uri).await?;
+    let (iss, status_list) = decode_bitstring(&credential, resolver).await?;
+
+    if iss != issuer {
+        return Err(anyhow!("status list credential issuer does not match {issuer}"));
+    }
+    if status_list.credential_id() != uri {
+        return Err(anyhow!("status list credential does not match {uri}"));
+    }
+    if status_list.status_purpose != entry.status_purpose {
+        return Err(anyhow!("status list credential purpose does not match entry"));
+    }
+
+    if status_list.is_set(entry.index()?)? {
+        return match entry.status_purpose {
+            StatusPurpose::Revocation => Err(anyhow!("credential has been revoked")),
+            StatusPurpose::Suspension => Err(anyhow!("credential is suspended")),
+        };
+    }
+    Ok(())
+}
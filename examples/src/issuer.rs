@@ -6,22 +6,25 @@ use std::collections::HashMap;
 use std::sync::LazyLock;
 
 use anyhow::Result;
-use axum::extract::{Path, Request, State};
-use axum::http::header::AUTHORIZATION;
+use axum::extract::{Path, Query, Request, State};
 use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
 use axum::response::{Html, IntoResponse, Redirect, Response};
 use axum::routing::{get, post};
 use axum::{Form, Json, Router};
 use axum_extra::TypedHeader;
-use axum_extra::headers::authorization::Bearer;
-use axum_extra::headers::{Authorization, Host};
+use axum_extra::headers::Host;
+use chrono::DateTime;
 use credibil_oid4vci::http::IntoHttp;
 use credibil_oid4vci::identity::did::Document;
-use credibil_oid4vci::status::StatusListRequest;
+use credibil_oid4vci::status::{
+    BitstringStatusListRequest, StatusListAggregation, StatusListAggregationRequest,
+    StatusListHeaders, StatusListRequest, StatusListResponse,
+};
 use credibil_oid4vci::{
-    AuthorizationRequest, CreateOfferRequest, CredentialHeaders, CredentialOfferRequest,
-    CredentialRequest, DeferredCredentialRequest, MetadataRequest, NonceRequest,
-    NotificationHeaders, NotificationRequest, PushedAuthorizationRequest, ServerRequest,
+    AuthorizationRequest, ChallengeRequest, CreateOfferRequest, CredentialHeaders,
+    CredentialOfferRequest, CredentialRequest, DeferredCredentialRequest, DeferredHeaders,
+    IntrospectionRequest, MetadataRequest, NonceRequest, NotificationHeaders, NotificationRequest,
+    ParHeaders, PushedAuthorizationRequest, RevocationRequest, ServerRequest, TokenHeaders,
     TokenRequest, html,
 };
 use oauth2::CsrfToken;
@@ -50,11 +53,16 @@ pub async fn serve(issuer_id: &'static str) -> Result<JoinHandle<()>> {
         .route("/par", get(par))
         .route("/login", post(handle_login))
         .route("/token", post(token))
+        .route("/introspect", post(introspect))
+        .route("/revoke", post(revoke))
         .route("/nonce", post(nonce))
+        .route("/challenge", post(challenge))
         .route("/credential", post(credential))
         .route("/deferred_credential", post(deferred_credential))
         .route("/notification", post(notification))
+        .route("/statuslists", get(aggregation))
         .route("/statuslists/{id}", get(statuslists))
+        .route("/statuslists/{id}/{purpose}", get(bitstring))
         .route("/.well-known/openid-credential-issuer", get(metadata))
         .route("/.well-known/oauth-authorization-server", get(oauth_server))
         .route("/.well-known/did.json", get(did))
@@ -185,7 +193,7 @@ async fn authorize(
 /// redirection URI using the "application/x-www-form-urlencoded" format.
 #[axum::debug_handler]
 async fn par(
-    State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
+    headers: HeaderMap, State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
     Form(req): Form<PushedAuthorizationRequest>,
 ) -> impl IntoResponse {
     let object = &req.request;
@@ -219,7 +227,11 @@ async fn par(
     }
 
     // process request
-    credibil_oid4vci::handle(&format!("http://{host}"), req, &provider)
+    let request = credibil_oid4vci::Request {
+        body: req,
+        headers: ParHeaders::from(headers),
+    };
+    credibil_oid4vci::handle(&format!("http://{host}"), request, &provider)
         .await
         .into_http()
         .into_response()
@@ -260,17 +272,40 @@ async fn handle_login(
 
 #[axum::debug_handler]
 async fn token(
-    State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
+    headers: HeaderMap, State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
     Form(form): Form<Vec<(String, String)>>,
 ) -> impl IntoResponse {
     let Ok(tr) = TokenRequest::form_decode(&form) else {
         return (StatusCode::BAD_REQUEST, Json(json!({"error": "invalid request"})))
             .into_response();
     };
-    credibil_oid4vci::handle(&format!("http://{host}"), tr, &provider)
-        .await
-        .into_http()
-        .into_response()
+    let request = credibil_oid4vci::Request {
+        body: tr,
+        headers: TokenHeaders::from(headers),
+    };
+    let result = credibil_oid4vci::handle(&format!("http://{host}"), request, &provider).await;
+    if let Err(e) = &result {
+        if let Some(response) = dpop_nonce(e) {
+            return response;
+        }
+    }
+    result.into_http().into_response()
+}
+
+#[axum::debug_handler]
+async fn introspect(
+    State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
+    Form(request): Form<IntrospectionRequest>,
+) -> impl IntoResponse {
+    credibil_oid4vci::handle(&format!("http://{host}"), request, &provider).await.into_http()
+}
+
+#[axum::debug_handler]
+async fn revoke(
+    State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
+    Form(request): Form<RevocationRequest>,
+) -> impl IntoResponse {
+    credibil_oid4vci::handle(&format!("http://{host}"), request, &provider).await.into_http()
 }
 
 #[axum::debug_handler]
@@ -282,47 +317,51 @@ async fn nonce(
 }
 
 #[axum::debug_handler]
-async fn credential(
+async fn challenge(
     State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
-    TypedHeader(auth): TypedHeader<Authorization<Bearer>>, Json(request): Json<CredentialRequest>,
+) -> impl IntoResponse {
+    let request = ChallengeRequest;
+    credibil_oid4vci::handle(&format!("http://{host}"), request, &provider).await.into_http()
+}
+
+#[axum::debug_handler]
+async fn credential(
+    headers: HeaderMap, State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
+    Json(request): Json<CredentialRequest>,
 ) -> impl IntoResponse {
     let request = credibil_oid4vci::Request {
         body: request,
-        headers: CredentialHeaders {
-            authorization: auth.token().to_string(),
-        },
+        headers: CredentialHeaders::from(headers),
     };
-    credibil_oid4vci::handle(&format!("http://{host}"), request, &provider).await.into_http()
+    let result = credibil_oid4vci::handle(&format!("http://{host}"), request, &provider).await;
+    if let Err(e) = &result {
+        if let Some(response) = dpop_nonce(e) {
+            return response;
+        }
+    }
+    result.into_http().into_response()
 }
 
 #[axum::debug_handler]
 async fn deferred_credential(
-    State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
-    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    headers: HeaderMap, State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
     Json(request): Json<DeferredCredentialRequest>,
 ) -> impl IntoResponse {
     let request = credibil_oid4vci::Request {
         body: request,
-        headers: CredentialHeaders {
-            authorization: auth.token().to_string(),
-        },
+        headers: DeferredHeaders::from(headers),
     };
     credibil_oid4vci::handle(&format!("http://{host}"), request, &provider).await.into_http()
 }
 
 #[axum::debug_handler]
 async fn notification(
-    State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
-    TypedHeader(auth): TypedHeader<Authorization<Bearer>>,
+    headers: HeaderMap, State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
     Json(request): Json<NotificationRequest>,
 ) -> impl IntoResponse {
-    let mut headers = HeaderMap::new();
-    headers.insert(AUTHORIZATION, auth.token().parse().unwrap());
     let request = credibil_oid4vci::Request {
         body: request,
-        headers: NotificationHeaders {
-            authorization: auth.token().to_string(),
-        },
+        headers: NotificationHeaders::from(headers),
     };
     credibil_oid4vci::handle(&format!("http://{host}"), request, &provider).await.into_http()
 }
@@ -330,10 +369,58 @@ async fn notification(
 // Status Lists endpoint
 #[axum::debug_handler]
 async fn statuslists(
-    State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>, Path(id): Path<String>,
-) -> impl IntoResponse {
-    let request = StatusListRequest { id: Some(id) };
-    credibil_status::handle(&format!("http://{host}"), request, &provider).await.into_http()
+    headers: HeaderMap, State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
+    Path(id): Path<String>, Query(query): Query<StatusListQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let request = credibil_status::Request {
+        body: StatusListRequest {
+            id: Some(format!("http://{host}/statuslists/{id}")),
+            time: query.time.and_then(|t| DateTime::from_timestamp(t, 0)),
+        },
+        headers: StatusListHeaders::from(headers),
+    };
+    let response = credibil_status::handle(&format!("http://{host}"), request, &provider)
+        .await
+        .map_err(AppError::from)?;
+
+    // return the token as-is using the negotiated media type
+    let body = match &*response {
+        StatusListResponse::Jwt(jwt) => jwt.clone().into_bytes(),
+        StatusListResponse::Cwt(cwt) => cwt.clone(),
+    };
+    Ok(([(header::CONTENT_TYPE, response.content_type())], body))
+}
+
+// Bitstring Status List Credential endpoint
+#[axum::debug_handler]
+async fn bitstring(
+    State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
+    Path((id, purpose)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    let request = BitstringStatusListRequest {
+        id: format!("http://{host}/statuslists/{id}/{purpose}"),
+    };
+    let response = credibil_status::handle(&format!("http://{host}"), request, &provider)
+        .await
+        .map_err(AppError::from)?;
+    Ok(([(header::CONTENT_TYPE, "application/vc+jwt")], response.0.clone()))
+}
+
+#[derive(Deserialize)]
+struct StatusListQuery {
+    time: Option<i64>,
+}
+
+// Status List Aggregation endpoint
+#[axum::debug_handler]
+async fn aggregation(
+    State(provider): State<Issuer>, TypedHeader(host): TypedHeader<Host>,
+) -> Result<Json<StatusListAggregation>, AppError> {
+    let response =
+        credibil_status::handle(&format!("http://{host}"), StatusListAggregationRequest, &provider)
+            .await
+            .map_err(AppError::from)?;
+    Ok(Json((*response).clone()))
 }
 
 #[axum::debug_handler]
@@ -349,6 +436,13 @@ async fn did(
     Ok(Json(doc.0.clone()))
 }
 
+// Return a `use_dpop_nonce` error with the fresh nonce in the `DPoP-Nonce`
+// header.
+fn dpop_nonce(err: &credibil_oid4vci::Error) -> Option<Response> {
+    let (status, headers) = err.dpop_nonce_response()?;
+    Some((status, headers, Json(err)).into_response())
+}
+
 // Wrap anyhow::Error.
 struct AppError(anyhow::Error);
 
@@ -0,0 +1,209 @@
+//! # JWT Access Tokens
+//!
+//! Issuance and verification of self-contained JWT access tokens as defined
+//! in [RFC9068](https://www.rfc-editor.org/rfc/rfc9068.html).
+//!
+//! JWT access tokens carry the authorized credentials in the token itself,
+//! allowing the authorization server and the Credential Issuer to be deployed
+//! as separate services that do not share state. The Credential Issuer
+//! verifies tokens using the keys published at the authorization server's
+//! `jwks_uri`.
+//!
+//! Tokens issued by any of the authorization servers listed in the Credential
+//! Issuer's `authorization_servers` metadata are accepted. Third-party
+//! authorization servers must issue JWT access tokens as the Credential
+//! Issuer holds no state for their opaque tokens.
+//!
+//! Revoked tokens are denied until they expire using a deny list kept in
+//! the authorization server's state. A Credential Issuer deployed without
+//! access to that state cannot see revocations, so should rely on the short
+//! lifetime of access tokens instead.
+
+use anyhow::{Context as _, anyhow};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::{DateTime, Utc};
+use credibil_core::state::State;
+use credibil_jose::{JwsBuilder, Jwt, PublicKeyJwk, decode_jws};
+use serde_json::Value;
+
+use crate::error::server;
+use crate::handlers::{Error, Result};
+use crate::provider::{Metadata, Provider, Signature, StateStore};
+use crate::state::Token;
+use crate::types::{AccessTokenClaims, ServerMetadata, TokenConfirmation};
+use crate::{JwtType, authorization_server};
+
+// State key prefix used to deny revoked JWT access tokens until they expire.
+const DENIED_PREFIX: &str = "denied-access-token:";
+
+/// Issue a signed JWT access token for the Credential Issuer (`issuer`)
+/// carrying the authorized credentials in `token`.
+///
+/// # Errors
+///
+/// Returns an error if the token cannot be signed.
+pub async fn issue(
+    issuer: &str, provider: &impl Provider, server: &ServerMetadata, token: &Token,
+    expires_at: DateTime<Utc>,
+) -> Result<String> {
+    let claims = AccessTokenClaims {
+        iss: server.oauth.issuer.clone(),
+        exp: expires_at,
+        aud: issuer.to_string(),
+        sub: token.subject_id.clone(),
+        client_id: token.client_id.clone(),
+        iat: Utc::now(),
+        jti: token.access_token.clone(),
+        authorization_details: token.authorized_details.clone(),
+        scope: token.scope.clone(),
+        cnf: token.dpop_jkt.clone().map(|jkt| TokenConfirmation { jkt }),
+        grant_id: Some(token.grant_id.clone()),
+    };
+
+    let kid = provider.verification_method().await.context("getting verification method")?;
+    let key_ref = kid.try_into().context("converting key_ref")?;
+    let jws = JwsBuilder::new()
+        .typ(JwtType::AccessToken)
+        .payload(claims)
+        .key_ref(&key_ref)
+        .add_signer(provider)
+        .build()
+        .await
+        .context("building jwt")?;
+
+    Ok(jws.encode().context("encoding jwt")?)
+}
+
+/// Whether the access token is a JWT rather than an opaque token.
+#[must_use]
+pub fn is_jwt(access_token: &str) -> bool {
+    access_token.split('.').count() == 3
+}
+
+/// Verify a JWT access token issued for the Credential Issuer (`issuer`),
+/// returning the equivalent access token state.
+///
+/// # Errors
+///
+/// Returns `Error::AccessDenied` if the token is invalid, has expired, or was
+/// not issued for the Credential Issuer by one of its authorization servers.
+pub async fn verify(
+    issuer: &str, provider: &impl Provider, access_token: &str,
+) -> Result<State<Token>> {
+    let server = token_server(issuer, provider, access_token).await?;
+    let Some(jwks_uri) = &server.oauth.jwks_uri else {
+        return Err(Error::AccessDenied("authorization server has no `jwks_uri`".to_string()));
+    };
+
+    let resolver = async |kid: String| find_key(provider, jwks_uri, &kid).await;
+    let jwt: Jwt<AccessTokenClaims> = decode_jws(access_token, resolver)
+        .await
+        .map_err(|e| Error::AccessDenied(format!("invalid access token: {e}")))?;
+    if jwt.header.typ != JwtType::AccessToken.to_string() {
+        return Err(Error::AccessDenied("invalid access token type".to_string()));
+    }
+
+    let claims = jwt.claims;
+    if claims.iss != server.oauth.issuer {
+        return Err(Error::AccessDenied("access token `iss` is invalid".to_string()));
+    }
+    if claims.aud != issuer {
+        return Err(Error::AccessDenied("access token `aud` is invalid".to_string()));
+    }
+    if claims.exp <= Utc::now() {
+        return Err(Error::AccessDenied("access token has expired".to_string()));
+    }
+    if StateStore::get::<String>(provider, issuer, &denied_key(&claims.jti)).await.is_ok() {
+        return Err(Error::AccessDenied("access token has been revoked".to_string()));
+    }
+
+    Ok(State {
+        body: Token {
+            subject_id: claims.sub,
+            access_token: access_token.to_string(),
+            authorized_details: claims.authorization_details,
+            scope: claims.scope,
+            client_id: claims.client_id,
+            dpop_jkt: claims.cnf.map(|cnf| cnf.jkt),
+            grant_id: claims.grant_id.unwrap_or_default(),
+            wallet: None,
+        },
+        expires_at: claims.exp,
+    })
+}
+
+/// Deny a revoked JWT access token until it expires.
+///
+/// # Errors
+///
+/// Returns an error if the token has no `jti` or the provider is not
+/// available.
+pub async fn deny(
+    issuer: &str, provider: &impl Provider, access_token: &str, expires_at: DateTime<Utc>,
+) -> Result<()> {
+    let Some(jti) = claim(access_token, "jti") else {
+        return Err(server!("access token `jti` is missing"));
+    };
+    let state = State {
+        body: access_token.to_string(),
+        expires_at,
+    };
+    StateStore::put(provider, issuer, &denied_key(&jti), &state).await.context("saving state")?;
+    Ok(())
+}
+
+// State key used to deny the access token identified by `jti`.
+fn denied_key(jti: &str) -> String {
+    format!("{DENIED_PREFIX}{jti}")
+}
+
+// Read a string claim from the (unverified) access token payload.
+fn claim(access_token: &str, name: &str) -> Option<String> {
+    access_token
+        .split('.')
+        .nth(1)
+        .and_then(|payload| Base64UrlUnpadded::decode_vec(payload).ok())
+        .and_then(|payload| serde_json::from_slice::<Value>(&payload).ok())
+        .and_then(|claims| claims.get(name).and_then(Value::as_str).map(ToString::to_string))
+}
+
+// Retrieve metadata for the authorization server claiming to have issued the
+// token: a server listed in the issuer's `authorization_servers` or, failing
+// that, the issuer's own authorization server. The token's signature and
+// `iss` are verified against the returned metadata.
+async fn token_server(
+    issuer: &str, provider: &impl Provider, access_token: &str,
+) -> Result<ServerMetadata> {
+    let Some(iss) = claim(access_token, "iss") else {
+        return Err(Error::AccessDenied("access token `iss` is missing".to_string()));
+    };
+
+    let Ok(metadata) = Metadata::issuer(provider, issuer).await else {
+        return Err(server!("unknown credential issuer"));
+    };
+    let listed = metadata.authorization_servers.is_some_and(|servers| servers.contains(&iss));
+    authorization_server::metadata(issuer, provider, listed.then_some(iss.as_str())).await
+}
+
+// Find the key identified by `kid` in the JWK Set published at `jwks_uri`.
+// The JWK Set is retrieved again when a cached copy does not contain the key
+// in case the authorization server has rotated its keys. As the `kid` is
+// chosen by the client, the JWK Set is retrieved again at most once a minute,
+// so unknown keys presented in the meantime are rejected using the cache.
+async fn find_key(
+    provider: &impl Provider, jwks_uri: &str, kid: &str,
+) -> anyhow::Result<PublicKeyJwk> {
+    for refresh in [false, true] {
+        let jwks = authorization_server::fetch(provider, jwks_uri, refresh)
+            .await
+            .context("fetching JWK Set")?;
+        let jwks: Value = serde_json::from_slice(&jwks).context("deserializing JWK Set")?;
+        let keys = jwks.get("keys").and_then(Value::as_array).cloned().unwrap_or_default();
+        if let Some(key) =
+            keys.into_iter().find(|k| k.get("kid").and_then(Value::as_str) == Some(kid))
+        {
+            return Ok(serde_json::from_value(key)?);
+        }
+    }
+    Err(anyhow!("no key in JWK Set matches {kid}"))
+}
@@ -0,0 +1,167 @@
+//! # Authorization Servers
+//!
+//! A Credential Issuer may rely on one or more authorization servers, listed
+//! in its `authorization_servers` metadata, including servers operated by
+//! third parties.
+//!
+//! The Credential Issuer's own authorization server is the one whose metadata
+//! is held by the provider. Metadata for other listed servers is retrieved
+//! from their well-known metadata endpoint as defined in
+//! [RFC8414](https://www.rfc-editor.org/rfc/rfc8414.html).
+//!
+//! Retrieved metadata and JWK Sets are cached for a short time so verifying
+//! an access token does not require a round trip to the authorization server.
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use anyhow::Context as _;
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::error::{invalid, server};
+use crate::handlers::Result;
+use crate::provider::{Metadata, Provider, Resolver};
+use crate::types::{IssuerMetadata, ServerMetadata};
+
+// Path to an authorization server's metadata, relative to its identifier.
+const WELL_KNOWN: &str = "/.well-known/oauth-authorization-server";
+
+// How long, in seconds, retrieved documents are cached.
+const CACHE_TTL: i64 = 300;
+
+// The minimum time, in seconds, between retrievals of a document that bypass
+// the cache. Limits the requests made on behalf of clients presenting tokens
+// signed with unknown keys.
+const REFRESH_INTERVAL: i64 = 60;
+
+// The maximum number of cached documents.
+const CACHE_SIZE: usize = 100;
+
+// Documents retrieved from authorization servers, keyed by URL.
+static CACHE: LazyLock<Mutex<HashMap<String, Cached>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+struct Cached {
+    data: Vec<u8>,
+    retrieved_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+/// Select the authorization server identified by `identifier` or, when not
+/// specified, the first server listed in the Credential Issuer's metadata.
+///
+/// Returns `None` when the Credential Issuer does not list any authorization
+/// servers, in which case it acts as its own authorization server.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidRequest` if the specified server is not listed in
+/// the Credential Issuer's metadata.
+pub fn select(issuer: &IssuerMetadata, identifier: Option<&str>) -> Result<Option<String>> {
+    let Some(servers) = &issuer.authorization_servers else {
+        if identifier.is_some() {
+            return Err(invalid!("issuer does not list `authorization_servers`"));
+        }
+        return Ok(None);
+    };
+
+    match identifier {
+        Some(id) if servers.iter().any(|s| s == id) => Ok(Some(id.to_string())),
+        Some(id) => Err(invalid!("{id} is not a listed authorization server")),
+        None => Ok(servers.first().cloned()),
+    }
+}
+
+/// Retrieve metadata for the authorization server identified by `identifier`,
+/// or for the Credential Issuer's own authorization server when `None`.
+///
+/// # Errors
+///
+/// Returns `Error::ServerError` if the metadata cannot be retrieved or does
+/// not belong to the identified server.
+pub async fn metadata(
+    issuer: &str, provider: &impl Provider, identifier: Option<&str>,
+) -> Result<ServerMetadata> {
+    let Ok(server) = Metadata::server(provider, issuer).await else {
+        return Err(server!("unknown authorization server"));
+    };
+    let Some(identifier) = identifier else {
+        return Ok(server);
+    };
+    if identifier == server.oauth.issuer {
+        return Ok(server);
+    }
+
+    let url = format!("{}{WELL_KNOWN}", identifier.trim_end_matches('/'));
+    let data = fetch(provider, &url, false)
+        .await
+        .map_err(|e| server!("issue retrieving authorization server metadata: {e}"))?;
+    let external: ServerMetadata =
+        serde_json::from_slice(&data).context("deserializing authorization server metadata")?;
+
+    if external.oauth.issuer != identifier {
+        return Err(server!("authorization server metadata `issuer` does not match {identifier}"));
+    }
+    Ok(external)
+}
+
+/// Whether the authorization server identified by `identifier` is the
+/// Credential Issuer's own authorization server. `None` identifies the
+/// Credential Issuer's own server.
+///
+/// # Errors
+///
+/// Returns `Error::ServerError` if the Credential Issuer's authorization
+/// server metadata cannot be retrieved.
+pub async fn is_local(
+    issuer: &str, provider: &impl Provider, identifier: Option<&str>,
+) -> Result<bool> {
+    let Some(identifier) = identifier else {
+        return Ok(true);
+    };
+    let Ok(server) = Metadata::server(provider, issuer).await else {
+        return Err(server!("unknown authorization server"));
+    };
+    Ok(identifier == server.oauth.issuer)
+}
+
+/// Retrieve the document published by an authorization server at `url`,
+/// using a cached copy when available. Set `refresh` to bypass the cache,
+/// for example when a key is missing from a cached JWK Set. The cache is
+/// bypassed at most once a minute for each document.
+///
+/// # Errors
+///
+/// Returns an error if the document cannot be retrieved.
+pub async fn fetch(provider: &impl Provider, url: &str, refresh: bool) -> anyhow::Result<Vec<u8>> {
+    let now = Utc::now();
+    {
+        let cache = CACHE.lock().map_err(|_| anyhow::anyhow!("cache lock poisoned"))?;
+        let fresh = |c: &&Cached| {
+            c.expires_at > now
+                && (!refresh || c.retrieved_at > now - TimeDelta::seconds(REFRESH_INTERVAL))
+        };
+        if let Some(cached) = cache.get(url).filter(fresh) {
+            return Ok(cached.data.clone());
+        }
+    }
+
+    let data = Resolver::resolve(provider, url).await?;
+
+    let mut cache = CACHE.lock().map_err(|_| anyhow::anyhow!("cache lock poisoned"))?;
+    cache.retain(|_, cached| cached.expires_at > now);
+    if cache.len() >= CACHE_SIZE && !cache.contains_key(url) {
+        let oldest = cache.iter().min_by_key(|(_, c)| c.expires_at).map(|(k, _)| k.clone());
+        if let Some(oldest) = oldest {
+            cache.remove(&oldest);
+        }
+    }
+    let cached = Cached {
+        data: data.clone(),
+        retrieved_at: now,
+        expires_at: now + TimeDelta::seconds(CACHE_TTL),
+    };
+    cache.insert(url.to_string(), cached);
+
+    Ok(data)
+}
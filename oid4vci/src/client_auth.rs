@@ -0,0 +1,319 @@
+//! # Client Authentication
+//!
+//! Authentication of confidential clients at the token, pushed authorization
+//! request, introspection, and revocation endpoints using JWT client assertions as defined in
+//! [RFC7523](https://www.rfc-editor.org/rfc/rfc7523.html).
+//!
+//! Clients registered with the `private_key_jwt` method sign assertions with
+//! a key from their registered JWK Set, while clients registered with the
+//! `client_secret_jwt` method use an HMAC of their client secret.
+//!
+//! Wallet instances, which are public clients, may instead authenticate using
+//! [OAuth 2.0 Attestation-Based Client Authentication]: an attestation of the
+//! Wallet issued by a trusted Wallet Provider, accompanied by a proof of
+//! possession of the attested key.
+//!
+//! [OAuth 2.0 Attestation-Based Client Authentication]: (https://datatracker.ietf.org/doc/draft-ietf-oauth-attestation-based-client-auth)
+
+use anyhow::{Context as _, anyhow};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::{DateTime, TimeDelta, Utc};
+use credibil_core::OneMany;
+use credibil_jose::{Jwt, KeyBinding, PublicKeyJwk, decode_jws};
+use credibil_proof::resolve_jwk;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::JwtType;
+use crate::handlers::{ClientAttestationHeaders, Error, Result};
+use crate::jwt::{CLOCK_SKEW, decode_header, first_use, use_nonce};
+use crate::oauth::{OAuthClient, TokenEndpointAuth};
+use crate::provider::{Metadata, NonceKind, Provider};
+use crate::state::Expire;
+use crate::types::{
+    ClientAssertion, ClientAssertionClaims, ClientAttestationClaims, ClientAttestationPopClaims,
+    ServerMetadata,
+};
+
+// State key prefix used to detect replayed client assertions.
+const JTI_PREFIX: &str = "client-jti:";
+
+// State key prefix used to detect replayed client attestation PoPs.
+const POP_JTI_PREFIX: &str = "attestation-jti:";
+
+/// Authenticate the client using its registered token endpoint
+/// authentication method.
+///
+/// Public clients (method `none`) must not provide an assertion. Confidential
+/// clients must provide a single-use assertion issued by the client to the
+/// authorization server. Clients registered to use attestation-based
+/// authentication must have presented a verified client `attestation`.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidClient` if the client cannot be authenticated.
+pub async fn authenticate(
+    issuer: &str, provider: &impl Provider, client: &OAuthClient,
+    assertion: Option<&ClientAssertion>, attestation: Option<&ClientAttestationClaims>,
+    server: &ServerMetadata,
+) -> Result<()> {
+    let method = client.token_endpoint_auth_method.clone().unwrap_or_default();
+    let supported = &server.oauth.token_endpoint_auth_methods_supported;
+    if supported.as_ref().is_some_and(|methods| !methods.contains(&method)) {
+        return Err(Error::InvalidClient("authentication method is not supported".to_string()));
+    }
+
+    if method == TokenEndpointAuth::AttestJwtClientAuth && attestation.is_none() {
+        return Err(Error::InvalidClient("client attestation is missing".to_string()));
+    }
+
+    let Some(ClientAssertion::JwtBearer { client_assertion }) = assertion else {
+        if matches!(method, TokenEndpointAuth::None | TokenEndpointAuth::AttestJwtClientAuth) {
+            return Ok(());
+        }
+        return Err(Error::InvalidClient("client assertion is missing".to_string()));
+    };
+
+    let claims = match method {
+        TokenEndpointAuth::None | TokenEndpointAuth::AttestJwtClientAuth => {
+            return Err(Error::InvalidClient(
+                "client is not registered for assertion authentication".to_string(),
+            ));
+        }
+        TokenEndpointAuth::PrivateKeyJwt => verify_signature(client_assertion, client).await?,
+        TokenEndpointAuth::ClientSecretJwt => verify_hmac(client_assertion, client)?,
+    };
+
+    // assertion must be issued by the client for this authorization server
+    if claims.iss != client.client_id || claims.sub != client.client_id {
+        return Err(Error::InvalidClient(
+            "assertion `iss` and `sub` must be client_id".to_string(),
+        ));
+    }
+    let audience = [
+        Some(server.oauth.token_endpoint.as_str()),
+        Some(server.oauth.issuer.as_str()),
+        server.oauth.pushed_authorization_request_endpoint.as_deref(),
+        server.oauth.introspection_endpoint.as_deref(),
+        server.oauth.revocation_endpoint.as_deref(),
+    ];
+    let accepted = |aud: &String| audience.contains(&Some(aud.as_str()));
+    let valid_aud = match &claims.aud {
+        OneMany::One(aud) => accepted(aud),
+        OneMany::Many(auds) => auds.iter().any(accepted),
+    };
+    if !valid_aud {
+        return Err(Error::InvalidClient("assertion `aud` is invalid".to_string()));
+    }
+
+    // assertions are short-lived, limiting how long their `jti` is retained
+    let skew = TimeDelta::seconds(CLOCK_SKEW);
+    let lifetime = Expire::Authorized.duration();
+    let now = Utc::now();
+    if claims.exp + skew < now {
+        return Err(Error::InvalidClient("assertion has expired".to_string()));
+    }
+    if claims.exp > now + lifetime + skew {
+        return Err(Error::InvalidClient("assertion `exp` is too far in the future".to_string()));
+    }
+    if let Some(iat) = claims.iat {
+        if iat > now + skew {
+            return Err(Error::InvalidClient("assertion `iat` is in the future".to_string()));
+        }
+        if iat < now - lifetime - skew {
+            return Err(Error::InvalidClient("assertion `iat` is too old".to_string()));
+        }
+    }
+
+    // assertions are single-use
+    if claims.jti.is_empty() {
+        return Err(Error::InvalidClient("assertion `jti` is missing".to_string()));
+    }
+    let jti_key = format!("{JTI_PREFIX}{}:{}", client.client_id, claims.jti);
+    if !first_use(issuer, provider, &jti_key, claims.exp + skew).await? {
+        return Err(Error::InvalidClient("assertion has already been used".to_string()));
+    }
+
+    Ok(())
+}
+
+/// Verify the client attestation and its proof of possession (PoP) presented
+/// by a Wallet instance, returning the attested Wallet metadata. Returns
+/// `None` when no attestation was presented.
+///
+/// The attestation must be signed by a trusted Wallet Provider and be issued
+/// to `client_id`, when provided. The PoP must be fresh, single-use, and
+/// signed by the attested key.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidClient` if the attestation or PoP is invalid.
+pub async fn verify_attestation(
+    issuer: &str, provider: &impl Provider, headers: &ClientAttestationHeaders,
+    client_id: Option<&str>, server: &ServerMetadata,
+) -> Result<Option<ClientAttestationClaims>> {
+    let (attestation, pop) = match (&headers.client_attestation, &headers.client_attestation_pop) {
+        (None, None) => return Ok(None),
+        (Some(attestation), Some(pop)) => (attestation, pop),
+        _ => {
+            return Err(Error::InvalidClient(
+                "client attestation and PoP must be provided together".to_string(),
+            ));
+        }
+    };
+
+    // attestation
+    let resolver = async |kid: String| resolve_jwk(&kid, provider).await;
+    let jwt: Jwt<ClientAttestationClaims> = decode_jws(attestation, resolver)
+        .await
+        .map_err(|e| Error::InvalidClient(format!("issue decoding client attestation: {e}")))?;
+    if jwt.header.typ != JwtType::ClientAttestation.to_string() {
+        return Err(Error::InvalidClient("invalid client attestation type".to_string()));
+    }
+
+    // the attestation must be signed by a trusted Wallet Provider
+    let KeyBinding::Kid(kid) = &jwt.header.key else {
+        return Err(Error::InvalidClient("client attestation 'kid' is missing".to_string()));
+    };
+    let wallet_provider = kid.split('#').next().unwrap_or_default();
+    if !Metadata::wallet_provider(provider, issuer, wallet_provider)
+        .await
+        .context("checking wallet provider")?
+    {
+        return Err(Error::InvalidClient(
+            "client attestation is not from a trusted wallet provider".to_string(),
+        ));
+    }
+
+    let claims = jwt.claims;
+    if claims.iss != wallet_provider {
+        return Err(Error::InvalidClient(
+            "client attestation `iss` must be the signing wallet provider".to_string(),
+        ));
+    }
+    if claims.exp <= Utc::now() {
+        return Err(Error::InvalidClient("client attestation has expired".to_string()));
+    }
+    if client_id.is_some_and(|id| id != claims.sub) {
+        return Err(Error::InvalidClient("client attestation `sub` must be client_id".to_string()));
+    }
+    let KeyBinding::Jwk(jwk) = &claims.cnf else {
+        return Err(Error::InvalidClient("client attestation `cnf` must be a JWK".to_string()));
+    };
+
+    // PoP, signed using the attested key
+    let resolver = async |_: String| Ok::<_, anyhow::Error>(jwk.clone());
+    let jwt: Jwt<ClientAttestationPopClaims> = decode_jws(pop, resolver)
+        .await
+        .map_err(|e| Error::InvalidClient(format!("issue decoding client attestation PoP: {e}")))?;
+    if jwt.header.typ != JwtType::ClientAttestationPop.to_string() {
+        return Err(Error::InvalidClient("invalid client attestation PoP type".to_string()));
+    }
+
+    let pop = jwt.claims;
+    if pop.iss != claims.sub {
+        return Err(Error::InvalidClient("client attestation PoP `iss` is invalid".to_string()));
+    }
+    if pop.aud != server.oauth.issuer {
+        return Err(Error::InvalidClient("client attestation PoP `aud` is invalid".to_string()));
+    }
+
+    // PoPs are short-lived and single-use
+    let skew = TimeDelta::seconds(CLOCK_SKEW);
+    let now = Utc::now();
+    if pop.iat > now + skew || pop.iat < now - Expire::Authorized.duration() - skew {
+        return Err(Error::InvalidClient("client attestation PoP is not fresh".to_string()));
+    }
+    if let Some(nonce) = &pop.nonce {
+        let Some(expires_at) = use_nonce(issuer, provider, NonceKind::Challenge, nonce).await?
+        else {
+            return Err(Error::InvalidClient(
+                "client attestation PoP nonce is invalid".to_string(),
+            ));
+        };
+        if expires_at <= now {
+            return Err(Error::InvalidClient(
+                "client attestation PoP nonce has expired".to_string(),
+            ));
+        }
+    }
+    if pop.jti.is_empty() {
+        return Err(Error::InvalidClient("client attestation PoP `jti` is missing".to_string()));
+    }
+    let jti_key = format!("{POP_JTI_PREFIX}{}:{}", pop.iss, pop.jti);
+    let expires_at = pop.iat + Expire::Authorized.duration() + skew;
+    if !first_use(issuer, provider, &jti_key, expires_at).await? {
+        return Err(Error::InvalidClient(
+            "client attestation PoP has already been used".to_string(),
+        ));
+    }
+
+    Ok(Some(claims))
+}
+
+// Verify an assertion signed using a key from the client's registered JWK
+// Set.
+async fn verify_signature(assertion: &str, client: &OAuthClient) -> Result<ClientAssertionClaims> {
+    let Some(jwks) = &client.jwks else {
+        return Err(Error::InvalidClient("client has no registered `jwks`".to_string()));
+    };
+    let Ok(jwks) = serde_json::from_str::<Value>(jwks) else {
+        return Err(Error::InvalidClient("client `jwks` is invalid".to_string()));
+    };
+    let keys = jwks.get("keys").and_then(Value::as_array).cloned().unwrap_or_default();
+
+    // find the key by `kid`, falling back to the only key in the set
+    let resolver = async |kid: String| {
+        let key = keys
+            .iter()
+            .find(|k| k.get("kid").and_then(Value::as_str) == Some(kid.as_str()))
+            .or_else(|| if keys.len() == 1 { keys.first() } else { None });
+        let Some(key) = key else {
+            return Err(anyhow!("no key in client `jwks` matches {kid}"));
+        };
+        Ok(serde_json::from_value::<PublicKeyJwk>(key.clone())?)
+    };
+
+    let jwt: Jwt<ClientAssertionClaims> = decode_jws(assertion, resolver)
+        .await
+        .map_err(|e| Error::InvalidClient(format!("issue verifying assertion: {e}")))?;
+    Ok(jwt.claims)
+}
+
+// Verify an assertion MAC'd (HS256) using the client's secret.
+fn verify_hmac(assertion: &str, client: &OAuthClient) -> Result<ClientAssertionClaims> {
+    let Some(secret) = &client.client_secret else {
+        return Err(Error::InvalidClient("client has no registered secret".to_string()));
+    };
+    // an expiry of 0 means the secret does not expire
+    let expired = client.client_secret_expires_at.filter(|exp| *exp != 0).is_some_and(|exp| {
+        DateTime::from_timestamp(exp, 0).is_none_or(|expires_at| expires_at < Utc::now())
+    });
+    if expired {
+        return Err(Error::InvalidClient("client secret has expired".to_string()));
+    }
+
+    decode_header(assertion, &["HS256".to_string()])
+        .map_err(|e| Error::InvalidClient(format!("assertion {e}")))?;
+    let Some((signing_input, signature)) = assertion.rsplit_once('.') else {
+        return Err(Error::InvalidClient("assertion is not a JWS".to_string()));
+    };
+    let Some((_, payload)) = signing_input.split_once('.') else {
+        return Err(Error::InvalidClient("assertion is not a JWS".to_string()));
+    };
+    let decode = |part: &str| {
+        Base64UrlUnpadded::decode_vec(part)
+            .map_err(|_| Error::InvalidClient("assertion is not base64url-encoded".to_string()))
+    };
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .map_err(|e| Error::ServerError(format!("issue creating HMAC: {e}")))?;
+    mac.update(signing_input.as_bytes());
+    if mac.verify_slice(&decode(signature)?).is_err() {
+        return Err(Error::InvalidClient("assertion signature is invalid".to_string()));
+    }
+
+    serde_json::from_slice(&decode(payload)?)
+        .map_err(|_| Error::InvalidClient("assertion claims are invalid".to_string()))
+}
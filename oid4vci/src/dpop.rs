@@ -0,0 +1,176 @@
+//! # DPoP Proofs
+//!
+//! Verification of DPoP proof JWTs used to sender-constrain access tokens as
+//! defined in [RFC9449](https://www.rfc-editor.org/rfc/rfc9449.html).
+//!
+//! The token endpoint binds an access token to the JWK thumbprint of the key
+//! used to sign the DPoP proof presented with the token request. Protected
+//! endpoints then require a DPoP proof signed by the same key.
+
+use std::collections::BTreeMap;
+
+use anyhow::Context as _;
+use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::{TimeDelta, Utc};
+use credibil_core::state::State;
+use credibil_jose::{Jwt, PublicKeyJwk, decode_jws};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::error::{DpopNonce, server};
+use crate::handlers::{Error, Result};
+use crate::jwt::{CLOCK_SKEW, decode_header, first_use};
+use crate::provider::{Metadata, Provider, StateStore};
+use crate::state::{Expire, Nonce};
+use crate::types::DPoPClaims;
+use crate::{JwtType, generate};
+
+// The HTTP method used by all DPoP-protected endpoints.
+const HTM: &str = "POST";
+
+// State key prefix used to detect replayed DPoP proofs.
+const JTI_PREFIX: &str = "dpop-jti:";
+
+// State key prefix used to save server-provided DPoP nonces.
+const NONCE_PREFIX: &str = "dpop-nonce:";
+
+/// Verify a DPoP proof presented to the endpoint at `htu`, returning the JWK
+/// SHA-256 thumbprint of the key the proof was signed with.
+///
+/// When an `access_token` is provided, the proof must contain a matching
+/// access token hash (`ath`).
+///
+/// # Errors
+///
+/// Returns `Error::InvalidDpopProof` if the proof is invalid or has been
+/// replayed, and `Error::UseDpopNonce` when a server-provided nonce is
+/// required but missing or unknown.
+pub async fn verify(
+    issuer: &str, provider: &impl Provider, proof: &str, htu: &str, access_token: Option<&str>,
+) -> Result<String> {
+    let Ok(server) = Metadata::server(provider, issuer).await else {
+        return Err(server!("unknown authorization server"));
+    };
+
+    let Some(algs) = &server.oauth.dpop_signing_alg_values_supported else {
+        return Err(Error::InvalidDpopProof("DPoP is not supported".to_string()));
+    };
+    let header = decode_header(proof, algs)
+        .map_err(|e| Error::InvalidDpopProof(format!("DPoP proof {e}")))?;
+    let Some(jwk_value) = header.get("jwk") else {
+        return Err(Error::InvalidDpopProof("DPoP proof `jwk` is missing".to_string()));
+    };
+    let Ok(jwk) = serde_json::from_value::<PublicKeyJwk>(jwk_value.clone()) else {
+        return Err(Error::InvalidDpopProof("DPoP proof `jwk` is invalid".to_string()));
+    };
+
+    let resolver = async |_: String| Ok::<_, anyhow::Error>(jwk.clone());
+    let jwt: Jwt<DPoPClaims> = decode_jws(proof, resolver)
+        .await
+        .map_err(|e| Error::InvalidDpopProof(format!("issue decoding JWT: {e}")))?;
+    if jwt.header.typ != JwtType::DPoP.to_string() {
+        return Err(Error::InvalidDpopProof("invalid DPoP proof type".to_string()));
+    }
+
+    let claims = jwt.claims;
+    if claims.htm != HTM {
+        return Err(Error::InvalidDpopProof("DPoP proof `htm` does not match".to_string()));
+    }
+    if normalize(&claims.htu) != normalize(htu) {
+        return Err(Error::InvalidDpopProof("DPoP proof `htu` does not match".to_string()));
+    }
+
+    // proofs are short-lived
+    let skew = TimeDelta::seconds(CLOCK_SKEW);
+    let now = Utc::now();
+    if claims.iat > now + skew {
+        return Err(Error::InvalidDpopProof("DPoP proof `iat` is in the future".to_string()));
+    }
+    if claims.iat < now - Expire::Authorized.duration() - skew {
+        return Err(Error::InvalidDpopProof("DPoP proof `iat` is too old".to_string()));
+    }
+
+    if access_token.is_some_and(|t| claims.ath.as_deref() != Some(access_token_hash(t).as_str())) {
+        return Err(Error::InvalidDpopProof("DPoP proof `ath` does not match".to_string()));
+    }
+
+    check_nonce(issuer, provider, claims.nonce.as_deref(), access_token.is_some()).await?;
+
+    // reject replayed proofs for as long as they would otherwise be accepted
+    if claims.jti.is_empty() {
+        return Err(Error::InvalidDpopProof("DPoP proof `jti` is missing".to_string()));
+    }
+    let jti_key = format!("{JTI_PREFIX}{}", claims.jti);
+    let expires_at = claims.iat + Expire::Authorized.duration() + skew;
+    if !first_use(issuer, provider, &jti_key, expires_at).await? {
+        return Err(Error::InvalidDpopProof("DPoP proof has been replayed".to_string()));
+    }
+
+    thumbprint(jwk_value)
+}
+
+/// Compute the access token hash (`ath`) used to bind a DPoP proof to an
+/// access token.
+#[must_use]
+pub fn access_token_hash(access_token: &str) -> String {
+    Base64UrlUnpadded::encode_string(&Sha256::digest(access_token.as_bytes()))
+}
+
+// Check the proof's `nonce` was issued by the server. A fresh nonce is
+// returned to the client when a nonce is required but missing or unknown.
+// Protected resources (`resource`) are presented with an access token.
+async fn check_nonce(
+    issuer: &str, provider: &impl Provider, nonce: Option<&str>, resource: bool,
+) -> Result<()> {
+    let valid = match nonce {
+        Some(nonce) => {
+            StateStore::get::<Nonce>(provider, issuer, &format!("{NONCE_PREFIX}{nonce}"))
+                .await
+                .is_ok_and(|state| !state.is_expired())
+        }
+        None => {
+            let configuration =
+                Metadata::configuration(provider, issuer).await.context("getting configuration")?;
+            !configuration.dpop_nonce_required
+        }
+    };
+    if valid {
+        return Ok(());
+    }
+
+    let nonce = generate::nonce();
+    let state = State {
+        body: Nonce { nonce: nonce.clone() },
+        expires_at: Utc::now() + Expire::Authorized.duration(),
+    };
+    StateStore::put(provider, issuer, &format!("{NONCE_PREFIX}{nonce}"), &state)
+        .await
+        .context("saving state")?;
+    Err(Error::UseDpopNonce(DpopNonce { nonce, resource }))
+}
+
+// Compute the JWK SHA-256 thumbprint of a public key as defined in RFC7638:
+// the hash of the key's required members, lexicographically ordered.
+fn thumbprint(jwk: &Value) -> Result<String> {
+    let members: &[&str] = match jwk.get("kty").and_then(Value::as_str) {
+        Some("EC") => &["crv", "kty", "x", "y"],
+        Some("OKP") => &["crv", "kty", "x"],
+        Some("RSA") => &["e", "kty", "n"],
+        _ => return Err(Error::InvalidDpopProof("DPoP proof `jwk` is unsupported".to_string())),
+    };
+
+    let mut required = BTreeMap::new();
+    for member in members {
+        let Some(value) = jwk.get(member) else {
+            return Err(Error::InvalidDpopProof(format!("DPoP proof `jwk` {member} is missing")));
+        };
+        required.insert(*member, value);
+    }
+    let canonical = serde_json::to_vec(&required).context("serializing JWK")?;
+    Ok(Base64UrlUnpadded::encode_string(&Sha256::digest(&canonical)))
+}
+
+// The HTTP URI without query and fragment parts.
+fn normalize(uri: &str) -> &str {
+    uri.split(['?', '#']).next().unwrap_or_default()
+}
@@ -6,9 +6,17 @@
 // TODO: add support for "client-state" in error responses.
 // TODO: use custom serialisation for Err enum.
 
-use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display};
+
+use http::header::{HeaderValue, WWW_AUTHENTICATE};
+use http::{HeaderMap, StatusCode};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use thiserror::Error;
 
+// The `WWW-Authenticate` challenge returned by protected resources requiring
+// a DPoP nonce.
+const DPOP_CHALLENGE: &str = r#"DPoP error="use_dpop_nonce", error_description="Resource server requires nonce in DPoP proof""#;
+
 /// `OpenID` error codes for  for Verifiable Credential Issuance and
 /// Presentation.
 #[derive(Error, Debug, Serialize, Deserialize)]
@@ -143,6 +151,86 @@ pub enum Error {
     /// Credential.
     #[error(r#"{{"error": "invalid_transaction_id", "error_description": "{0}"}}"#)]
     InvalidTransactionId(String),
+
+    /// The `notification_id` in the Notification Request was invalid, that
+    /// is, it was not issued by the Credential Issuer to the Wallet
+    /// presenting the access token.
+    #[error(r#"{{"error": "invalid_notification_id", "error_description": "{0}"}}"#)]
+    InvalidNotificationId(String),
+
+    /// The Notification Request is missing a required parameter, includes an
+    /// unsupported parameter or parameter value, repeats the same parameter,
+    /// or is otherwise malformed.
+    #[error(r#"{{"error": "invalid_notification_request", "error_description": "{0}"}}"#)]
+    InvalidNotificationRequest(String),
+
+    /// The DPoP proof presented with the request is missing, invalid, or
+    /// does not match the key the access token is bound to.
+    #[error(r#"{{"error": "invalid_dpop_proof", "error_description": "{0}"}}"#)]
+    InvalidDpopProof(String),
+
+    /// The DPoP proof must include a server-provided nonce. The error
+    /// contains a fresh nonce the Client should use in a new DPoP proof,
+    /// returned in the `DPoP-Nonce` response header rather than the body.
+    #[error(r#"{{"error": "use_dpop_nonce", "error_description": "{0}"}}"#)]
+    UseDpopNonce(DpopNonce),
+}
+
+impl Error {
+    /// The HTTP status and headers for a `use_dpop_nonce` error response.
+    ///
+    /// The fresh nonce is returned in the `DPoP-Nonce` header. The
+    /// authorization server responds with `400 Bad Request` while protected
+    /// resources, such as the Credential Endpoint, respond with
+    /// `401 Unauthorized` and a `WWW-Authenticate` challenge.
+    #[must_use]
+    pub fn dpop_nonce_response(&self) -> Option<(StatusCode, HeaderMap)> {
+        let Self::UseDpopNonce(challenge) = self else {
+            return None;
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("dpop-nonce", HeaderValue::from_str(&challenge.nonce).ok()?);
+        if !challenge.resource {
+            return Some((StatusCode::BAD_REQUEST, headers));
+        }
+        headers.insert(WWW_AUTHENTICATE, HeaderValue::from_static(DPOP_CHALLENGE));
+        Some((StatusCode::UNAUTHORIZED, headers))
+    }
+}
+
+/// A fresh server-provided nonce for the Client to use in its next DPoP
+/// proof. Only the error description is serialized in the error body.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DpopNonce {
+    /// The nonce to return in the `DPoP-Nonce` response header.
+    pub nonce: String,
+
+    /// Whether the nonce was required by a protected resource rather than
+    /// the authorization server.
+    pub resource: bool,
+}
+
+impl Display for DpopNonce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let server = if self.resource { "resource server" } else { "authorization server" };
+        write!(f, "{server} requires nonce in DPoP proof")
+    }
+}
+
+impl Serialize for DpopNonce {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for DpopNonce {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let description = String::deserialize(deserializer)?;
+        Ok(Self {
+            nonce: String::new(),
+            resource: description.starts_with("resource"),
+        })
+    }
 }
 
 impl From<anyhow::Error> for Error {
@@ -189,6 +277,14 @@ impl From<anyhow::Error> for Error {
             Some(Self::InvalidTransactionId(e)) => {
                 Self::InvalidTransactionId(format!("{err}: {e}"))
             }
+            Some(Self::InvalidNotificationId(e)) => {
+                Self::InvalidNotificationId(format!("{err}: {e}"))
+            }
+            Some(Self::InvalidNotificationRequest(e)) => {
+                Self::InvalidNotificationRequest(format!("{err}: {e}"))
+            }
+            Some(Self::InvalidDpopProof(e)) => Self::InvalidDpopProof(format!("{err}: {e}")),
+            Some(Self::UseDpopNonce(n)) => Self::UseDpopNonce(n.clone()),
             None => {
                 let source = err.source().map_or_else(String::new, ToString::to_string);
                 Self::ServerError(format!("{err}: {source}"))
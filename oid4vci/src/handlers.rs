@@ -5,27 +5,34 @@
 //! serialized to a JSON object.
 
 mod authorize;
+mod challenge;
 mod create_offer;
 mod credential;
 mod credential_offer;
 mod deferred;
+mod introspection;
 mod metadata;
 mod nonce;
 mod notification;
 mod par;
 mod register;
+mod revocation;
 mod server;
 mod token;
 
 use std::fmt::Debug;
 
 pub use credibil_core::api::{Body, Handler, Headers, NoHeaders, Request, Response};
+use credibil_core::state::State;
 use http::HeaderMap;
-use http::header::ACCEPT_LANGUAGE;
+use http::header::{ACCEPT_LANGUAGE, AUTHORIZATION};
 use tracing::instrument;
 
 pub use crate::error::Error;
-use crate::provider::Provider;
+use crate::provider::{Provider, StateStore, issuance_events};
+use crate::state::Token;
+use crate::types::{AuthorizedDetail, IssuanceEvent, IssuanceEventKind};
+use crate::{access_token, dpop};
 
 /// Result type for `OpenID` for Verifiable Credential Issuance.
 pub type Result<T, E = Error> = anyhow::Result<T, E>;
@@ -66,15 +73,116 @@ pub type MetadataHeaders = LanguageHeader;
 /// Notification request headers.
 pub type NotificationHeaders = AuthorizationHeader;
 
+/// Pushed Authorization Request headers.
+pub type ParHeaders = ClientAttestationHeaders;
+
 /// Registration request headers.
 pub type RegistrationHeaders = AuthorizationHeader;
 
+/// Token request headers.
+#[derive(Clone, Debug, Default)]
+pub struct TokenHeaders {
+    /// The `DPoP` header (DPoP proof JWT). When set, the issued access token
+    /// is bound to the proof's key.
+    pub dpop: Option<String>,
+
+    /// Client attestation headers used by Wallets to authenticate.
+    pub attestation: ClientAttestationHeaders,
+}
+
+impl From<HeaderMap> for TokenHeaders {
+    fn from(headers: HeaderMap) -> Self {
+        Self {
+            dpop: dpop_header(&headers),
+            attestation: ClientAttestationHeaders::from(headers),
+        }
+    }
+}
+
+/// OAuth 2.0 Attestation-Based Client Authentication headers, used by Wallet
+/// instances to authenticate at the token and pushed authorization request
+/// endpoints.
+#[derive(Clone, Debug, Default)]
+pub struct ClientAttestationHeaders {
+    /// The `OAuth-Client-Attestation` header: a Wallet Provider's attestation
+    /// of the Wallet instance.
+    pub client_attestation: Option<String>,
+
+    /// The `OAuth-Client-Attestation-PoP` header: the Wallet instance's proof
+    /// of possession of the attested key.
+    pub client_attestation_pop: Option<String>,
+}
+
+impl From<HeaderMap> for ClientAttestationHeaders {
+    fn from(headers: HeaderMap) -> Self {
+        let header =
+            |name: &str| headers.get(name).and_then(|h| h.to_str().ok()).map(ToString::to_string);
+        Self {
+            client_attestation: header("oauth-client-attestation"),
+            client_attestation_pop: header("oauth-client-attestation-pop"),
+        }
+    }
+}
+
 /// An authorization-only header for use by handlers that soley require
 /// authorization.
 #[derive(Clone, Debug)]
 pub struct AuthorizationHeader {
     /// The authorization header (access token).
     pub authorization: String,
+
+    /// The `DPoP` header (DPoP proof JWT). Required when the access token is
+    /// DPoP-bound.
+    pub dpop: Option<String>,
+}
+
+impl From<HeaderMap> for AuthorizationHeader {
+    fn from(headers: HeaderMap) -> Self {
+        let authorization = headers
+            .get(AUTHORIZATION)
+            .and_then(|h| h.to_str().ok())
+            .map(|h| h.split_once(' ').map_or(h, |(_, token)| token).to_string())
+            .unwrap_or_default();
+        Self {
+            authorization,
+            dpop: dpop_header(&headers),
+        }
+    }
+}
+
+impl AuthorizationHeader {
+    // Retrieve the access token's state. JWT access tokens are verified by
+    // signature rather than retrieved from state. DPoP-bound tokens must be
+    // presented with a DPoP proof for the endpoint at `htu` signed by the
+    // bound key.
+    pub(crate) async fn verify(
+        &self, issuer: &str, provider: &impl Provider, htu: &str,
+    ) -> Result<State<Token>> {
+        let state = if access_token::is_jwt(&self.authorization) {
+            access_token::verify(issuer, provider, &self.authorization).await?
+        } else {
+            let Ok(state) = StateStore::get::<Token>(provider, issuer, &self.authorization).await
+            else {
+                return Err(Error::AccessDenied("invalid access token".to_string()));
+            };
+            state
+        };
+
+        if let Some(jkt) = &state.body.dpop_jkt {
+            let Some(proof) = &self.dpop else {
+                return Err(Error::InvalidDpopProof("DPoP proof is missing".to_string()));
+            };
+            let thumbprint =
+                dpop::verify(issuer, provider, proof, htu, Some(&self.authorization)).await?;
+            if &thumbprint != jkt {
+                return Err(Error::InvalidDpopProof(
+                    "DPoP key does not match access token".to_string(),
+                ));
+            }
+        }
+
+        Ok(state)
+    }
 }
 
 /// An language-only header for use by handlers that soley require
@@ -92,3 +200,29 @@ impl From<HeaderMap> for LanguageHeader {
         Self { accept_language }
     }
 }
+
+// Record an issuance lifecycle event with the registered observer. Events are
+// recorded once the step they describe has taken effect, so a failure to
+// record one is logged rather than returned to the client.
+fn record(issuer: &str, kind: IssuanceEventKind) {
+    let Some(events) = issuance_events() else {
+        return;
+    };
+    let event = IssuanceEvent::from(kind);
+    if let Err(e) = events.record(issuer, &event) {
+        tracing::warn!("issue recording issuance event {event:?}: {e}");
+    }
+}
+
+// The `credential_configuration_id`s of authorized credentials.
+fn configuration_ids(details: &[AuthorizedDetail]) -> Vec<String> {
+    details
+        .iter()
+        .filter_map(AuthorizedDetail::credential_configuration_id)
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn dpop_header(headers: &HeaderMap) -> Option<String> {
+    headers.get("dpop").and_then(|h| h.to_str().ok()).map(ToString::to_string)
+}
@@ -17,13 +17,13 @@ use credibil_core::state::State;
 
 use crate::error::{invalid, server};
 use crate::generate;
-use crate::handlers::{Body, Error, Handler, Request, Response, Result};
+use crate::handlers::{Body, Error, Handler, Request, Response, Result, configuration_ids, record};
 use crate::oauth::GrantType;
 use crate::provider::{Metadata, Provider, StateStore, Subject};
-use crate::state::{Authorized, Expire, Offered};
+use crate::state::{Authorized, Expire, Offered, Pushed};
 use crate::types::{
     AuthorizationDefinition, AuthorizationDetail, AuthorizationDetailType, AuthorizationRequest,
-    AuthorizationResponse, AuthorizedDetail, IssuerMetadata, RequestObject,
+    AuthorizationResponse, AuthorizedDetail, IssuanceEventKind, IssuerMetadata, RequestObject,
 };
 
 /// Authorization request handler.
@@ -35,20 +35,21 @@ use crate::types::{
 async fn authorize(
     issuer: &str, provider: &impl Provider, request: AuthorizationRequest,
 ) -> Result<AuthorizationResponse> {
-    // request object or URI (Pushed Authorization Request)
+    // request object or URI (Pushed Authorization Request), along with the
+    // Wallet attested when the request was pushed
     let mut is_par = false;
-    let request = match request {
-        AuthorizationRequest::Object(request) => request,
+    let (request, wallet) = match request {
+        AuthorizationRequest::Object(request) => (request, None),
         AuthorizationRequest::Uri(uri) => {
             is_par = true;
-            let state = StateStore::get::<RequestObject>(provider, issuer, &uri.request_uri)
+            let state = StateStore::get::<Pushed>(provider, issuer, &uri.request_uri)
                 .await
                 .context("retrieving state")?;
 
             if state.expires_at < Utc::now() {
                 return Err(invalid!("`request_uri` has expired"));
             }
-            state.body.clone()
+            (state.body.request, state.body.wallet)
         }
     };
 
@@ -68,9 +69,10 @@ async fn authorize(
     let mut details = vec![];
 
     for (config_id, mut auth_det) in ctx.auth_dets.clone() {
-        let identifiers = Subject::authorize(provider, issuer, &request.subject_id, &config_id)
-            .await
-            .map_err(|e| Error::AccessDenied(format!("issue authorizing subject: {e}")))?;
+        let identifiers =
+            Subject::authorize(provider, issuer, &request.subject_id, &config_id, wallet.as_ref())
+                .await
+                .map_err(|e| Error::AccessDenied(format!("issue authorizing subject: {e}")))?;
 
         auth_det.credential = AuthorizationDefinition::ConfigurationId {
             credential_configuration_id: config_id.clone(),
@@ -97,6 +99,7 @@ async fn authorize(
             code_challenge: request.code_challenge,
             code_challenge_method: request.code_challenge_method,
             details,
+            scope: (!ctx.scopes.is_empty()).then(|| ctx.scopes.join(" ")),
             client_id: request.client_id,
             redirect_uri: request.redirect_uri.clone(),
         },
@@ -105,6 +108,13 @@ async fn authorize(
     let code = generate::auth_code();
     StateStore::put(provider, issuer, &code, &state).await.context("saving authorization state")?;
 
+    let event = IssuanceEventKind::AuthorizationGranted {
+        subject_id: state.body.subject_id,
+        client_id: state.body.client_id,
+        credential_configuration_ids: configuration_ids(&state.body.details),
+    };
+    record(issuer, event);
+
     // remove offer state
     if let Some(issuer_state) = &request.issuer_state {
         StateStore::purge(provider, issuer, issuer_state).await.context("purging offer state")?;
@@ -133,6 +143,7 @@ impl Body for AuthorizationRequest {}
 pub struct Context {
     pub issuer: IssuerMetadata,
     pub auth_dets: HashMap<String, AuthorizationDetail>,
+    pub scopes: Vec<String>,
     pub is_par: bool,
 }
 
@@ -147,11 +158,21 @@ impl Context {
                 request.client_id
             )));
         };
-        // TODO: support authorization issuers
         let Ok(server) = Metadata::server(provider, &self.issuer.credential_issuer).await else {
             return Err(invalid!("invalid `credential_issuer`"));
         };
 
+        // When the issuer lists `authorization_servers`, this server must be
+        // one of them.
+        if self
+            .issuer
+            .authorization_servers
+            .as_ref()
+            .is_some_and(|s| !s.contains(&server.oauth.issuer))
+        {
+            return Err(server!("authorization server is not listed by the issuer"));
+        }
+
         // If the server requires pushed authorization requests, the request
         // must be a PAR.
         if server.oauth.require_pushed_authorization_requests.is_some_and(|par| par && !self.is_par)
@@ -223,7 +244,7 @@ impl Context {
         }
         // verify scope
         if let Some(scope) = &request.scope {
-            self.verify_scope(scope)?;
+            self.verify_scope(scope);
         }
 
         // redirect_uri
@@ -275,6 +296,11 @@ impl Context {
                     "invalid authorization_details type".to_string(),
                 ));
             }
+            if !self.issuer.is_located(detail.locations.as_deref()) {
+                return Err(Error::InvalidAuthorizationDetails(
+                    "`locations` must contain the credential issuer".to_string(),
+                ));
+            }
 
             // verify requested claims
             let config_id = match &detail.credential {
@@ -310,39 +336,32 @@ impl Context {
         Ok(())
     }
 
-    // Verify Credentials requested in `scope` are supported.
+    // Map each `scope` value requesting a Credential to its credential
+    // configuration. Other values, such as `openid` or `offline_access`, are
+    // not for the Credential Issuer and are ignored.
     // N.B. has side effect of saving valid scope items into context for later use.
-    fn verify_scope(&mut self, scope: &str) -> Result<()> {
-        if let Some(scope_item) = scope.split_whitespace().next() {
-            // find supported configuration with the requested scope
-            let mut found = false;
-            for (config_id, cred_cfg) in &self.issuer.credential_configurations_supported {
-                // `authorization_details` credential request takes precedence `scope` request
-                if self.auth_dets.contains_key(config_id) {
-                    continue;
-                }
-
-                // save scope item + credential_configuration_id
-                if cred_cfg.scope == Some(scope_item.to_string()) {
-                    let detail = AuthorizationDetail {
-                        r#type: AuthorizationDetailType::OpenIdCredential,
-                        credential: AuthorizationDefinition::ConfigurationId {
-                            credential_configuration_id: config_id.clone(),
-                        },
-                        claims: None,
-                        locations: None,
-                    };
+    fn verify_scope(&mut self, scope: &str) {
+        for scope_item in scope.split_whitespace() {
+            let Some(config_id) = self.issuer.scope_configuration_id(scope_item) else {
+                continue;
+            };
 
-                    self.auth_dets.insert(config_id.clone(), detail);
-                    found = true;
-                    break;
-                }
-            }
-            if !found {
-                return Err(Error::InvalidScope("invalid scope".to_string()));
+            // `authorization_details` credential request takes precedence `scope` request
+            if self.auth_dets.contains_key(config_id) {
+                continue;
             }
-        }
 
-        Ok(())
+            // save scope item + credential_configuration_id
+            let detail = AuthorizationDetail {
+                r#type: AuthorizationDetailType::OpenIdCredential,
+                credential: AuthorizationDefinition::ConfigurationId {
+                    credential_configuration_id: config_id.clone(),
+                },
+                claims: None,
+                locations: self.issuer.locations(),
+            };
+            self.auth_dets.insert(config_id.clone(), detail);
+            self.scopes.push(scope_item.to_string());
+        }
     }
 }
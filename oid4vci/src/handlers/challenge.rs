@@ -0,0 +1,55 @@
+//! # Challenge Endpoint
+//!
+//! This endpoint allows a Client to acquire a fresh attestation challenge to
+//! use as the `nonce` in a client attestation PoP.
+//!
+//! Challenges are held separately from the `c_nonce` values issued by the
+//! Nonce Endpoint, so one cannot be used in place of the other.
+
+use anyhow::Context as _;
+use chrono::Utc;
+
+use crate::generate;
+use crate::handlers::{Body, Error, Handler, Request, Response, Result};
+use crate::provider::{NonceKind, NonceStore, Provider};
+use crate::state::Expire;
+use crate::types::{ChallengeRequest, ChallengeResponse};
+
+/// Challenge request handler.
+///
+/// # Errors
+///
+/// Returns an `OpenID4VP` error if the request is invalid or if the provider is
+/// not available.
+async fn challenge(
+    issuer: &str, provider: &impl Provider, _: ChallengeRequest,
+) -> Result<ChallengeResponse> {
+    let attestation_challenge = generate::nonce();
+
+    let expires_at = Utc::now() + Expire::Authorized.duration();
+    NonceStore::put_nonce(
+        provider,
+        issuer,
+        NonceKind::Challenge,
+        &attestation_challenge,
+        expires_at,
+    )
+    .await
+    .context("saving challenge")?;
+
+    Ok(ChallengeResponse {
+        attestation_challenge,
+    })
+}
+
+impl<P: Provider> Handler<ChallengeResponse, P> for Request<ChallengeRequest> {
+    type Error = Error;
+
+    async fn handle(
+        self, issuer: &str, provider: &P,
+    ) -> Result<impl Into<Response<ChallengeResponse>>, Self::Error> {
+        challenge(issuer, provider, self.body).await
+    }
+}
+
+impl Body for ChallengeRequest {}
@@ -13,21 +13,24 @@ use credibil_core::state::State;
 use http::StatusCode;
 
 use crate::error::{invalid, server};
-use crate::generate;
-use crate::handlers::{Body, Error, Handler, Request, Response, Result};
+use crate::handlers::{Body, Error, Handler, Request, Response, Result, record};
 use crate::oauth::GrantType;
 use crate::provider::{Metadata, Provider, StateStore, Subject};
 use crate::state::{Expire, Offered};
 use crate::types::{
     AuthorizationCodeGrant, AuthorizationDefinition, AuthorizationDetail, AuthorizationDetailType,
     AuthorizedDetail, CreateOfferRequest, CreateOfferResponse, CredentialOffer, Grants,
-    IssuerMetadata, OfferType, PreAuthorizedCodeGrant, SendBy, ServerMetadata, TxCode,
+    IssuanceEventKind, IssuerMetadata, OfferType, PreAuthorizedCodeGrant, SendBy, ServerMetadata,
+    TxCode,
 };
+use crate::{authorization_server, generate};
 
 #[derive(Debug, Default)]
 struct Context {
     issuer: IssuerMetadata,
     server: ServerMetadata,
+    authorization_server: Option<String>,
+    local_server: bool,
 }
 
 /// Credential Offer request handler generates and returns a Credential Offer.
@@ -36,11 +39,20 @@ async fn create_offer(
 ) -> Result<Response<CreateOfferResponse>> {
     let iss = Metadata::issuer(provider, issuer).await.context("getting issuer metadata")?;
 
-    // TODO: determine how to select correct server?
-    // select `authorization_server`, if specified
-    let server = Metadata::server(provider, issuer).await.context("getting server metadata")?;
-
-    let ctx = Context { issuer: iss, server };
+    // the Wallet obtains its access token from the selected authorization server
+    let authorization_server =
+        authorization_server::select(&iss, request.authorization_server.as_deref())?;
+    let server =
+        authorization_server::metadata(issuer, provider, authorization_server.as_deref()).await?;
+    let local_server =
+        authorization_server::is_local(issuer, provider, authorization_server.as_deref()).await?;
+
+    let ctx = Context {
+        issuer: iss,
+        server,
+        authorization_server,
+        local_server,
+    };
 
     request.verify(&ctx)?;
 
@@ -58,7 +70,7 @@ async fn create_offer(
         || grant_types.contains(&GrantType::AuthorizationCode)
     {
         let auth_items = if grant_types.contains(&GrantType::PreAuthorizedCode) {
-            Some(authorize(issuer, provider, &request).await?)
+            Some(authorize(issuer, provider, &request, &ctx).await?)
         } else {
             None
         };
@@ -75,6 +87,13 @@ async fn create_offer(
         StateStore::put(provider, issuer, &state_key, &state).await.context("saving state")?;
     }
 
+    let event = IssuanceEventKind::OfferCreated {
+        subject_id: request.subject_id.clone(),
+        credential_configuration_ids: request.credential_configuration_ids.clone(),
+        grant_types,
+    };
+    record(issuer, event);
+
     // respond with Offer object or uri?
     if request.send_by == SendBy::ByVal {
         return Ok(Response {
@@ -153,6 +172,14 @@ impl CreateOfferRequest {
             if grant_types.contains(&GrantType::PreAuthorizedCode) && self.subject_id.is_none() {
                 return Err(invalid!("`subject_id` is required for pre-authorization"));
             }
+
+            // pre-authorized codes can only be redeemed at the issuer's own
+            // authorization server
+            if grant_types.contains(&GrantType::PreAuthorizedCode) && !ctx.local_server {
+                return Err(invalid!(
+                    "pre-authorized codes must use the issuer's own authorization server"
+                ));
+            }
         }
 
         Ok(())
@@ -162,11 +189,7 @@ impl CreateOfferRequest {
     fn create_offer(&self, ctx: &Context) -> CredentialOffer {
         let auth_code = generate::auth_code();
         let grant_types = self.grant_types.clone().unwrap_or_default();
-
-        // TODO: determine how to select correct server?
-        // select `authorization_server`, if specified
-        let authorization_server =
-            ctx.issuer.authorization_servers.as_ref().map(|servers| servers[0].clone());
+        let authorization_server = ctx.authorization_server.clone();
 
         let mut grants = Grants {
             authorization_code: None,
@@ -216,7 +239,7 @@ impl CreateOfferRequest {
 
 /// Authorize requested credentials for the subject.
 async fn authorize(
-    issuer: &str, provider: &impl Provider, request: &CreateOfferRequest,
+    issuer: &str, provider: &impl Provider, request: &CreateOfferRequest, ctx: &Context,
 ) -> Result<Vec<AuthorizedDetail>> {
     // skip authorization if not pre-authorized
 
@@ -224,7 +247,7 @@ async fn authorize(
     let subject_id = request.subject_id.clone().unwrap_or_default();
 
     for config_id in request.credential_configuration_ids.clone() {
-        let identifiers = Subject::authorize(provider, issuer, &subject_id, &config_id)
+        let identifiers = Subject::authorize(provider, issuer, &subject_id, &config_id, None)
             .await
             .context("authorizing holder")?;
 
@@ -235,7 +258,7 @@ async fn authorize(
                     credential_configuration_id: config_id.clone(),
                 },
                 claims: None,
-                locations: None,
+                locations: ctx.issuer.locations(),
             },
             credential_identifiers: identifiers,
         });
@@ -10,60 +10,84 @@
 
 use std::collections::HashSet;
 use std::fmt::Debug;
+use std::slice;
 
-use anyhow::Context as _;
-use chrono::Utc;
+use anyhow::{Context as _, anyhow};
+use base64ct::{Base64, Base64UrlUnpadded, Encoding};
+use chrono::{DateTime, TimeDelta, Utc};
 use credibil_core::state::State;
-use credibil_jose::{Jwt, KeyBinding, decode_jws};
+use credibil_jose::{Jwt, KeyBinding, PublicKeyJwk, decode_jws};
 use credibil_proof::resolve_jwk;
-use credibil_status::{StatusList, StatusStore, TokenBuilder};
+use credibil_status::{
+    BitstringStatusListEntry, IssuedCredential, Registry, StatusClaim, StatusListEntry,
+    StatusListManager, StatusPurpose, StatusStore,
+};
 use credibil_vdc::FormatProfile;
 use credibil_vdc::mso_mdoc::MdocBuilder;
 use credibil_vdc::sd_jwt::SdJwtVcBuilder;
 use credibil_vdc::w3c_vc::W3cVcBuilder;
+use serde_json::{Value, json};
+use x509_cert::Certificate;
+use x509_cert::der::Decode;
+use x509_cert::der::oid::ObjectIdentifier;
 
 use crate::error::server;
-use crate::handlers::{Body, CredentialHeaders, Error, Handler, Request, Response, Result};
-use crate::provider::{Metadata, Provider, StateStore, Subject};
-use crate::state::{Deferred, Expire, Token};
+use crate::handlers::{Body, CredentialHeaders, Error, Handler, Request, Response, Result, record};
+use crate::jwt::{CLOCK_SKEW, decode_header, use_nonce};
+use crate::provider::{Metadata, NonceKind, Provider, StateStore, Subject};
+use crate::state::{Deferred, Expire, Issued, Token};
 use crate::types::{
-    AuthorizedDetail, Credential, CredentialConfiguration, CredentialRequest, CredentialResponse,
-    Dataset, IssuerMetadata, MultipleProofs, Proof, ProofClaims, RequestBy, SingleProof,
+    AttestationClaims, AuthorizedDetail, BindingMethod, Credential, CredentialConfiguration,
+    CredentialRequest, CredentialResponse, Dataset, IssuanceEventKind, IssuerMetadata,
+    MultipleProofs, Proof, ProofClaims, ProofTypesSupported, RequestBy, SingleProof,
 };
 use crate::{JwtType, generate};
 
+// Proof types
+const JWT: &str = "jwt";
+const ATTESTATION: &str = "attestation";
+
+// The default minimum number of seconds a Wallet should wait between requests
+// to the Deferred Credential Endpoint.
+const INTERVAL: i64 = 5;
+
+// X.509 public key algorithm and curve identifiers.
+const ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+const EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const SECP256K1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.10");
+const SECP256R1: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+
 /// Credential request handler.
 ///
 /// # Errors
 ///
 /// Returns an `OpenID4VCI` error if the request is invalid or if the provider is
 /// not available.
-pub async fn credential(
+async fn credential(
     issuer: &str, provider: &impl Provider, request: Request<CredentialRequest, CredentialHeaders>,
 ) -> Result<CredentialResponse> {
-    let Ok(state) =
-        StateStore::get::<Token>(provider, issuer, &request.headers.authorization).await
-    else {
-        return Err(Error::AccessDenied("invalid access token".to_string()));
-    };
+    let metadata = Metadata::issuer(provider, issuer).await.context("fetching metadata")?;
+    let state = request.headers.verify(issuer, provider, &metadata.credential_endpoint).await?;
+    process(issuer, provider, state, metadata, request.body).await
+}
 
+// Process a credential request authorized by the access token `state`,
+// issuing or deferring the requested credential.
+async fn process(
+    issuer: &str, provider: &impl Provider, state: State<Token>, metadata: IssuerMetadata,
+    request: CredentialRequest,
+) -> Result<CredentialResponse> {
     // create a request context for data accessed more than once
     let mut ctx = Context {
         state,
-        issuer: Metadata::issuer(provider, issuer).await.context("fetching metadata")?,
+        issuer: metadata,
+        configuration_id: String::new(),
         configuration: CredentialConfiguration::default(),
-        proof_kids: vec![],
+        proof_keys: vec![],
     };
 
-    let request = request.body;
     let authorized = request.authorized_detail(&ctx)?;
 
-    // check whether issuance should be deferred
-    let dataset = ctx.dataset(issuer, provider, &request, &authorized).await?;
-    if dataset.pending {
-        return ctx.defer(issuer, provider, request).await;
-    }
-
     // credential configuration
     let Some(config_id) = authorized.credential_configuration_id() else {
         return Err(Error::InvalidCredentialRequest("no credential_configuration_id".to_string()));
@@ -72,8 +96,79 @@ pub async fn credential(
         return Err(server!("credential configuration unable to be found"));
     };
 
+    ctx.configuration_id = config_id.to_string();
     ctx.configuration = config.clone();
     request.verify(issuer, provider, &mut ctx).await?;
+
+    // check whether issuance should be deferred, retaining the verified proof
+    // keys for when the credential is ready
+    let identifier = ctx.identifier(issuer, provider, &request, &authorized).await?;
+    let dataset = ctx.dataset(issuer, provider, &identifier, &authorized).await?;
+    if dataset.pending {
+        return ctx.defer(issuer, provider, &identifier, &authorized, &dataset).await;
+    }
+    ctx.issue(issuer, provider, dataset).await
+}
+
+/// Issue the credential for a deferred issuance transaction using the proof
+/// keys captured when issuance was deferred.
+///
+/// # Errors
+///
+/// Returns `Error::IssuancePending` when the issuer is not yet ready to issue
+/// the credential, or an `OpenID4VCI` error if the provider is not available.
+pub async fn issue_deferred(
+    issuer: &str, provider: &impl Provider, state: State<Token>, metadata: IssuerMetadata,
+    deferred: Deferred,
+) -> Result<CredentialResponse> {
+    if state.is_expired() {
+        return Err(Error::InvalidCredentialRequest("token state expired".to_string()));
+    }
+    if state.body.subject_id != deferred.subject_id {
+        return Err(Error::InvalidTransactionId(
+            "transaction was not deferred for the token's subject".to_string(),
+        ));
+    }
+
+    // the access token must still authorize the deferred credential: tokens
+    // that do not identify datasets authorize by configuration alone
+    let identifier = &deferred.credential_identifier;
+    let config_id = deferred.credential_configuration_id;
+    let authorizes = |ad: &AuthorizedDetail| {
+        ad.credential_configuration_id() == Some(config_id.as_str())
+            && (ad.credential_identifiers.is_empty()
+                || ad.credential_identifiers.contains(identifier))
+    };
+    if !state.body.authorized_details.iter().any(authorizes) {
+        return Err(Error::InvalidCredentialRequest(
+            "unauthorized credential requested".to_string(),
+        ));
+    }
+
+    let Some(configuration) = metadata.credential_configurations_supported.get(&config_id).cloned()
+    else {
+        return Err(server!("credential configuration unable to be found"));
+    };
+
+    let ctx = Context {
+        state,
+        issuer: metadata,
+        configuration_id: config_id,
+        configuration,
+        proof_keys: deferred.proof_keys,
+    };
+
+    // is the issuer ready to issue the credential?
+    let subject_id = &ctx.state.body.subject_id;
+    let mut dataset =
+        Subject::deferred(provider, issuer, subject_id, &deferred.transaction_id, identifier)
+            .await
+            .context("populating claims")?;
+    if dataset.pending {
+        return Err(Error::IssuancePending(dataset.interval.unwrap_or(INTERVAL)));
+    }
+
+    retain_authorized(&mut dataset, &deferred.authorized);
     ctx.issue(issuer, provider, dataset).await
 }
 
@@ -93,18 +188,12 @@ impl Body for CredentialRequest {}
 struct Context {
     state: State<Token>,
     issuer: IssuerMetadata,
+    configuration_id: String,
     configuration: CredentialConfiguration,
-    proof_kids: Vec<String>,
+    proof_keys: Vec<KeyBinding>,
 }
 
 impl CredentialRequest {
-    // TODO: check this list for compliance
-    // To validate a key proof, ensure that:
-    //   - the header parameter does not contain a private key
-    //   - the creation time of the JWT, as determined by either the issuance time,
-    //     or a server managed timestamp via the nonce claim, is within an
-    //     acceptable window (see Section 11.5).
-
     // Verify the credential request
     async fn verify(
         &self, issuer: &str, provider: &impl Provider, ctx: &mut Context,
@@ -121,69 +210,63 @@ impl CredentialRequest {
                 return Err(Error::InvalidProof("proof not set".to_string()));
             };
 
-            // TODO: cater for non-JWT proofs - use w3c-vc::decode method
-            let _ = supported_types.get("jwt").ok_or_else(|| {
-                Error::InvalidCredentialRequest("proof type not supported".to_string())
-            })?;
-
-            // extract proof JWT(s) from request
-            let proof_jwts = match proof {
+            // extract proof JWT(s) or key attestation(s) from request
+            let (proof_type, proofs) = match proof {
                 Proof::Single(proof_type) => match proof_type {
-                    SingleProof::Jwt { jwt } => &vec![jwt.clone()],
-                    SingleProof::Attestation { .. } => todo!(),
+                    SingleProof::Jwt { jwt } => (JWT, slice::from_ref(jwt)),
+                    SingleProof::Attestation { attestation } => {
+                        (ATTESTATION, slice::from_ref(attestation))
+                    }
                 },
                 Proof::Multiple(proofs_type) => match proofs_type {
-                    MultipleProofs::Jwt(proof_jwts) => proof_jwts,
-                    MultipleProofs::Attestation(_) => todo!(),
+                    MultipleProofs::Jwt(proof_jwts) => (JWT, proof_jwts.as_slice()),
+                    MultipleProofs::Attestation(attestations) => {
+                        (ATTESTATION, attestations.as_slice())
+                    }
                 },
             };
 
+            // TODO: cater for non-JWT proofs - use w3c-vc::decode method
+            let Some(supported) = supported_types.get(proof_type) else {
+                return Err(Error::InvalidCredentialRequest(
+                    "proof type not supported".to_string(),
+                ));
+            };
+            let supported = supported.clone();
+
+            let configuration =
+                Metadata::configuration(provider, issuer).await.context("getting configuration")?;
+            let skew = TimeDelta::seconds(configuration.clock_skew.unwrap_or(CLOCK_SKEW));
+
             // the same `c_nonce` should be used for all proofs
             let mut nonces = HashSet::new();
-            let resolver = async |kid: String| resolve_jwk(&kid, provider).await;
-
-            for proof in proof_jwts {
-                // TODO: ProofClaims cannot use `client_id` if the access token was
-                // obtained in a pre-auth flow with anonymous access to the token
-                // endpoint
-                // TODO: check proof is signed with supported algorithm (from proof_type)
-
-                let jwt: Jwt<ProofClaims> = match decode_jws(proof, resolver).await {
-                    Ok(jwt) => jwt,
-                    Err(e) => {
-                        return Err(Error::InvalidProof(format!("issue decoding JWT: {e}")));
-                    }
-                };
-
-                // proof type
-                if jwt.header.typ != JwtType::ProofJwt.to_string() {
-                    return Err(Error::InvalidProof("invalid proof type".to_string()));
-                }
-                if jwt.claims.credential_issuer != ctx.issuer.credential_issuer {
-                    return Err(Error::InvalidProof("invalid proof issuer".to_string()));
-                }
 
-                // c_nonce issued by token endpoint
-                let c_nonce = jwt.claims.nonce.as_ref().ok_or_else(|| {
-                    Error::InvalidProof("proof JWT nonce claim is missing".to_string())
-                })?;
-                nonces.insert(c_nonce.clone());
+            for proof in proofs {
+                let header = decode_header(proof, &supported.proof_signing_alg_values_supported)
+                    .map_err(|e| Error::InvalidProof(format!("proof {e}")))?;
 
-                // extract Key ID for use when building credential
-                let KeyBinding::Kid(kid) = &jwt.header.key else {
-                    return Err(Error::InvalidProof("Proof JWT 'kid' is missing".to_string()));
+                let c_nonce = if proof_type == ATTESTATION {
+                    ctx.verify_attestation(issuer, provider, proof, &supported, skew).await?
+                } else {
+                    // key attestations in JWT proof headers are not supported
+                    if supported.key_attestations_required.is_some() {
+                        return Err(Error::InvalidProof("key attestation is required".to_string()));
+                    }
+                    ctx.verify_proof(provider, proof, &header, skew).await?
                 };
-                ctx.proof_kids.push(kid.to_string());
+                nonces.insert(c_nonce);
             }
 
             // should only be a single c_nonce, but just in case...
             for c_nonce in nonces {
-                StateStore::get::<String>(provider, issuer, &c_nonce)
-                    .await
-                    .context("proof nonce claim")?;
-                StateStore::purge(provider, issuer, &c_nonce)
-                    .await
-                    .context("deleting proof nonce")?;
+                let Some(expires_at) =
+                    use_nonce(issuer, provider, NonceKind::CNonce, &c_nonce).await?
+                else {
+                    return Err(Error::InvalidNonce("`c_nonce` is invalid".to_string()));
+                };
+                if expires_at <= Utc::now() {
+                    return Err(Error::InvalidNonce("`c_nonce` has expired".to_string()));
+                }
             }
         }
 
@@ -217,101 +300,190 @@ impl CredentialRequest {
 }
 
 impl Context {
+    // Verify a proof JWT, returning its `c_nonce`. The proof's key, identified
+    // by a `kid`, `jwk`, or `x5c` header, is used to bind a credential.
+    async fn verify_proof(
+        &mut self, provider: &impl Provider, proof: &str, header: &Value, skew: TimeDelta,
+    ) -> Result<String> {
+        let header_jwk = header_jwk(header)?;
+        let resolver = async |kid: String| match &header_jwk {
+            Some(jwk) => Ok(jwk.clone()),
+            None => resolve_jwk(&kid, provider).await,
+        };
+        let jwt: Jwt<ProofClaims> = match decode_jws(proof, resolver).await {
+            Ok(jwt) => jwt,
+            Err(e) => {
+                return Err(Error::InvalidProof(format!("issue decoding JWT: {e}")));
+            }
+        };
+
+        // proof type
+        if jwt.header.typ != JwtType::ProofJwt.to_string() {
+            return Err(Error::InvalidProof("invalid proof type".to_string()));
+        }
+        if jwt.claims.credential_issuer != self.issuer.credential_issuer {
+            return Err(Error::InvalidProof("invalid proof issuer".to_string()));
+        }
+        check_iat(jwt.claims.iat, skew)?;
+
+        // `iss` must be omitted when the access token was obtained using
+        // anonymous pre-authorized access
+        match (&jwt.claims.client_id, &self.state.body.client_id) {
+            (Some(_), None) => {
+                return Err(Error::InvalidProof(
+                    "proof `iss` must be omitted for anonymous access".to_string(),
+                ));
+            }
+            (Some(iss), Some(client_id)) if iss != client_id => {
+                return Err(Error::InvalidProof(
+                    "proof `iss` does not match `client_id`".to_string(),
+                ));
+            }
+            _ => {}
+        }
+
+        // c_nonce issued by token endpoint
+        let Some(c_nonce) = jwt.claims.nonce else {
+            return Err(Error::InvalidProof("proof JWT nonce claim is missing".to_string()));
+        };
+
+        // extract the key for use when building credential
+        let key = match header_jwk {
+            Some(jwk) => KeyBinding::Jwk(jwk),
+            None => {
+                let KeyBinding::Kid(kid) = jwt.header.key else {
+                    return Err(Error::InvalidProof("Proof JWT 'kid' is missing".to_string()));
+                };
+                KeyBinding::Kid(kid)
+            }
+        };
+        self.check_binding(&key)?;
+        self.proof_keys.push(key);
+
+        Ok(c_nonce)
+    }
+
+    // Check the key can be used to bind the credential using one of the
+    // configuration's `cryptographic_binding_methods_supported`.
+    fn check_binding(&self, key: &KeyBinding) -> Result<()> {
+        let Some(methods) = &self.configuration.cryptographic_binding_methods_supported else {
+            return Ok(());
+        };
+
+        let supported = if let KeyBinding::Kid(kid) = key {
+            BindingMethod::from_did(kid).is_some_and(|method| methods.contains(&method))
+        } else {
+            methods.contains(&BindingMethod::Jwk) || methods.contains(&BindingMethod::CoseKey)
+        };
+        if !supported {
+            return Err(Error::InvalidProof(
+                "proof key binding method is not supported".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    // Verify a key attestation signed by a trusted Wallet Provider, returning
+    // its `c_nonce`. Each attested key is used to bind a credential.
+    async fn verify_attestation(
+        &mut self, issuer: &str, provider: &impl Provider, attestation: &str,
+        supported: &ProofTypesSupported, skew: TimeDelta,
+    ) -> Result<String> {
+        let resolver = async |kid: String| resolve_jwk(&kid, provider).await;
+        let jwt: Jwt<AttestationClaims> = match decode_jws(attestation, resolver).await {
+            Ok(jwt) => jwt,
+            Err(e) => {
+                return Err(Error::InvalidProof(format!("issue decoding key attestation: {e}")));
+            }
+        };
+
+        if jwt.header.typ != JwtType::KeyAttestation.to_string() {
+            return Err(Error::InvalidProof("invalid key attestation type".to_string()));
+        }
+
+        // the attestation must be signed by a trusted Wallet Provider
+        let KeyBinding::Kid(kid) = &jwt.header.key else {
+            return Err(Error::InvalidProof("key attestation 'kid' is missing".to_string()));
+        };
+        let wallet_provider = kid.split('#').next().unwrap_or_default();
+        if !Metadata::wallet_provider(provider, issuer, wallet_provider)
+            .await
+            .context("checking wallet provider")?
+        {
+            return Err(Error::InvalidProof(
+                "key attestation is not from a trusted wallet provider".to_string(),
+            ));
+        }
+
+        // key attestations are long-lived so only the `exp` limits their age
+        let claims = jwt.claims;
+        if claims.iat > Utc::now() + skew {
+            return Err(Error::InvalidProof("key attestation `iat` is in the future".to_string()));
+        }
+        if claims.exp <= Utc::now() {
+            return Err(Error::InvalidProof("key attestation has expired".to_string()));
+        }
+        if claims.attested_keys.is_empty() {
+            return Err(Error::InvalidProof("key attestation has no attested keys".to_string()));
+        }
+        if let Some(required) = &supported.key_attestations_required {
+            required
+                .check(claims.key_storage.as_deref(), claims.user_authentication.as_deref())
+                .map_err(|e| Error::InvalidProof(e.to_string()))?;
+        }
+
+        // c_nonce issued by nonce endpoint
+        let Some(c_nonce) = claims.nonce else {
+            return Err(Error::InvalidProof("key attestation nonce claim is missing".to_string()));
+        };
+
+        for jwk in claims.attested_keys {
+            let key = KeyBinding::Jwk(jwk);
+            self.check_binding(&key)?;
+            self.proof_keys.push(key);
+        }
+        Ok(c_nonce)
+    }
+
     // Issue the requested credential.
     async fn issue(
         &self, issuer: &str, provider: &impl Provider, dataset: Dataset,
     ) -> Result<CredentialResponse> {
         let mut credentials = vec![];
-
-        let mut status_list = StatusList::new().context("creating status list")?;
+        let configuration =
+            Metadata::configuration(provider, issuer).await.context("getting configuration")?;
+        let status_lists = StatusListManager::new(
+            configuration.status_list_uri.unwrap_or_else(|| format!("{issuer}/statuslists")),
+        );
+        let notification_id = generate::notification_id();
+        let mut issued = vec![];
 
         // create a credential for each proof
-        for kid in &self.proof_kids {
-            let status_claim = status_list
-                .add_entry("http://localhost:8080/statuslists/1")
-                .context("creating status claim")?;
-
-            let credential = match &self.configuration.profile {
-                FormatProfile::JwtVcJson {
-                    credential_definition,
-                } => {
-                    // FIXME: do we need to resolve DID document?
-                    let Some(did) = kid.split('#').next() else {
-                        return Err(Error::InvalidProof("Proof JWT DID is invalid".to_string()));
-                    };
-                    let jwt = W3cVcBuilder::new()
-                        .r#type(credential_definition.r#type.clone())
-                        .issuer(&self.issuer.credential_issuer)
-                        .holder(did)
-                        .status(status_claim)
-                        .claims(dataset.claims.clone())
-                        .signer(provider)
-                        .build()
-                        .await
-                        .context("creating `jwt_vc_json` credential")?;
-
-                    Credential {
-                        credential: jwt.into(),
-                    }
-                }
-                FormatProfile::MsoMdoc { doctype } => {
-                    let jwk = resolve_jwk(kid, provider)
-                        .await
-                        .context("retrieving JWK for `dc+sd-jwt` credential")?;
-
-                    let mdl = MdocBuilder::new()
-                        .doctype(doctype)
-                        .device_key(jwk)
-                        .claims(dataset.claims.clone())
-                        .signer(provider)
-                        .build()
-                        .await
-                        .context("creating `mso_mdoc` credential")?;
-
-                    Credential {
-                        credential: mdl.into(),
-                    }
-                }
-                FormatProfile::DcSdJwt { vct } => {
-                    // TODO: cache the result of jwk when verifying proof (`verify` method)
-                    let jwk =
-                        resolve_jwk(kid, provider).await.context("getting JWK for `dc+sd-jwt`")?;
-                    let Some(did) = kid.split('#').next() else {
-                        return Err(Error::InvalidProof("Proof JWT DID is invalid".to_string()));
-                    };
-
-                    let sd_jwt = SdJwtVcBuilder::new()
-                        .vct(vct)
-                        .issuer(self.issuer.credential_issuer.clone())
-                        .claims(dataset.claims.clone())
-                        .key_binding(jwk)
-                        .holder(did)
-                        .status(status_claim)
-                        .signer(provider)
-                        .build()
-                        .await
-                        .context("creating `dc+sd-jwt` credential")?;
-
-                    Credential {
-                        credential: sd_jwt.into(),
-                    }
-                }
-                FormatProfile::JwtVcJsonLd { .. } => todo!(),
-                FormatProfile::LdpVc { .. } => todo!(),
-            };
-
+        for key in &self.proof_keys {
+            let status_claim = status_lists
+                .allocate(issuer, provider)
+                .await
+                .context("allocating status list entry")?;
+
+            issued.push(IssuedCredential {
+                credential_configuration_id: self.configuration_id.clone(),
+                subject_id: self.state.body.subject_id.clone(),
+                notification_id: Some(notification_id.clone()),
+                status: status_claim.status_list.clone(),
+            });
+
+            let credential = self.create(issuer, provider, key, &dataset, status_claim).await?;
             credentials.push(credential);
         }
 
-        let list_id = format!("{issuer}/statuslists/1");
-        let token = TokenBuilder::new()
-            .status_list(status_list.clone())
-            .uri(&list_id)
-            .signer(provider)
-            .build()
-            .await
-            .context("building status list token")?;
-        StatusStore::put(provider, issuer, &list_id, &token).await.context("saving status list")?;
+        // only register credentials once all have been created
+        let mut entries = vec![];
+        for issued in issued {
+            Registry::register(provider, issuer, &issued)
+                .await
+                .context("registering issued credential")?;
+            entries.push(issued.status);
+        }
 
         // update token state with new `c_nonce`
         let mut state = self.state.clone();
@@ -322,11 +494,7 @@ impl Context {
             .await
             .context("saving state")?;
 
-        // TODO: create issuance state for notification endpoint
-        let notification_id = generate::notification_id();
-        StateStore::put(provider, issuer, &notification_id, &state)
-            .await
-            .context("saving state")?;
+        self.record_issuance(issuer, provider, &notification_id, entries).await?;
 
         Ok(CredentialResponse::Credentials {
             credentials,
@@ -334,49 +502,349 @@ impl Context {
         })
     }
 
+    // Create a credential bound to `key`, referencing the credential's status
+    // using `status_claim`.
+    async fn create(
+        &self, issuer: &str, provider: &impl Provider, key: &KeyBinding, dataset: &Dataset,
+        status_claim: StatusClaim,
+    ) -> Result<Credential> {
+        let credential = match &self.configuration.profile {
+            FormatProfile::JwtVcJson {
+                credential_definition,
+            } => {
+                // FIXME: do we need to resolve DID document?
+                let mut builder = W3cVcBuilder::new()
+                    .r#type(credential_definition.r#type.clone())
+                    .issuer(&self.issuer.credential_issuer)
+                    .holder(holder_did(key)?)
+                    .claims(dataset.claims.clone());
+
+                // reference Bitstring Status List Credentials when
+                // published, otherwise the Status List Token
+                if bitstring_published(issuer, provider, &status_claim.status_list).await? {
+                    for purpose in [StatusPurpose::Revocation, StatusPurpose::Suspension] {
+                        builder = builder.bitstring_status(BitstringStatusListEntry::new(
+                            &status_claim.status_list,
+                            purpose,
+                        ));
+                    }
+                } else {
+                    builder = builder.status(status_claim);
+                }
+
+                let jwt = builder
+                    .signer(provider)
+                    .build()
+                    .await
+                    .context("creating `jwt_vc_json` credential")?;
+
+                Credential {
+                    credential: jwt.into(),
+                }
+            }
+            FormatProfile::MsoMdoc { doctype } => {
+                let jwk = holder_jwk(key, provider)
+                    .await
+                    .context("retrieving JWK for `mso_mdoc` credential")?;
+
+                let mut builder = MdocBuilder::new()
+                    .doctype(doctype)
+                    .device_key(jwk)
+                    .claims(dataset.claims.clone());
+
+                // status can only be checked when a CWT Status List
+                // Token has been published
+                if cwt_published(issuer, provider, &status_claim.status_list).await? {
+                    builder = builder.status(status_claim);
+                }
+
+                let mdl = builder
+                    .signer(provider)
+                    .build()
+                    .await
+                    .context("creating `mso_mdoc` credential")?;
+
+                Credential {
+                    credential: mdl.into(),
+                }
+            }
+            FormatProfile::DcSdJwt { vct } => {
+                // TODO: cache the result of jwk when verifying proof (`verify` method)
+                let jwk = holder_jwk(key, provider).await.context("getting JWK for `dc+sd-jwt`")?;
+
+                let sd_jwt = SdJwtVcBuilder::new()
+                    .vct(vct)
+                    .issuer(self.issuer.credential_issuer.clone())
+                    .claims(dataset.claims.clone())
+                    .key_binding(jwk)
+                    .holder(holder_did(key)?)
+                    .status(status_claim)
+                    .signer(provider)
+                    .build()
+                    .await
+                    .context("creating `dc+sd-jwt` credential")?;
+
+                Credential {
+                    credential: sd_jwt.into(),
+                }
+            }
+            FormatProfile::JwtVcJsonLd { .. } => todo!(),
+            FormatProfile::LdpVc { .. } => todo!(),
+        };
+        Ok(credential)
+    }
+
+    // Record each issued credential and save an issuance record for use by
+    // the Notification endpoint.
+    async fn record_issuance(
+        &self, issuer: &str, provider: &impl Provider, notification_id: &str,
+        credentials: Vec<StatusListEntry>,
+    ) -> Result<()> {
+        let token = &self.state.body;
+        for entry in &credentials {
+            let event = IssuanceEventKind::CredentialIssued {
+                subject_id: token.subject_id.clone(),
+                credential_configuration_id: self.configuration_id.clone(),
+                format: self.configuration.profile.to_string(),
+                status_list: entry.uri.clone(),
+                status_index: entry.idx,
+                notification_id: notification_id.to_string(),
+            };
+            record(issuer, event);
+        }
+
+        let state = State {
+            body: Issued {
+                subject_id: token.subject_id.clone(),
+                client_id: token.client_id.clone(),
+                grant_id: token.grant_id.clone(),
+                credential_configuration_id: self.configuration_id.clone(),
+                credentials,
+                event: None,
+            },
+            expires_at: Utc::now() + Expire::Issued.duration(),
+        };
+        StateStore::put(provider, issuer, notification_id, &state).await.context("saving state")?;
+        Ok(())
+    }
+
     // Defer issuance of the requested credential.
     async fn defer(
-        &self, issuer: &str, provider: &impl Provider, request: CredentialRequest,
+        &self, issuer: &str, provider: &impl Provider, identifier: &str,
+        authorized: &AuthorizedDetail, dataset: &Dataset,
     ) -> Result<CredentialResponse> {
         let txn_id = generate::transaction_id();
 
         let state = State {
             body: Deferred {
                 transaction_id: txn_id.clone(),
-                credential_request: request,
+                subject_id: self.state.body.subject_id.clone(),
+                credential_identifier: identifier.to_string(),
+                credential_configuration_id: self.configuration_id.clone(),
+                authorized: authorized.clone(),
+                proof_keys: self.proof_keys.clone(),
             },
-            expires_at: Utc::now() + Expire::Access.duration(),
+            expires_at: Utc::now() + Expire::Deferred.duration(),
         };
         StateStore::put(provider, issuer, &txn_id, &state).await.context("saving state")?;
 
+        let event = IssuanceEventKind::Deferred {
+            subject_id: self.state.body.subject_id.clone(),
+            credential_configuration_id: self.configuration_id.clone(),
+            transaction_id: txn_id.clone(),
+        };
+        record(issuer, event);
+
         Ok(CredentialResponse::TransactionId {
             transaction_id: txn_id,
+            interval: dataset.interval.unwrap_or(INTERVAL),
         })
     }
 
-    // Get credential dataset for the request
-    async fn dataset(
+    // Get the `credential_identifier` of the subject's dataset for the
+    // request. Requests by `credential_configuration_id` use the first of the
+    // subject's datasets authorized for the configuration.
+    async fn identifier(
         &self, issuer: &str, provider: &impl Provider, request: &CredentialRequest,
         authorized: &AuthorizedDetail,
-    ) -> Result<Dataset> {
-        let RequestBy::Identifier(identifier) = &request.credential else {
-            return Err(Error::InvalidCredentialRequest(
-                "requesting credentials by `credential_configuration_id` is unsupported"
-                    .to_string(),
+    ) -> Result<String> {
+        let config_id = match &request.credential {
+            RequestBy::Identifier(identifier) => return Ok(identifier.clone()),
+            RequestBy::ConfigurationId(config_id) => config_id,
+        };
+
+        // limit to datasets the attested Wallet may be issued, when attested
+        let token = &self.state.body;
+        let wallet = token.wallet.as_ref();
+        let identifiers =
+            Subject::authorize(provider, issuer, &token.subject_id, config_id, wallet)
+                .await
+                .map_err(|e| {
+                    Error::CredentialRequestDenied(format!("issue authorizing subject: {e}"))
+                })?;
+
+        // limit to datasets authorized by the token, when identified
+        let authorized_ids = &authorized.credential_identifiers;
+        let Some(identifier) = identifiers
+            .into_iter()
+            .find(|id| authorized_ids.is_empty() || authorized_ids.contains(id))
+        else {
+            return Err(Error::CredentialRequestDenied(
+                "subject has no dataset for the requested credential".to_string(),
             ));
         };
+        Ok(identifier)
+    }
 
-        // get claims dataset for `credential_identifier`
+    // Get credential dataset for `credential_identifier`.
+    async fn dataset(
+        &self, issuer: &str, provider: &impl Provider, identifier: &str,
+        authorized: &AuthorizedDetail,
+    ) -> Result<Dataset> {
         let subject_id = &self.state.body.subject_id;
         let mut dataset = Subject::dataset(provider, issuer, subject_id, identifier)
             .await
             .context("populating claims")?;
 
-        // only include previously requested/authorized claims
-        if let Some(claims) = &authorized.authorization_detail.claims {
-            dataset.claims.retain(|k, _| claims.iter().any(|c| c.path.contains(k)));
-        }
-
+        retain_authorized(&mut dataset, authorized);
         Ok(dataset)
     }
 }
+
+// Only include previously requested/authorized claims in the dataset.
+fn retain_authorized(dataset: &mut Dataset, authorized: &AuthorizedDetail) {
+    if let Some(claims) = &authorized.authorization_detail.claims {
+        dataset.claims.retain(|k, _| claims.iter().any(|c| c.path.contains(k)));
+    }
+}
+
+// The public key embedded in a proof's protected header, either as a `jwk` or
+// as the leaf certificate of an `x5c` chain.
+//
+// The `x5c` chain is not validated: there is no trust anchor, path, or
+// validity period check. The leaf certificate is only used to carry the
+// Wallet's key, so an `x5c`-bound proof is treated exactly like a `jwk`-bound
+// proof and establishes no more trust in the key.
+fn header_jwk(header: &Value) -> Result<Option<PublicKeyJwk>> {
+    if let Some(jwk) = header.get("jwk") {
+        let Ok(jwk) = serde_json::from_value(jwk.clone()) else {
+            return Err(Error::InvalidProof("proof `jwk` is invalid".to_string()));
+        };
+        return Ok(Some(jwk));
+    }
+
+    if let Some(x5c) = header.get("x5c") {
+        let Some(leaf) = x5c.as_array().and_then(|chain| chain.first()).and_then(Value::as_str)
+        else {
+            return Err(Error::InvalidProof("proof `x5c` is empty".to_string()));
+        };
+        let jwk = certificate_jwk(leaf)
+            .map_err(|e| Error::InvalidProof(format!("proof `x5c` is invalid: {e}")))?;
+        return Ok(Some(jwk));
+    }
+
+    Ok(None)
+}
+
+// Convert the public key of a base64-encoded DER X.509 certificate to a JWK.
+fn certificate_jwk(certificate: &str) -> anyhow::Result<PublicKeyJwk> {
+    let der = Base64::decode_vec(certificate).map_err(|_| anyhow!("not base64-encoded"))?;
+    let cert = Certificate::from_der(&der).map_err(|e| anyhow!("issue decoding: {e}"))?;
+    let spki = &cert.tbs_certificate.subject_public_key_info;
+    let Some(key) = spki.subject_public_key.as_bytes() else {
+        return Err(anyhow!("public key is invalid"));
+    };
+
+    let jwk = if spki.algorithm.oid == ED25519 {
+        json!({"kty": "OKP", "crv": "Ed25519", "x": Base64UrlUnpadded::encode_string(key)})
+    } else if spki.algorithm.oid == EC_PUBLIC_KEY {
+        let curve =
+            spki.algorithm.parameters.as_ref().and_then(|p| p.decode_as::<ObjectIdentifier>().ok());
+        let crv = match curve {
+            Some(oid) if oid == SECP256K1 => "secp256k1",
+            Some(oid) if oid == SECP256R1 => "P-256",
+            _ => return Err(anyhow!("unsupported curve")),
+        };
+
+        // uncompressed SEC1 point
+        let Some(point) = key.strip_prefix(&[0x04]) else {
+            return Err(anyhow!("public key is not an uncompressed point"));
+        };
+        let (x, y) = point.split_at(point.len() / 2);
+        json!({
+            "kty": "EC",
+            "crv": crv,
+            "x": Base64UrlUnpadded::encode_string(x),
+            "y": Base64UrlUnpadded::encode_string(y),
+        })
+    } else {
+        return Err(anyhow!("unsupported public key algorithm"));
+    };
+
+    Ok(serde_json::from_value(jwk)?)
+}
+
+// Check the proof was created within an acceptable window: no earlier than
+// the lifetime of a `c_nonce` and not in the future, allowing for clock skew.
+fn check_iat(iat: DateTime<Utc>, skew: TimeDelta) -> Result<()> {
+    let now = Utc::now();
+
+    if iat > now + skew {
+        return Err(Error::InvalidProof("proof `iat` is in the future".to_string()));
+    }
+    if iat < now - Expire::Authorized.duration() - skew {
+        return Err(Error::InvalidProof("proof `iat` is too old".to_string()));
+    }
+    Ok(())
+}
+
+// The DID of the holder the credential is bound to. Attested keys are
+// identified using the `did:jwk` method.
+fn holder_did(key: &KeyBinding) -> Result<String> {
+    if let KeyBinding::Jwk(jwk) = key {
+        let jwk = serde_json::to_vec(jwk).context("serializing attested key")?;
+        return Ok(format!("did:jwk:{}", Base64UrlUnpadded::encode_string(&jwk)));
+    }
+    let KeyBinding::Kid(kid) = key else {
+        return Err(Error::InvalidProof("proof key is unsupported".to_string()));
+    };
+    let Some(did) = kid.split('#').next() else {
+        return Err(Error::InvalidProof("Proof JWT DID is invalid".to_string()));
+    };
+    Ok(did.to_string())
+}
+
+// The public key the credential is bound to.
+async fn holder_jwk(key: &KeyBinding, provider: &impl Provider) -> anyhow::Result<PublicKeyJwk> {
+    if let KeyBinding::Jwk(jwk) = key {
+        return Ok(jwk.clone());
+    }
+    let KeyBinding::Kid(kid) = key else {
+        return Err(anyhow!("proof key is unsupported"));
+    };
+    resolve_jwk(kid, provider).await
+}
+
+// Whether Bitstring Status List Credentials have been published for the
+// Status List referenced by `entry`.
+async fn bitstring_published(
+    issuer: &str, provider: &impl Provider, entry: &StatusListEntry,
+) -> anyhow::Result<bool> {
+    let uri =
+        BitstringStatusListEntry::new(entry, StatusPurpose::Revocation).status_list_credential;
+    let credential = StatusStore::get_bitstring(provider, issuer, &uri)
+        .await
+        .context("retrieving status list credential")?;
+    Ok(credential.is_some())
+}
+
+// Whether a CWT-formatted Status List Token has been published for the Status
+// List referenced by `entry`.
+async fn cwt_published(
+    issuer: &str, provider: &impl Provider, entry: &StatusListEntry,
+) -> anyhow::Result<bool> {
+    let token = StatusStore::get_cwt(provider, issuer, &entry.uri)
+        .await
+        .context("retrieving status list token")?;
+    Ok(token.is_some())
+}
@@ -17,9 +17,11 @@
 use anyhow::Context as _;
 
 use crate::error::invalid;
-use crate::handlers::{Body, Error, Handler, Request, Response, Result};
+use crate::handlers::{Body, Error, Handler, Request, Response, Result, record};
 use crate::provider::{Provider, StateStore};
-use crate::types::{CredentialOffer, CredentialOfferRequest, CredentialOfferResponse};
+use crate::types::{
+    CredentialOffer, CredentialOfferRequest, CredentialOfferResponse, IssuanceEventKind,
+};
 
 /// Endpoint for the Wallet to request the Issuer's Credential Offer when
 /// engaged in a cross-device flow.
@@ -40,6 +42,11 @@ async fn credential_offer(
         return Err(invalid!("state expired"));
     }
 
+    let event = IssuanceEventKind::OfferFetched {
+        credential_configuration_ids: state.body.credential_configuration_ids.clone(),
+    };
+    record(issuer, event);
+
     Ok(CredentialOfferResponse(state.body))
 }
 
@@ -7,17 +7,19 @@
 //! The Wallet MUST present to the Deferred Endpoint an Access Token that is
 //! valid for the issuance of the Credential previously requested at the
 //! Credential Endpoint or the Batch Credential Endpoint.
+//!
+//! The credential is issued once the issuer signals, via the `Subject`
+//! provider, that it is ready. Until then, an `issuance_pending` error is
+//! returned with the interval the Wallet should wait before retrying.
 
 use anyhow::Context as _;
 
 use crate::error::invalid;
-use crate::handlers::credential::credential;
-use crate::handlers::{
-    Body, CredentialHeaders, DeferredHeaders, Error, Handler, Request, Response, Result,
-};
-use crate::provider::{Provider, StateStore};
+use crate::handlers::credential::issue_deferred;
+use crate::handlers::{Body, DeferredHeaders, Error, Handler, Request, Response, Result, record};
+use crate::provider::{Metadata, Provider, StateLock, StateStore};
 use crate::state::Deferred;
-use crate::types::{CredentialResponse, DeferredCredentialRequest, DeferredCredentialResponse};
+use crate::types::{DeferredCredentialRequest, DeferredCredentialResponse, IssuanceEventKind};
 
 /// Deferred credential request handler.
 ///
@@ -31,6 +33,16 @@ async fn deferred(
 ) -> Result<DeferredCredentialResponse> {
     let transaction_id = &request.body.transaction_id;
 
+    // verify the access token before revealing anything about the transaction
+    let metadata = Metadata::issuer(provider, issuer).await.context("fetching metadata")?;
+    let htu = metadata.deferred_credential_endpoint.clone().unwrap_or_default();
+    let token = request.headers.verify(issuer, provider, &htu).await?;
+
+    // hold the transaction while the credential is issued so concurrent
+    // requests cannot both issue it
+    let resource = format!("deferred:{transaction_id}");
+    let _guard = StateLock::lock(provider, issuer, &resource).await.context("locking state")?;
+
     // retrieve deferred credential request from state
     let Ok(state) = StateStore::get::<Deferred>(provider, issuer, transaction_id).await else {
         return Err(Error::InvalidTransactionId("deferred state not found".to_string()));
@@ -39,24 +51,19 @@ async fn deferred(
         return Err(invalid!("state expired"));
     }
 
-    // make credential request
-    let req = Request {
-        body: state.body.credential_request,
-        headers: CredentialHeaders {
-            authorization: request.headers.authorization,
-        },
-    };
-    let response = credential(issuer, provider, req).await?;
-
-    // is issuance still pending?
-    if let CredentialResponse::TransactionId { .. } = response {
-        // TODO: make retry interval configurable
-        return Err(Error::IssuancePending(5));
-    }
+    // issue the credential when the issuer is ready
+    let subject_id = token.body.subject_id.clone();
+    let response = issue_deferred(issuer, provider, token, metadata, state.body).await?;
 
     // remove deferred state item
     StateStore::purge(provider, issuer, transaction_id).await.context("purging state")?;
 
+    let event = IssuanceEventKind::DeferredCompleted {
+        subject_id,
+        transaction_id: transaction_id.clone(),
+    };
+    record(issuer, event);
+
     Ok(response)
 }
 
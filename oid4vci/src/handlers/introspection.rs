@@ -0,0 +1,154 @@
+//! # Token Introspection Endpoint [RFC7662]
+//!
+//! This endpoint allows authenticated confidential clients, such as a
+//! separately deployed Credential Issuer, to determine the state of an access
+//! or refresh token and the credentials it authorizes.
+//!
+//! [RFC7662]: (https://www.rfc-editor.org/rfc/rfc7662.html)
+
+use credibil_core::state::State;
+
+use crate::client_auth;
+use crate::error::server;
+use crate::handlers::{Body, Error, Handler, Request, Response, Result};
+use crate::oauth::{OAuthClient, TokenEndpointAuth};
+use crate::provider::{Metadata, Provider, StateStore};
+use crate::state::{Refresh, Token};
+use crate::types::{
+    ClientAssertion, IntrospectionRequest, IntrospectionResponse, TokenConfirmation, TokenType,
+    TokenTypeHint,
+};
+
+/// Introspection request handler.
+///
+/// # Errors
+///
+/// Returns an `OpenID4VP` error if the client cannot be authenticated or if
+/// the provider is not available.
+async fn introspection(
+    issuer: &str, provider: &impl Provider, request: IntrospectionRequest,
+) -> Result<IntrospectionResponse> {
+    let client =
+        authenticate(issuer, provider, &request.client_id, request.client_assertion.as_ref())
+            .await?;
+    if !is_confidential(&client) {
+        return Err(Error::UnauthorizedClient(
+            "client is not permitted to introspect tokens".to_string(),
+        ));
+    }
+
+    let Ok(server) = Metadata::server(provider, issuer).await else {
+        return Err(server!("unknown authorization server"));
+    };
+
+    let response = match find(issuer, provider, &request.token, request.token_type_hint).await {
+        Some(TokenState::Access(state)) if !state.is_expired() => {
+            let token = state.body;
+            let token_type =
+                if token.dpop_jkt.is_some() { TokenType::DPoP } else { TokenType::Bearer };
+            IntrospectionResponse {
+                active: true,
+                client_id: token.client_id,
+                sub: Some(token.subject_id),
+                iss: Some(server.oauth.issuer),
+                exp: Some(state.expires_at),
+                token_type: Some(token_type),
+                authorization_details: Some(token.authorized_details),
+                scope: token.scope,
+                cnf: token.dpop_jkt.map(|jkt| TokenConfirmation { jkt }),
+            }
+        }
+        Some(TokenState::Refresh(state)) if !state.is_expired() && !state.body.used => {
+            let refresh = state.body;
+            IntrospectionResponse {
+                active: true,
+                client_id: refresh.client_id,
+                sub: Some(refresh.subject_id),
+                iss: Some(server.oauth.issuer),
+                exp: Some(state.expires_at),
+                token_type: None,
+                authorization_details: Some(refresh.authorized_details),
+                scope: refresh.scope,
+                cnf: refresh.dpop_jkt.map(|jkt| TokenConfirmation { jkt }),
+            }
+        }
+        _ => IntrospectionResponse::default(),
+    };
+
+    Ok(response)
+}
+
+impl<P: Provider> Handler<IntrospectionResponse, P> for Request<IntrospectionRequest> {
+    type Error = Error;
+
+    async fn handle(
+        self, issuer: &str, provider: &P,
+    ) -> Result<impl Into<Response<IntrospectionResponse>>, Self::Error> {
+        introspection(issuer, provider, self.body).await
+    }
+}
+
+impl Body for IntrospectionRequest {}
+
+/// The state of an access or refresh token.
+pub enum TokenState {
+    /// Access token state.
+    Access(State<Token>),
+
+    /// Refresh token state.
+    Refresh(State<Refresh>),
+}
+
+/// Authenticate the client calling the introspection or revocation endpoint.
+///
+/// # Errors
+///
+/// Returns `Error::InvalidClient` if the client is unknown or cannot be
+/// authenticated.
+pub async fn authenticate(
+    issuer: &str, provider: &impl Provider, client_id: &str, assertion: Option<&ClientAssertion>,
+) -> Result<OAuthClient> {
+    let Ok(client) = Metadata::client(provider, issuer, client_id).await else {
+        return Err(Error::InvalidClient(format!("{client_id} is not a valid client_id")));
+    };
+    let Ok(server) = Metadata::server(provider, issuer).await else {
+        return Err(Error::InvalidClient("unknown authorization server".to_string()));
+    };
+    client_auth::authenticate(issuer, provider, &client.oauth, assertion, None, &server).await?;
+    Ok(client.oauth)
+}
+
+/// Whether the client authenticates using a client assertion.
+#[must_use]
+pub const fn is_confidential(client: &OAuthClient) -> bool {
+    matches!(
+        client.token_endpoint_auth_method,
+        Some(TokenEndpointAuth::PrivateKeyJwt | TokenEndpointAuth::ClientSecretJwt)
+    )
+}
+
+/// Find the state of an access or refresh token, looking for the hinted type
+/// of token first.
+pub async fn find(
+    issuer: &str, provider: &impl Provider, token: &str, hint: Option<TokenTypeHint>,
+) -> Option<TokenState> {
+    // only accept token state saved under the access token itself
+    let access = async || {
+        StateStore::get::<Token>(provider, issuer, token)
+            .await
+            .ok()
+            .filter(|state| state.body.access_token == token)
+    };
+    let refresh = async || StateStore::get::<Refresh>(provider, issuer, token).await.ok();
+
+    if hint == Some(TokenTypeHint::RefreshToken) {
+        if let Some(state) = refresh().await {
+            return Some(TokenState::Refresh(state));
+        }
+        return access().await.map(TokenState::Access);
+    }
+    if let Some(state) = access().await {
+        return Some(TokenState::Access(state));
+    }
+    refresh().await.map(TokenState::Refresh)
+}
@@ -39,7 +39,14 @@ async fn metadata(
     issuer: &str, provider: &impl Provider, _: Request<MetadataRequest, MetadataHeaders>,
 ) -> Result<MetadataResponse> {
     // FIXME: use language header in request
-    let credential_issuer = Metadata::issuer(provider, issuer).await.context("getting metadata")?;
+    let mut credential_issuer =
+        Metadata::issuer(provider, issuer).await.context("getting metadata")?;
+
+    // advertise the endpoint Status List Tokens are published under
+    if credential_issuer.status_list_aggregation_endpoint.is_none() {
+        credential_issuer.status_list_aggregation_endpoint = Some(format!("{issuer}/statuslists"));
+    }
+
     Ok(MetadataResponse(credential_issuer))
 }
 
@@ -9,11 +9,10 @@
 
 use anyhow::Context as _;
 use chrono::Utc;
-use credibil_core::state::State;
 
 use crate::generate;
 use crate::handlers::{Body, Error, Handler, Request, Response, Result};
-use crate::provider::{Provider, StateStore};
+use crate::provider::{NonceKind, NonceStore, Provider};
 use crate::state::Expire;
 use crate::types::{NonceRequest, NonceResponse};
 
@@ -26,11 +25,10 @@ use crate::types::{NonceRequest, NonceResponse};
 async fn nonce(issuer: &str, provider: &impl Provider, _: NonceRequest) -> Result<NonceResponse> {
     let c_nonce = generate::nonce();
 
-    let state = &State {
-        body: c_nonce.clone(),
-        expires_at: Utc::now() + Expire::Authorized.duration(),
-    };
-    StateStore::put(provider, issuer, &c_nonce, state).await.context("failed to purge state")?;
+    let expires_at = Utc::now() + Expire::Authorized.duration();
+    NonceStore::put_nonce(provider, issuer, NonceKind::CNonce, &c_nonce, expires_at)
+        .await
+        .context("saving nonce")?;
 
     Ok(NonceResponse { c_nonce })
 }
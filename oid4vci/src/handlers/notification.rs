@@ -1,5 +1,3 @@
-// TODO: implement Notification endpoint
-
 //! # Notification Endpoint
 //!
 //! This endpoint is used by the Wallet to notify the Credential Issuer of
@@ -18,13 +16,19 @@
 //! `notification_id`, it returns success. Due to the network errors, there are
 //! no guarantees that a Credential Issuer will receive a notification within a
 //! certain time period or at all.
+//!
+//! Each `notification_id` identifies an issuance record linking to the
+//! credential(s) issued with it. Notifications are passed on to the issuer
+//! using `Subject::notify`.
+
+use anyhow::Context as _;
 
 use crate::handlers::{
-    Body, Error, Handler, Headers, NotificationHeaders, Request, Response, Result,
+    Body, Error, Handler, Headers, NotificationHeaders, Request, Response, Result, record,
 };
-use crate::provider::{Provider, StateStore};
-use crate::state::Token;
-use crate::types::{NotificationRequest, NotificationResponse};
+use crate::provider::{Metadata, Provider, StateLock, StateStore, Subject};
+use crate::state::Issued;
+use crate::types::{IssuanceEventKind, NotificationRequest, NotificationResponse};
 
 /// Notification request handler.
 ///
@@ -37,17 +41,55 @@ async fn notification(
     request: Request<NotificationRequest, NotificationHeaders>,
 ) -> Result<NotificationResponse> {
     // verify access token
-    let _ = StateStore::get::<Token>(provider, issuer, &request.headers.authorization)
-        .await
-        .map_err(|_| Error::AccessDenied("invalid access token".to_string()))?;
+    let metadata = Metadata::issuer(provider, issuer).await.context("fetching metadata")?;
+    let htu = metadata.notification_endpoint.unwrap_or_default();
+    let token = request.headers.verify(issuer, provider, &htu).await?.body;
 
     let request = request.body;
-    let Ok(_state) = StateStore::get::<Token>(provider, issuer, &request.notification_id).await
+    request.verify()?;
+
+    // hold the lock until the notification is saved so concurrent, repeated
+    // notifications are only passed on once
+    let _guard =
+        StateLock::lock(provider, issuer, "notification").await.context("locking state")?;
+    let Ok(mut state) = StateStore::get::<Issued>(provider, issuer, &request.notification_id).await
     else {
-        return Err(Error::AccessDenied("invalid notification id".to_string()));
+        return Err(Error::InvalidNotificationId("unknown `notification_id`".to_string()));
     };
 
+    // the credential(s) must have been issued with a token from the same
+    // authorization grant as the presented token
+    let issued = &state.body;
+    if issued.subject_id != token.subject_id
+        || issued.client_id != token.client_id
+        || issued.grant_id != token.grant_id
+    {
+        return Err(Error::InvalidNotificationId(
+            "`notification_id` was not issued to the client".to_string(),
+        ));
+    }
+
     tracing::info!("notification: {:#?}, {:#?}", request.event, request.event_description,);
+    let event = IssuanceEventKind::NotificationReceived {
+        subject_id: state.body.subject_id.clone(),
+        notification_id: request.notification_id.clone(),
+        event: request.event.clone(),
+    };
+    record(issuer, event);
+
+    // repeated notifications of the same event have no further effect
+    if state.body.event.as_ref() == Some(&request.event) {
+        return Ok(NotificationResponse);
+    }
+
+    Subject::notify(provider, issuer, &state.body.subject_id, &request)
+        .await
+        .context("notifying issuer")?;
+
+    state.body.event = Some(request.event);
+    StateStore::put(provider, issuer, &request.notification_id, &state)
+        .await
+        .context("saving state")?;
 
     Ok(NotificationResponse)
 }
@@ -66,4 +108,23 @@ impl<P: Provider> Handler<NotificationResponse, P>
 
 impl Body for NotificationRequest {}
 
+impl NotificationRequest {
+    // Verify the notification request.
+    fn verify(&self) -> Result<()> {
+        if self.notification_id.is_empty() {
+            return Err(Error::InvalidNotificationRequest("missing `notification_id`".to_string()));
+        }
+
+        // descriptions are limited to printable ASCII, excluding '"' and '\'
+        let printable = |c: char| matches!(c, '\x20'..='\x21' | '\x23'..='\x5b' | '\x5d'..='\x7e');
+        if self.event_description.as_ref().is_some_and(|d| !d.chars().all(printable)) {
+            return Err(Error::InvalidNotificationRequest(
+                "`event_description` contains invalid characters".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
 impl Headers for NotificationHeaders {}
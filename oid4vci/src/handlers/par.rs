@@ -10,10 +10,13 @@ use anyhow::Context as _;
 use chrono::{Duration, Utc};
 use credibil_core::state::State;
 
-use crate::generate;
-use crate::handlers::{Body, Error, Handler, Request, Response, Result, authorize};
+use crate::handlers::{
+    Body, Error, Handler, Headers, ParHeaders, Request, Response, Result, authorize,
+};
 use crate::provider::{Metadata, Provider, StateStore};
+use crate::state::Pushed;
 use crate::types::{PushedAuthorizationRequest, PushedAuthorizationResponse};
+use crate::{client_auth, generate};
 
 /// Endpoint for the Wallet to push an Authorization Request when using Pushed
 /// Authorization Requests.
@@ -24,8 +27,28 @@ use crate::types::{PushedAuthorizationRequest, PushedAuthorizationResponse};
 /// not available.
 async fn par(
     issuer: &str, provider: &impl Provider, request: PushedAuthorizationRequest,
+    headers: &ParHeaders,
 ) -> Result<PushedAuthorizationResponse> {
-    // TODO: authenticate client using client assertion (same as token endpoint)
+    // authenticate the client (same as token endpoint)
+    let client_id = &request.request.client_id;
+    let Ok(client) = Metadata::client(provider, issuer, client_id).await else {
+        return Err(Error::InvalidClient(format!("{client_id} is not a valid client_id")));
+    };
+    let Ok(server) = Metadata::server(provider, issuer).await else {
+        return Err(Error::InvalidClient("unknown authorization server".to_string()));
+    };
+    let wallet =
+        client_auth::verify_attestation(issuer, provider, headers, Some(client_id), &server)
+            .await?;
+    client_auth::authenticate(
+        issuer,
+        provider,
+        &client.oauth,
+        request.client_assertion.as_ref(),
+        wallet.as_ref(),
+        &server,
+    )
+    .await?;
 
     // verify the pushed RequestObject using `/authorize` endpoint logic
     let Ok(issuer_meta) = Metadata::issuer(provider, issuer).await else {
@@ -41,9 +64,13 @@ async fn par(
     let request_uri = format!("urn:ietf:params:oauth:request_uri:{}", generate::uri_token());
     let expires_in = Duration::seconds(600);
 
-    // save request to state for retrieval by authorization endpoint
+    // save request to state for retrieval by authorization endpoint, along
+    // with the attested Wallet the request was pushed by
     let state = State {
-        body: request.request.clone(),
+        body: Pushed {
+            request: request.request,
+            wallet,
+        },
         expires_at: Utc::now() + expires_in,
     };
     StateStore::put(provider, issuer, &request_uri, &state).await.context("saving state")?;
@@ -60,8 +87,22 @@ impl<P: Provider> Handler<PushedAuthorizationResponse, P> for Request<PushedAuth
     async fn handle(
         self, issuer: &str, provider: &P,
     ) -> Result<impl Into<Response<PushedAuthorizationResponse>>, Self::Error> {
-        par(issuer, provider, self.body).await
+        par(issuer, provider, self.body, &ParHeaders::default()).await
+    }
+}
+
+impl<P: Provider> Handler<PushedAuthorizationResponse, P>
+    for Request<PushedAuthorizationRequest, ParHeaders>
+{
+    type Error = Error;
+
+    async fn handle(
+        self, issuer: &str, provider: &P,
+    ) -> Result<impl Into<Response<PushedAuthorizationResponse>>, Self::Error> {
+        par(issuer, provider, self.body, &self.headers).await
     }
 }
 
 impl Body for PushedAuthorizationRequest {}
+
+impl Headers for ParHeaders {}
@@ -0,0 +1,74 @@
+//! # Token Revocation Endpoint [RFC7009]
+//!
+//! This endpoint allows clients to notify the authorization server that an
+//! access or refresh token is no longer needed, removing the token's state.
+//! Revoking a refresh token revokes every refresh token rotated from the same
+//! grant along with the access tokens issued for them.
+//!
+//! Public clients may only revoke tokens issued to them. Confidential clients,
+//! such as an administration console, may revoke any token.
+//!
+//! [RFC7009]: (https://www.rfc-editor.org/rfc/rfc7009.html)
+
+use anyhow::Context as _;
+
+use crate::handlers::introspection::{self, TokenState};
+use crate::handlers::{Body, Error, Handler, Request, Response, Result, token};
+use crate::provider::{Provider, StateStore};
+use crate::types::{RevocationRequest, RevocationResponse};
+
+/// Revocation request handler.
+///
+/// # Errors
+///
+/// Returns an `OpenID4VP` error if the client cannot be authenticated, is not
+/// permitted to revoke the token, or if the provider is not available.
+async fn revocation(
+    issuer: &str, provider: &impl Provider, request: RevocationRequest,
+) -> Result<RevocationResponse> {
+    let client = introspection::authenticate(
+        issuer,
+        provider,
+        &request.client_id,
+        request.client_assertion.as_ref(),
+    )
+    .await?;
+
+    // invalid tokens do not cause an error
+    let Some(state) =
+        introspection::find(issuer, provider, &request.token, request.token_type_hint).await
+    else {
+        return Ok(RevocationResponse);
+    };
+
+    let issued_to = match &state {
+        TokenState::Access(state) => state.body.client_id.as_ref(),
+        TokenState::Refresh(state) => state.body.client_id.as_ref(),
+    };
+    if !introspection::is_confidential(&client) && issued_to != Some(&client.client_id) {
+        return Err(Error::UnauthorizedClient("token was not issued to the client".to_string()));
+    }
+
+    // revoking a refresh token also revokes its family and their access tokens
+    match &state {
+        TokenState::Access(_) => token::revoke_access(issuer, provider, &request.token).await?,
+        TokenState::Refresh(state) => {
+            token::revoke_family(issuer, provider, &state.body.family_id).await?;
+            StateStore::purge(provider, issuer, &request.token).await.context("purging state")?;
+        }
+    }
+
+    Ok(RevocationResponse)
+}
+
+impl<P: Provider> Handler<RevocationResponse, P> for Request<RevocationRequest> {
+    type Error = Error;
+
+    async fn handle(
+        self, issuer: &str, provider: &P,
+    ) -> Result<impl Into<Response<RevocationResponse>>, Self::Error> {
+        revocation(issuer, provider, self.body).await
+    }
+}
+
+impl Body for RevocationRequest {}
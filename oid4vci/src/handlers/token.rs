@@ -11,8 +11,6 @@
 //! credentials, or other sensitive information, as well as the "Pragma"
 //! response header field [RFC2616](https://www.rfc-editor.org/rfc/rfc2616) with a value of "no-cache".
 
-// TODO: verify `client_assertion` JWT, when set
-
 use std::fmt::Debug;
 
 use anyhow::Context as _;
@@ -21,15 +19,23 @@ use credibil_core::state::State;
 use serde::de::DeserializeOwned;
 
 use crate::error::{invalid, server};
-use crate::handlers::{Body, Error, Handler, Request, Response, Result};
+use crate::handlers::{
+    Body, Error, Handler, Headers, Request, Response, Result, TokenHeaders, configuration_ids,
+    record,
+};
 use crate::oauth::GrantType;
-use crate::provider::{Metadata, Provider, StateStore};
-use crate::state::{Authorized, Expire, Offered, Token};
+use crate::provider::{Metadata, Provider, StateLock, StateStore, Subject};
+use crate::state::{Authorized, Expire, Family, Offered, Refresh, Token};
 use crate::types::{
-    AuthorizationDefinition, AuthorizationDetail, AuthorizedDetail, IssuerMetadata, TokenGrantType,
-    TokenRequest, TokenResponse, TokenType,
+    AuthorizationDefinition, AuthorizationDetail, AuthorizedDetail, ClientAttestationClaims,
+    IssuanceEventKind, IssuerMetadata, ServerMetadata, TokenGrantType, TokenRequest, TokenResponse,
+    TokenType,
 };
-use crate::{generate, pkce};
+use crate::{access_token, client_auth, dpop, generate, pkce};
+
+// State key prefix used to track the current refresh token in a family of
+// rotated refresh tokens.
+const FAMILY_PREFIX: &str = "refresh-family:";
 
 /// Token request handler.
 ///
@@ -38,16 +44,36 @@ use crate::{generate, pkce};
 /// Returns an `OpenID4VP` error if the request is invalid or if the provider is
 /// not available.
 async fn token(
-    issuer: &str, provider: &impl Provider, request: TokenRequest,
+    issuer: &str, provider: &impl Provider, request: TokenRequest, headers: &TokenHeaders,
 ) -> Result<TokenResponse> {
+    let Ok(server) = Metadata::server(provider, issuer).await else {
+        return Err(invalid!("unknown authorization server"));
+    };
+
+    // verify the DPoP proof before the grant is used so a Client can retry
+    // with a server-provided nonce
+    let dpop_jkt = verify_dpop(issuer, provider, headers, &server).await?;
+
+    // the Wallet may authenticate using a client attestation
+    let wallet = client_auth::verify_attestation(
+        issuer,
+        provider,
+        &headers.attestation,
+        request.client_id.as_deref(),
+        &server,
+    )
+    .await?;
+
     let mut ctx = Context {
         issuer,
         offered: None,
         authorized: None,
+        refresh: None,
+        wallet,
     };
 
     // get previously authorized credentials from state
-    let (subject_id, authorized_details) = match &request.grant_type {
+    let (subject_id, authorized_details, scope) = match &request.grant_type {
         TokenGrantType::PreAuthorizedCode {
             pre_authorized_code, ..
         } => {
@@ -59,12 +85,17 @@ async fn token(
             let Some(authorization_details) = state.body.details else {
                 return Err(server!("no authorized items"));
             };
-            (subject_id, authorization_details)
+            (subject_id, authorization_details, None)
         }
         TokenGrantType::AuthorizationCode { code, .. } => {
             let state = get_state::<Authorized>(issuer, code, provider).await?;
             ctx.authorized = Some(state.body.clone());
-            (state.body.subject_id, state.body.details)
+            (state.body.subject_id, state.body.details, state.body.scope)
+        }
+        TokenGrantType::RefreshToken { refresh_token } => {
+            let refresh = get_refresh(issuer, provider, refresh_token).await?.body;
+            ctx.refresh = Some(refresh.clone());
+            (refresh.subject_id, refresh.authorized_details, refresh.scope)
         }
     };
 
@@ -72,26 +103,61 @@ async fn token(
 
     // get the subset of requested credentials from those previously authorized
     let retained_details = request.retain(provider, &ctx, &authorized_details).await?;
+    let retained_details = authorize_wallet(provider, &ctx, &subject_id, retained_details).await?;
+
+    // credentials authorized using `scope` are not returned as `authorization_details`
+    let (scope, requested_details) =
+        split_scope(issuer, provider, scope.as_deref(), &retained_details).await?;
+
+    // bind the token to the DPoP proof's key, when provided
+    check_dpop(&ctx, dpop_jkt.as_ref())?;
+    let token_type = if dpop_jkt.is_some() { TokenType::DPoP } else { TokenType::Bearer };
+
+    // the refresh token is only used once the request has been verified
+    if let TokenGrantType::RefreshToken { refresh_token } = &request.grant_type {
+        redeem_refresh(issuer, provider, refresh_token).await?;
+    }
 
     // update state
-    let state = State {
+    let mut state = State {
         body: Token {
             subject_id,
             access_token: generate::token(),
             authorized_details: retained_details,
+            scope,
+            client_id: request.client_id.clone().filter(|id| !id.is_empty()),
+            dpop_jkt,
+            grant_id: ctx.refresh.as_ref().map_or_else(generate::token, |r| r.family_id.clone()),
+            wallet: ctx.wallet.clone().or_else(|| ctx.refresh.as_ref()?.wallet.clone()),
         },
         expires_at: Utc::now() + Expire::Access.duration(),
     };
+    let configuration =
+        Metadata::configuration(provider, issuer).await.context("getting configuration")?;
+    if configuration.jwt_access_tokens {
+        state.body.access_token =
+            access_token::issue(issuer, provider, &server, &state.body, state.expires_at).await?;
+    }
     StateStore::put(provider, issuer, &state.body.access_token, &state)
         .await
         .context("saving state")?;
+    record_token(issuer, &request.grant_type, &state.body);
+
+    let supports_refresh = server.oauth.grant_types_supported.as_ref();
+    let refresh_token = if supports_refresh.is_some_and(|g| g.contains(&GrantType::RefreshToken)) {
+        Some(issue_refresh(issuer, provider, &state.body).await?)
+    } else {
+        None
+    };
 
     // return response
     Ok(TokenResponse {
         access_token: state.body.access_token,
-        token_type: TokenType::Bearer,
+        token_type,
         expires_in: Expire::Access.duration().num_seconds(),
-        authorization_details: Some(state.body.authorized_details),
+        refresh_token,
+        authorization_details: requested_details,
+        scope: state.body.scope,
     })
 }
 
@@ -101,17 +167,31 @@ impl<P: Provider> Handler<TokenResponse, P> for Request<TokenRequest> {
     async fn handle(
         self, issuer: &str, provider: &P,
     ) -> Result<impl Into<Response<TokenResponse>>, Self::Error> {
-        token(issuer, provider, self.body).await
+        token(issuer, provider, self.body, &TokenHeaders::default()).await
+    }
+}
+
+impl<P: Provider> Handler<TokenResponse, P> for Request<TokenRequest, TokenHeaders> {
+    type Error = Error;
+
+    async fn handle(
+        self, issuer: &str, provider: &P,
+    ) -> Result<impl Into<Response<TokenResponse>>, Self::Error> {
+        token(issuer, provider, self.body, &self.headers).await
     }
 }
 
 impl Body for TokenRequest {}
 
+impl Headers for TokenHeaders {}
+
 #[derive(Debug)]
 struct Context<'a> {
     issuer: &'a str,
     offered: Option<Offered>,
     authorized: Option<Authorized>,
+    refresh: Option<Refresh>,
+    wallet: Option<ClientAttestationClaims>,
 }
 
 async fn get_state<T: DeserializeOwned>(
@@ -127,6 +207,190 @@ async fn get_state<T: DeserializeOwned>(
     Ok(state)
 }
 
+// Verify the DPoP proof, when provided, returning the thumbprint of its key.
+async fn verify_dpop(
+    issuer: &str, provider: &impl Provider, headers: &TokenHeaders, server: &ServerMetadata,
+) -> Result<Option<String>> {
+    let Some(proof) = &headers.dpop else {
+        return Ok(None);
+    };
+    Ok(Some(dpop::verify(issuer, provider, proof, &server.oauth.token_endpoint, None).await?))
+}
+
+// Check the DPoP proof's key, identified by its thumbprint, may be bound to
+// the token. A refresh token bound to a DPoP key may only be used with the
+// same key.
+fn check_dpop(ctx: &Context<'_>, dpop_jkt: Option<&String>) -> Result<()> {
+    if ctx
+        .refresh
+        .as_ref()
+        .and_then(|r| r.dpop_jkt.as_ref())
+        .is_some_and(|jkt| dpop_jkt != Some(jkt))
+    {
+        return Err(Error::InvalidDpopProof("DPoP key does not match refresh token".to_string()));
+    }
+    Ok(())
+}
+
+// Split the authorized credentials into those authorized using `scope` and
+// those returned as `authorization_details`. The `scope` is limited to values
+// for credentials that remain authorized.
+async fn split_scope(
+    issuer: &str, provider: &impl Provider, scope: Option<&str>, details: &[AuthorizedDetail],
+) -> Result<(Option<String>, Option<Vec<AuthorizedDetail>>)> {
+    let Some(scope) = scope else {
+        return Ok((None, Some(details.to_vec())));
+    };
+    let Ok(metadata) = Metadata::issuer(provider, issuer).await else {
+        return Err(invalid!("unknown credential issuer"));
+    };
+
+    let mut scopes = vec![];
+    let mut scoped_ids = vec![];
+    for item in scope.split_whitespace() {
+        let Some(config_id) = metadata.scope_configuration_id(item) else {
+            continue;
+        };
+        if details.iter().any(|d| d.credential_configuration_id() == Some(config_id.as_str())) {
+            scopes.push(item);
+            scoped_ids.push(config_id.as_str());
+        }
+    }
+
+    let requested = details
+        .iter()
+        .filter(|d| d.credential_configuration_id().is_none_or(|id| !scoped_ids.contains(&id)))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Ok((
+        (!scopes.is_empty()).then(|| scopes.join(" ")),
+        (!requested.is_empty()).then_some(requested),
+    ))
+}
+
+// Get the state of an unused refresh token. Presenting a used token revokes
+// its whole family.
+async fn get_refresh(
+    issuer: &str, provider: &impl Provider, refresh_token: &str,
+) -> Result<State<Refresh>> {
+    let Ok(state) = StateStore::get::<Refresh>(provider, issuer, refresh_token).await else {
+        return Err(Error::InvalidGrant("invalid refresh token".to_string()));
+    };
+    if state.is_expired() {
+        StateStore::purge(provider, issuer, refresh_token).await.context("purging state")?;
+        return Err(Error::InvalidGrant("refresh token has expired".to_string()));
+    }
+
+    if state.body.used {
+        revoke_family(issuer, provider, &state.body.family_id).await?;
+        return Err(Error::InvalidGrant("refresh token has already been used".to_string()));
+    }
+
+    Ok(state)
+}
+
+// Exchange a refresh token, marking it as used rather than removing it so
+// reuse can be detected. The token is re-read and marked while holding a lock
+// so a token exchanged by a concurrent request is treated as reused.
+async fn redeem_refresh(issuer: &str, provider: &impl Provider, refresh_token: &str) -> Result<()> {
+    let _guard = StateLock::lock(provider, issuer, "refresh").await.context("locking state")?;
+    let mut state = get_refresh(issuer, provider, refresh_token).await?;
+    state.body.used = true;
+    StateStore::put(provider, issuer, refresh_token, &state).await.context("saving state")?;
+    Ok(())
+}
+
+// Issue a (rotated) refresh token for the access token. The new token becomes
+// the current token in the family of the token's grant.
+async fn issue_refresh(issuer: &str, provider: &impl Provider, token: &Token) -> Result<String> {
+    let family_id = token.grant_id.clone();
+    let family_key = format!("{FAMILY_PREFIX}{family_id}");
+    let refresh_token = generate::token();
+    let expires_at = Utc::now() + Expire::Refresh.duration();
+
+    let state = State {
+        body: Refresh {
+            subject_id: token.subject_id.clone(),
+            authorized_details: token.authorized_details.clone(),
+            scope: token.scope.clone(),
+            client_id: token.client_id.clone(),
+            dpop_jkt: token.dpop_jkt.clone(),
+            family_id: family_id.clone(),
+            used: false,
+            wallet: token.wallet.clone(),
+        },
+        expires_at,
+    };
+    StateStore::put(provider, issuer, &refresh_token, &state).await.context("saving state")?;
+
+    let mut access_tokens = StateStore::get::<Family>(provider, issuer, &family_key)
+        .await
+        .map(|family| family.body.access_tokens)
+        .unwrap_or_default();
+    access_tokens.push(token.access_token.clone());
+    let family = State {
+        body: Family {
+            refresh_token: refresh_token.clone(),
+            access_tokens,
+        },
+        expires_at,
+    };
+    StateStore::put(provider, issuer, &family_key, &family).await.context("saving state")?;
+
+    Ok(refresh_token)
+}
+
+/// Revoke a family of rotated refresh tokens along with the access tokens
+/// issued from it.
+///
+/// # Errors
+///
+/// Returns an error if the provider is not available.
+pub async fn revoke_family(issuer: &str, provider: &impl Provider, family_id: &str) -> Result<()> {
+    let family_key = format!("{FAMILY_PREFIX}{family_id}");
+    if let Ok(family) = StateStore::get::<Family>(provider, issuer, &family_key).await {
+        for access_token in &family.body.access_tokens {
+            revoke_access(issuer, provider, access_token).await?;
+        }
+        StateStore::purge(provider, issuer, &family.body.refresh_token)
+            .await
+            .context("purging state")?;
+    }
+    StateStore::purge(provider, issuer, &family_key).await.context("purging state")?;
+    Ok(())
+}
+
+/// Revoke an access token. JWT access tokens are verified without consulting
+/// their state so are also denied until they expire.
+///
+/// # Errors
+///
+/// Returns an error if the provider is not available.
+pub async fn revoke_access(
+    issuer: &str, provider: &impl Provider, access_token: &str,
+) -> Result<()> {
+    let Ok(state) = StateStore::get::<Token>(provider, issuer, access_token).await else {
+        return Ok(());
+    };
+    if access_token::is_jwt(access_token) {
+        access_token::deny(issuer, provider, access_token, state.expires_at).await?;
+    }
+    StateStore::purge(provider, issuer, access_token).await.context("purging state")?;
+    Ok(())
+}
+
+// Record the issue of an access token.
+fn record_token(issuer: &str, grant_type: &TokenGrantType, token: &Token) {
+    let event = IssuanceEventKind::TokenIssued {
+        subject_id: token.subject_id.clone(),
+        client_id: token.client_id.clone(),
+        grant_type: grant_type.into(),
+        credential_configuration_ids: configuration_ids(&token.authorized_details),
+    };
+    record(issuer, event);
+}
+
 impl TokenRequest {
     // Verify the token request.
     async fn verify(
@@ -134,12 +398,6 @@ impl TokenRequest {
     ) -> Result<()> {
         tracing::debug!("token::verify");
 
-        // TODO: get Issuer metadata
-        // If the Token Request contains authorization_details and Issuer
-        // metadata contains an authorization_servers parameter, the
-        // authorization_details object MUST contain the Issuer's identifier
-        // in locations.
-
         let Ok(server) = Metadata::server(provider, ctx.issuer).await else {
             return Err(invalid!("unknown authorization server"));
         };
@@ -213,6 +471,37 @@ impl TokenRequest {
                     return Err(Error::AccessDenied("`code_verifier` is invalid".to_string()));
                 }
             }
+            TokenGrantType::RefreshToken { .. } => {
+                let Some(refresh) = &ctx.refresh else {
+                    return Err(server!("refresh state not set"));
+                };
+
+                // grant_type supported?
+                if !grant_types_supported.contains(&GrantType::RefreshToken) {
+                    return Err(Error::InvalidGrant("unsupported `grant_type`".to_string()));
+                }
+
+                // client_id is the same as the one the refresh token was issued to
+                if self.client_id.as_ref().filter(|id| !id.is_empty()) != refresh.client_id.as_ref()
+                {
+                    return Err(Error::InvalidGrant(
+                        "refresh token was issued to another client".to_string(),
+                    ));
+                }
+            }
+        }
+
+        self.authenticate(issuer, provider, ctx, &server).await
+    }
+
+    // Authenticate the client, when identified, and check its scopes are
+    // supported.
+    async fn authenticate(
+        &self, issuer: &str, provider: &impl Provider, ctx: &Context<'_>, server: &ServerMetadata,
+    ) -> Result<()> {
+        // the client being authenticated is identified by `client_id`
+        if self.client_assertion.is_some() && self.client_id.is_none() {
+            return Err(Error::InvalidClient("`client_id` is missing".to_string()));
         }
 
         if let Some(client_id) = &self.client_id {
@@ -220,6 +509,16 @@ impl TokenRequest {
             let Ok(client) = Metadata::client(provider, issuer, client_id).await else {
                 return Err(Error::InvalidClient(format!("{client_id} is not a valid client_id")));
             };
+            client_auth::authenticate(
+                issuer,
+                provider,
+                &client.oauth,
+                self.client_assertion.as_ref(),
+                ctx.wallet.as_ref(),
+                server,
+            )
+            .await?;
+
             // Client and server must support the same scopes.
             if let Some(client_scope) = &client.oauth.scope {
                 if let Some(server_scopes) = &server.oauth.scopes_supported {
@@ -238,8 +537,6 @@ impl TokenRequest {
         Ok(())
     }
 
-    // TODO: add `client_assertion` JWT verification
-
     // Filter previously authorized credentials by those selfed.
     async fn retain(
         &self, provider: &impl Provider, ctx: &Context<'_>, authorized: &[AuthorizedDetail],
@@ -257,6 +554,14 @@ impl TokenRequest {
         let mut retained = vec![];
 
         for detail in requested {
+            // the issuer's identifier must be in `locations` when it relies on
+            // separate authorization servers
+            if !issuer.is_located(detail.locations.as_deref()) {
+                return Err(Error::InvalidAuthorizationDetails(
+                    "`locations` must contain the credential issuer".to_string(),
+                ));
+            }
+
             // check requested `authorization_detail` has been previously authorized
             let mut found = false;
             for ad in authorized {
@@ -286,6 +591,47 @@ impl TokenRequest {
     }
 }
 
+// Limit authorized credentials to those the subject may be issued using the
+// attested Wallet, when the Wallet authenticated using a client attestation.
+async fn authorize_wallet(
+    provider: &impl Provider, ctx: &Context<'_>, subject_id: &str, details: Vec<AuthorizedDetail>,
+) -> Result<Vec<AuthorizedDetail>> {
+    let Some(wallet) = &ctx.wallet else {
+        return Ok(details);
+    };
+
+    let mut authorized = vec![];
+    for mut detail in details {
+        let AuthorizationDefinition::ConfigurationId {
+            credential_configuration_id,
+        } = &detail.authorization_detail.credential
+        else {
+            return Err(server!("authorized credential has no `credential_configuration_id`"));
+        };
+        let identifiers = Subject::authorize(
+            provider,
+            ctx.issuer,
+            subject_id,
+            credential_configuration_id,
+            Some(wallet),
+        )
+        .await
+        .map_err(|e| Error::AccessDenied(format!("issue authorizing wallet: {e}")))?;
+
+        detail.credential_identifiers.retain(|id| identifiers.contains(id));
+        if !detail.credential_identifiers.is_empty() {
+            authorized.push(detail);
+        }
+    }
+
+    if authorized.is_empty() {
+        return Err(Error::AccessDenied(
+            "wallet is not authorized for the requested credentials".to_string(),
+        ));
+    }
+    Ok(authorized)
+}
+
 // Verify requested claims exist as supported claims and all mandatory claims
 // have been requested.
 fn verify_claims(issuer: &IssuerMetadata, detail: &AuthorizationDetail) -> Result<()> {
@@ -0,0 +1,95 @@
+//! # JWT Checks
+//!
+//! Checks shared by the verification of JWTs presented by clients: client
+//! assertions, client attestation PoPs, DPoP proofs, and Credential Request
+//! proofs.
+
+use anyhow::{Context as _, anyhow};
+use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::{DateTime, Utc};
+use credibil_core::state::State;
+use serde_json::Value;
+
+use crate::handlers::Result;
+use crate::provider::{NonceKind, NonceStore, Provider, StateLock, StateStore};
+
+/// The default maximum allowable difference, in seconds, between a JWT's time
+/// claims and the server's clock.
+pub const CLOCK_SKEW: i64 = 60;
+
+// JWK members that are only present in private keys.
+const PRIVATE_KEY_MEMBERS: [&str; 7] = ["d", "p", "q", "dp", "dq", "qi", "k"];
+
+/// Decode a JWS's protected header, checking it is signed using one of the
+/// `supported` algorithms and does not contain private key material.
+///
+/// # Errors
+///
+/// Returns an error describing the problem with the header, for callers to
+/// report using their own error type.
+pub fn decode_header(jws: &str, supported: &[String]) -> anyhow::Result<Value> {
+    let Some((encoded, _)) = jws.split_once('.') else {
+        return Err(anyhow!("is not a JWS"));
+    };
+    let Ok(decoded) = Base64UrlUnpadded::decode_vec(encoded) else {
+        return Err(anyhow!("header is not base64url-encoded"));
+    };
+    let Ok(header) = serde_json::from_slice::<Value>(&decoded) else {
+        return Err(anyhow!("header is not a JSON object"));
+    };
+
+    let Some(alg) = header.get("alg").and_then(Value::as_str) else {
+        return Err(anyhow!("`alg` is missing"));
+    };
+    if !supported.iter().any(|a| a == alg) {
+        return Err(anyhow!("`alg` {alg} is not supported"));
+    }
+    if header.get("jwk").is_some_and(|jwk| PRIVATE_KEY_MEMBERS.iter().any(|m| jwk.get(m).is_some()))
+    {
+        return Err(anyhow!("header contains a private key"));
+    }
+
+    Ok(header)
+}
+
+/// Record the use of a single-use JWT, identified by its state `key`, until
+/// `expires_at`. Returns `false` when the JWT has already been used.
+///
+/// The check and the record are made while holding a lock so concurrent
+/// requests presenting the same JWT cannot both succeed.
+///
+/// # Errors
+///
+/// Returns an error if the provider is not available.
+pub async fn first_use(
+    issuer: &str, provider: &impl Provider, key: &str, expires_at: DateTime<Utc>,
+) -> Result<bool> {
+    let _guard = StateLock::lock(provider, issuer, "jti").await.context("locking state")?;
+    if StateStore::get::<String>(provider, issuer, key).await.is_ok() {
+        return Ok(false);
+    }
+    let state = State {
+        body: key.to_string(),
+        expires_at,
+    };
+    StateStore::put(provider, issuer, key, &state).await.context("saving state")?;
+    Ok(true)
+}
+
+/// Use a nonce of the given kind, returning when it expires. Returns `None`
+/// when the nonce was never issued as that kind or has already been used.
+///
+/// The nonce is looked up and removed while holding a lock so concurrent
+/// requests presenting the same nonce cannot both use it.
+///
+/// # Errors
+///
+/// Returns an error if the provider is not available.
+pub async fn use_nonce(
+    issuer: &str, provider: &impl Provider, kind: NonceKind, nonce: &str,
+) -> Result<Option<DateTime<Utc>>> {
+    let _guard = StateLock::lock(provider, issuer, "nonce").await.context("locking state")?;
+    let expires_at =
+        NonceStore::take_nonce(provider, issuer, kind, nonce).await.context("taking nonce")?;
+    Ok(expires_at)
+}
@@ -15,9 +15,14 @@ pub mod pkce;
 pub mod provider;
 pub mod types;
 
+mod access_token;
+mod authorization_server;
+mod client_auth;
+mod dpop;
 mod error;
 mod generate;
 mod handlers;
+mod jwt;
 mod state;
 
 /// Re-export key dependencies.
@@ -26,14 +31,16 @@ pub mod proof {
 }
 pub use credibil_core::state::State;
 pub use credibil_core::{OneMany, datastore, html, http};
+pub use credibil_jose as jose;
 pub use credibil_proof::{
     did, {self as identity},
 };
+pub use credibil_status as status;
+pub use credibil_vdc as vdc;
 pub use credibil_vdc::{mso_mdoc, sd_jwt, w3c_vc};
 use serde::{Deserialize, Serialize};
-pub use {credibil_jose as jose, credibil_status as status, credibil_vdc as vdc};
 
-pub use self::error::Error;
+pub use self::error::{DpopNonce, Error};
 pub use self::handlers::*;
 pub use self::types::*;
 
@@ -48,6 +55,29 @@ pub enum JwtType {
     /// JWT `typ` for Wallet's Proof of possession of key material.
     #[serde(rename = "oid4vci-proof+jwt")]
     ProofJwt,
+
+    /// JWT `typ` for a Wallet Provider's attestation of Wallet key material.
+    #[serde(rename = "key-attestation+jwt")]
+    KeyAttestation,
+
+    /// JWT `typ` for a DPoP proof of possession of the key an access token
+    /// is bound to.
+    #[serde(rename = "dpop+jwt")]
+    DPoP,
+
+    /// JWT `typ` for a Wallet Provider's attestation of a Wallet instance
+    /// (client).
+    #[serde(rename = "oauth-client-attestation+jwt")]
+    ClientAttestation,
+
+    /// JWT `typ` for a Wallet instance's proof of possession of the key bound
+    /// to its client attestation.
+    #[serde(rename = "oauth-client-attestation-pop+jwt")]
+    ClientAttestationPop,
+
+    /// JWT `typ` for a self-contained JWT access token.
+    #[serde(rename = "at+jwt")]
+    AccessToken,
 }
 
 impl From<JwtType> for String {
@@ -61,6 +91,11 @@ impl From<&JwtType> for String {
         match t {
             JwtType::Jwt => "jwt".to_string(),
             JwtType::ProofJwt => "oid4vci-proof+jwt".to_string(),
+            JwtType::KeyAttestation => "key-attestation+jwt".to_string(),
+            JwtType::DPoP => "dpop+jwt".to_string(),
+            JwtType::ClientAttestation => "oauth-client-attestation+jwt".to_string(),
+            JwtType::ClientAttestationPop => "oauth-client-attestation-pop+jwt".to_string(),
+            JwtType::AccessToken => "at+jwt".to_string(),
         }
     }
 }
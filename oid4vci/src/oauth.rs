@@ -279,6 +279,17 @@ pub struct OAuthServer {
     /// data only via PAR. If omitted, the default value is false.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub require_pushed_authorization_requests: Option<bool>,
+
+    /// A list of the JWS algorithms supported for DPoP proof JWTs. DPoP
+    /// proofs are rejected when not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dpop_signing_alg_values_supported: Option<Vec<String>>,
+
+    /// The URL of the challenge endpoint from which a client can obtain an
+    /// attestation challenge to use as the `nonce` in a client attestation
+    /// PoP.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub challenge_endpoint: Option<String>,
 }
 
 /// Grant Types supported by the Authorization Server.
@@ -292,6 +303,11 @@ pub enum GrantType {
     #[default]
     #[serde(rename = "urn:ietf:params:oauth:grant-type:pre-authorized_code")]
     PreAuthorizedCode,
+
+    /// The OAuth 2.0 Grant Type used to obtain a new access token using a
+    /// refresh token.
+    #[serde(rename = "refresh_token")]
+    RefreshToken,
 }
 
 /// Response types supported by the authorization endpoint.
@@ -357,6 +373,10 @@ pub enum TokenEndpointAuth {
 
     /// The client uses JWT for client authentication.
     PrivateKeyJwt,
+
+    /// The client (Wallet instance) authenticates using a Wallet Provider's
+    /// client attestation and proof of possession.
+    AttestJwtClientAuth,
     //
     // /// The client uses RFC6749 HTTP POST
     // ClientSecretPost,
@@ -7,33 +7,77 @@
 //! The default implementation only requires library users to implement the
 //! `Datastore` trait, which is used to store and retrieve data. Users can
 //! implement the other traits as needed.
+//!
+//! Issuance lifecycle events are optionally observed by registering an
+//! `IssuanceEvents` implementation using `observe_issuance`.
 
+use std::any::Any;
 use std::collections::HashMap;
+use std::fmt::{self, Debug};
 use std::future::Future;
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
 
 use anyhow::{Result, anyhow};
+use chrono::{DateTime, Utc};
 use credibil_core::datastore::Datastore;
+use credibil_core::state::State;
 pub use credibil_core::state::StateStore;
 pub use credibil_proof::{Resolver, Signature};
-pub use credibil_status::StatusStore;
+pub use credibil_status::{BitstringEncoder, CwtEncoder, Registry, StatusStore};
 
-use crate::types::{ClientMetadata, Dataset, IssuerMetadata, ServerMetadata};
+use crate::types::{
+    ClientAttestationClaims, ClientMetadata, Dataset, IssuanceEvent, IssuerConfiguration,
+    IssuerMetadata, NotificationRequest, ServerMetadata,
+};
 
 const METADATA: &str = "METADATA";
 const ISSUER: &str = "ISSUER";
 const SERVER: &str = "SERVER";
+const CONFIGURATION: &str = "CONFIGURATION";
 const SUBJECT: &str = "SUBJECT";
+const WALLET_PROVIDER: &str = "WALLETPROVIDER";
+const NONCE: &str = "NONCE";
+const CHALLENGE: &str = "CHALLENGE";
+
+// The registered observer of issuance lifecycle events.
+static ISSUANCE_EVENTS: OnceLock<Box<dyn IssuanceEvents>> = OnceLock::new();
+
+// Per-owner, per-resource locks used to serialize state updates.
+static LOCKS: LazyLock<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
 
 /// Issuer Provider trait.
 pub trait Provider:
-    Metadata + Subject + StateStore + Signature + Resolver + StatusStore + Clone
+    Metadata
+    + Subject
+    + StateLock
+    + StateStore
+    + NonceStore
+    + Signature
+    + Resolver
+    + StatusStore
+    + Registry
+    + CwtEncoder
+    + BitstringEncoder
+    + Clone
 {
 }
 
 /// A blanket implementation for `Provider` trait so that any type implementing
 /// the required super traits is considered a `Provider`.
 impl<T> Provider for T where
-    T: Metadata + Subject + StateStore + Signature + Resolver + StatusStore + Clone
+    T: Metadata
+        + Subject
+        + StateLock
+        + StateStore
+        + NonceStore
+        + Signature
+        + Resolver
+        + StatusStore
+        + Registry
+        + CwtEncoder
+        + BitstringEncoder
+        + Clone
 {
 }
 
@@ -51,11 +95,23 @@ pub trait Metadata: Send + Sync {
     /// Authorization Server metadata for the specified issuer/server.
     fn server(&self, owner: &str) -> impl Future<Output = Result<ServerMetadata>> + Send;
 
+    /// Issuer and Authorization Server settings that are not published as
+    /// metadata.
+    fn configuration(
+        &self, owner: &str,
+    ) -> impl Future<Output = Result<IssuerConfiguration>> + Send;
+
     /// Used to dynamically register OAuth 2.0 clients with the authorization
     /// server.
     fn register(
         &self, owner: &str, client: &ClientMetadata,
     ) -> impl Future<Output = Result<ClientMetadata>> + Send;
+
+    /// Whether the Wallet Provider identified by `wallet_provider` (the DID
+    /// of its signing key) is trusted to attest to Wallet key material.
+    fn wallet_provider(
+        &self, owner: &str, wallet_provider: &str,
+    ) -> impl Future<Output = Result<bool>> + Send;
 }
 
 /// The Subject trait specifies how the library expects issuance subject (user)
@@ -65,8 +121,13 @@ pub trait Subject: Send + Sync {
     /// `credential_configuration_id`. Returns a one or more
     /// `credential_identifier`s the subject (holder) is authorized to
     /// request.
+    ///
+    /// When the Wallet authenticated using a client attestation, `wallet`
+    /// holds the attested Wallet metadata, allowing issuance to be limited to
+    /// Wallets meeting the issuer's requirements.
     fn authorize(
         &self, owner: &str, subject_id: &str, credential_configuration_id: &str,
+        wallet: Option<&ClientAttestationClaims>,
     ) -> impl Future<Output = Result<Vec<String>>> + Send;
 
     /// Returns a populated `Dataset` object for the given subject (holder) and
@@ -74,6 +135,171 @@ pub trait Subject: Send + Sync {
     fn dataset(
         &self, owner: &str, subject_id: &str, credential_identifier: &str,
     ) -> impl Future<Output = Result<Dataset>> + Send;
+
+    /// Returns the `Dataset` for a deferred issuance transaction. The issuer
+    /// signals the credential is ready to be issued by returning a dataset
+    /// that is no longer `pending`.
+    ///
+    /// Defaults to the subject's current `dataset` for the credential.
+    fn deferred(
+        &self, owner: &str, subject_id: &str, transaction_id: &str, credential_identifier: &str,
+    ) -> impl Future<Output = Result<Dataset>> + Send {
+        let _ = transaction_id;
+        self.dataset(owner, subject_id, credential_identifier)
+    }
+
+    /// Called when the Wallet notifies the issuer of an event for the
+    /// credentials issued to the subject with the request's
+    /// `notification_id`, allowing the issuer to react. For example, by
+    /// revoking credentials the holder has deleted.
+    ///
+    /// Issued credentials can be selected using
+    /// `CredentialSelector::NotificationId`. Repeated notifications of the
+    /// same event are not passed on.
+    fn notify(
+        &self, owner: &str, subject_id: &str, notification: &NotificationRequest,
+    ) -> impl Future<Output = Result<()>> + Send {
+        let _ = (owner, subject_id, notification);
+        async { Ok(()) }
+    }
+}
+
+/// The `IssuanceEvents` trait is used by implementers to observe the issuance
+/// lifecycle. Each handler records the events occurring in it, for example to
+/// maintain an audit trail of issued credentials.
+///
+/// Observing events is optional: events are ignored until an implementation
+/// is registered using [`observe_issuance`].
+pub trait IssuanceEvents: Send + Sync {
+    /// Record an issuance lifecycle event. Implementations should hand the
+    /// event off, for example to a channel, rather than block the handler.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event could not be recorded.
+    fn record(&self, owner: &str, event: &IssuanceEvent) -> Result<()>;
+}
+
+/// Register the process-wide observer of issuance lifecycle events.
+///
+/// # Errors
+///
+/// Returns an error if an observer has already been registered.
+pub fn observe_issuance(events: impl IssuanceEvents + 'static) -> Result<()> {
+    ISSUANCE_EVENTS
+        .set(Box::new(events))
+        .map_err(|_| anyhow!("an issuance events observer is already registered"))
+}
+
+// The registered observer of issuance lifecycle events, if any.
+pub(crate) fn issuance_events() -> Option<&'static dyn IssuanceEvents> {
+    ISSUANCE_EVENTS.get().map(AsRef::as_ref)
+}
+
+/// The `StateLock` trait is used to serialize state updates that must not
+/// interleave, such as checking and recording that a one-time value has been
+/// used.
+pub trait StateLock: Send + Sync {
+    /// Acquire exclusive access to the owner's `resource`, released when the
+    /// returned guard is dropped.
+    ///
+    /// The default implementation only serializes access within the current
+    /// process. Implementations sharing state across processes should
+    /// override it to use a distributed lock.
+    fn lock(&self, owner: &str, resource: &str) -> impl Future<Output = Result<StateGuard>> + Send {
+        let key = format!("{owner}:{resource}");
+        let lock = LOCKS
+            .lock()
+            .map(|mut locks| locks.entry(key.clone()).or_default().clone())
+            .map_err(|_| anyhow!("state locks are poisoned"));
+        async move {
+            let guard = lock?.lock_owned().await;
+            Ok(StateGuard::new(LocalGuard {
+                key,
+                guard: Some(guard),
+            }))
+        }
+    }
+}
+
+// A guard for the default in-process lock. The lock is removed once its last
+// user releases it.
+struct LocalGuard {
+    key: String,
+    guard: Option<tokio::sync::OwnedMutexGuard<()>>,
+}
+
+impl Drop for LocalGuard {
+    fn drop(&mut self) {
+        let Ok(mut locks) = LOCKS.lock() else {
+            return;
+        };
+        // requests waiting for the lock hold a reference to it
+        drop(self.guard.take());
+        if locks.get(&self.key).is_some_and(|lock| Arc::strong_count(lock) == 1) {
+            locks.remove(&self.key);
+        }
+    }
+}
+
+/// The kind of single-use nonce being held. Nonces of one kind cannot be
+/// used in place of another.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NonceKind {
+    /// A `c_nonce` issued by the Nonce Endpoint for Credential Request
+    /// proofs.
+    CNonce,
+
+    /// An attestation challenge issued by the Challenge Endpoint for client
+    /// attestation PoPs.
+    Challenge,
+}
+
+impl NonceKind {
+    const fn partition(self) -> &'static str {
+        match self {
+            Self::CNonce => NONCE,
+            Self::Challenge => CHALLENGE,
+        }
+    }
+}
+
+/// The `NonceStore` trait is used by implementers to hold the single-use
+/// nonces issued by the Nonce and Challenge Endpoints until they are used.
+pub trait NonceStore: Send + Sync {
+    /// Save a nonce of the given kind that can be used until `expires_at`.
+    /// Implementations should discard nonces that expire without being used.
+    fn put_nonce(
+        &self, owner: &str, kind: NonceKind, nonce: &str, expires_at: DateTime<Utc>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    /// Remove a nonce of the given kind, returning when it expires. Returns
+    /// `None` when the nonce was never issued as that kind or has already
+    /// been used.
+    fn take_nonce(
+        &self, owner: &str, kind: NonceKind, nonce: &str,
+    ) -> impl Future<Output = Result<Option<DateTime<Utc>>>> + Send;
+}
+
+/// Exclusive access to a resource, released when dropped.
+pub struct StateGuard {
+    _guard: Box<dyn Any + Send + Sync>,
+}
+
+impl StateGuard {
+    /// Create a guard from the underlying lock's guard.
+    #[must_use]
+    pub fn new(guard: impl Any + Send + Sync) -> Self {
+        Self {
+            _guard: Box::new(guard),
+        }
+    }
+}
+
+impl Debug for StateGuard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("StateGuard")
+    }
 }
 
 impl<T: Datastore> Metadata for T {
@@ -98,6 +324,13 @@ impl<T: Datastore> Metadata for T {
         Ok(serde_json::from_slice(&data)?)
     }
 
+    async fn configuration(&self, owner: &str) -> Result<IssuerConfiguration> {
+        let Some(data) = Datastore::get(self, owner, METADATA, CONFIGURATION).await? else {
+            return Ok(IssuerConfiguration::default());
+        };
+        Ok(serde_json::from_slice(&data)?)
+    }
+
     async fn register(&self, owner: &str, client: &ClientMetadata) -> Result<ClientMetadata> {
         let mut client = client.clone();
         client.oauth.client_id = uuid::Uuid::new_v4().to_string();
@@ -106,11 +339,16 @@ impl<T: Datastore> Metadata for T {
         Datastore::put(self, owner, METADATA, &client.oauth.client_id, &data).await?;
         Ok(client)
     }
+
+    async fn wallet_provider(&self, owner: &str, wallet_provider: &str) -> Result<bool> {
+        Ok(Datastore::get(self, owner, WALLET_PROVIDER, wallet_provider).await?.is_some())
+    }
 }
 
 impl<T: Datastore> Subject for T {
     async fn authorize(
         &self, owner: &str, subject_id: &str, credential_configuration_id: &str,
+        _: Option<&ClientAttestationClaims>,
     ) -> Result<Vec<String>> {
         let Some(data) = Datastore::get(self, owner, SUBJECT, subject_id).await? else {
             return Err(anyhow!("could not find dataset for subject"));
@@ -144,3 +382,39 @@ impl<T: Datastore> Subject for T {
         Ok(dataset.clone())
     }
 }
+
+impl<T: Datastore> StateLock for T {}
+
+impl<T: Datastore> NonceStore for T {
+    async fn put_nonce(
+        &self, owner: &str, kind: NonceKind, nonce: &str, expires_at: DateTime<Utc>,
+    ) -> Result<()> {
+        let partition = kind.partition();
+
+        // discard nonces that expired without being used
+        for (_, data) in Datastore::get_all(self, owner, partition).await? {
+            let state: State<String> = serde_json::from_slice(&data)?;
+            if state.is_expired() {
+                Datastore::delete(self, owner, partition, &state.body).await?;
+            }
+        }
+
+        let state = State {
+            body: nonce.to_string(),
+            expires_at,
+        };
+        Datastore::put(self, owner, partition, nonce, &serde_json::to_vec(&state)?).await
+    }
+
+    async fn take_nonce(
+        &self, owner: &str, kind: NonceKind, nonce: &str,
+    ) -> Result<Option<DateTime<Utc>>> {
+        let partition = kind.partition();
+        let Some(data) = Datastore::get(self, owner, partition, nonce).await? else {
+            return Ok(None);
+        };
+        Datastore::delete(self, owner, partition, nonce).await?;
+        let state: State<String> = serde_json::from_slice(&data)?;
+        Ok(Some(state.expires_at))
+    }
+}
@@ -2,10 +2,12 @@
 //! in the issuance process.
 
 use chrono::TimeDelta;
+use credibil_jose::KeyBinding;
+use credibil_status::StatusListEntry;
 use serde::{Deserialize, Serialize};
 
 use crate::oauth::CodeChallengeMethod;
-use crate::types::{AuthorizedDetail, CredentialRequest};
+use crate::types::{AuthorizedDetail, ClientAttestationClaims, NotificationEvent, RequestObject};
 
 /// Pre-authorization state from the `create_offer` endpoint.
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
@@ -25,6 +27,19 @@ pub struct Offered {
     pub tx_code: Option<String>,
 }
 
+/// Pushed Authorization Request state, saved using the `request_uri`
+/// returned to the Wallet.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Pushed {
+    /// The pushed authorization request.
+    pub request: RequestObject,
+
+    /// The attested Wallet metadata, when the Wallet authenticated using a
+    /// client attestation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<ClientAttestationClaims>,
+}
+
 /// Authorization state.
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
 #[allow(clippy::struct_field_names)]
@@ -49,6 +64,10 @@ pub struct Authorized {
     /// A list of authorized `scope` or `authorization_details` entries along
     /// with credential metadata and dataset identifiers.
     pub details: Vec<AuthorizedDetail>,
+
+    /// The `scope` values credentials were authorized with, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
 }
 
 /// Token state.
@@ -65,14 +84,116 @@ pub struct Token {
     /// A list `authorization_details` entries including credential
     /// identifiers.
     pub authorized_details: Vec<AuthorizedDetail>,
+
+    /// The `scope` values of credentials authorized using `scope`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+
+    /// The `client_id` of the Client the token was issued to. Not set when
+    /// the token was obtained using anonymous pre-authorized access.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+
+    /// The JWK SHA-256 thumbprint of the DPoP key the token is bound to. Not
+    /// set for bearer tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dpop_jkt: Option<String>,
+
+    /// Identifies the authorization grant the token was issued under. Tokens
+    /// issued in exchange for the grant's refresh tokens share its
+    /// identifier, which is also the refresh token family's identifier.
+    /// Empty for tokens issued by third-party authorization servers.
+    pub grant_id: String,
+
+    /// The attested Wallet metadata, when the Wallet authenticated using a
+    /// client attestation. Not carried by JWT access tokens, whose
+    /// `credential_identifiers` are limited to the datasets the Wallet may be
+    /// issued when the token is issued.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<ClientAttestationClaims>,
 }
 
-// /// Issued Credential state (for Notification endpoint).
-// #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
-// pub struct Issued {
-//     /// The issued credential.
-//     pub credential: VerifiableCredential,
-// }
+/// Refresh token state.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Refresh {
+    /// Identifies the (previously authenticated) Holder in order that Issuer
+    /// can authorize credential issuance.
+    pub subject_id: String,
+
+    /// The `authorization_details` entries, including credential identifiers,
+    /// to reissue with new access tokens.
+    pub authorized_details: Vec<AuthorizedDetail>,
+
+    /// The `scope` values to reissue with new access tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+
+    /// The `client_id` of the Client the token was issued to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+
+    /// The JWK SHA-256 thumbprint of the DPoP key the token is bound to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dpop_jkt: Option<String>,
+
+    /// Identifies the chain of rotated refresh tokens descended from a single
+    /// authorization grant.
+    pub family_id: String,
+
+    /// Whether the token has been exchanged. Presenting a used token
+    /// indicates it has been compromised.
+    pub used: bool,
+
+    /// The attested Wallet metadata the token was issued with, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet: Option<ClientAttestationClaims>,
+}
+
+/// Refresh token family state, saved using the family's identifier.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Family {
+    /// The current (most recently issued) refresh token in the family.
+    pub refresh_token: String,
+
+    /// The access tokens issued for the authorization grant and in exchange
+    /// for the family's refresh tokens.
+    pub access_tokens: Vec<String>,
+}
+
+/// Server-provided DPoP nonce state, saved using the nonce prefixed to
+/// distinguish it from `c_nonce` values.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Nonce {
+    /// The nonce issued to the Client.
+    pub nonce: String,
+}
+
+/// Issuance record for the Notification endpoint, saved using the
+/// `notification_id` returned with the issued credential(s).
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Issued {
+    /// Identifies the Holder the credential(s) were issued to.
+    pub subject_id: String,
+
+    /// The `client_id` of the Wallet the credential(s) were issued to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+
+    /// The authorization grant of the access token the credential(s) were
+    /// issued with.
+    pub grant_id: String,
+
+    /// The credential configuration the credential(s) were issued under.
+    pub credential_configuration_id: String,
+
+    /// The Status List entries of the issued credential(s), linking the
+    /// record to the issued credential registry.
+    pub credentials: Vec<StatusListEntry>,
+
+    /// The last event the Wallet notified the Credential Issuer of.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<NotificationEvent>,
+}
 
 /// Deferred issuance state.
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
@@ -81,8 +202,23 @@ pub struct Deferred {
     /// state persistence key.
     pub transaction_id: String,
 
-    /// Save the Credential request when issuance is deferred.
-    pub credential_request: CredentialRequest,
+    /// The subject the credential is to be issued to. Only an access token
+    /// issued for the same subject can redeem the transaction.
+    pub subject_id: String,
+
+    /// The `credential_identifier` of the credential to issue.
+    pub credential_identifier: String,
+
+    /// The credential configuration the credential is to be issued under.
+    pub credential_configuration_id: String,
+
+    /// The access token's authorization detail the credential was matched to
+    /// when issuance was deferred.
+    pub authorized: AuthorizedDetail,
+
+    /// Keys taken from the Credential Request's verified proofs, used to bind
+    /// the credential once issued.
+    pub proof_keys: Vec<KeyBinding>,
 }
 
 /// Expire enum.
@@ -91,6 +227,12 @@ pub enum Expire {
     Authorized,
     /// Access state expiration.
     Access,
+    /// Refresh token expiration.
+    Refresh,
+    /// Issuance record expiration.
+    Issued,
+    /// Deferred issuance transaction expiration.
+    Deferred,
     // /// Nonce state expiration.
     // Nonce,
 }
@@ -102,6 +244,9 @@ impl Expire {
         match self {
             Self::Authorized => TimeDelta::try_minutes(5).unwrap_or_default(),
             Self::Access => TimeDelta::try_minutes(15).unwrap_or_default(),
+            Self::Refresh => TimeDelta::try_days(30).unwrap_or_default(),
+            Self::Issued => TimeDelta::try_days(7).unwrap_or_default(),
+            Self::Deferred => TimeDelta::try_days(7).unwrap_or_default(),
             // Self::Nonce => TimeDelta::try_minutes(10).unwrap_or_default(),
         }
     }
@@ -1,8 +1,10 @@
 //! # `OpenID` for Verifiable Credential Issuance
 
 mod authorization;
+mod configuration;
 mod credential;
 mod credential_offer;
+mod event;
 mod metadata;
 mod notification;
 mod token;
@@ -13,8 +15,10 @@ use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
 pub use self::authorization::*;
+pub use self::configuration::*;
 pub use self::credential::*;
 pub use self::credential_offer::*;
+pub use self::event::*;
 pub use self::metadata::*;
 pub use self::notification::*;
 pub use self::token::*;
@@ -33,6 +37,11 @@ pub struct Dataset {
     /// Specifies whether user information required for the credential subject
     /// is pending.
     pub pending: bool,
+
+    /// When `pending`, the minimum number of seconds the Wallet should wait
+    /// between requests to the Deferred Credential Endpoint. Defaults to 5.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<i64>,
 }
 
 /// A request for a nonce is made by sending an empty request to the Issuer's
@@ -49,3 +58,20 @@ pub struct NonceResponse {
     /// The nonce value.
     pub c_nonce: String,
 }
+
+/// A request for an attestation challenge is made by sending an empty request
+/// to the Authorization Server's Challenge endpoint (`challenge_endpoint`
+/// Authorization Server Metadata).
+#[derive(Clone, Debug, Default)]
+pub struct ChallengeRequest;
+
+/// Used by the Authorization Server to return a new attestation challenge,
+/// for use as the `nonce` in a client attestation PoP.
+///
+/// The Authorization Server MUST make the response uncacheable by adding a
+/// Cache-Control header field including the value `no-store`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ChallengeResponse {
+    /// The attestation challenge value.
+    pub attestation_challenge: String,
+}
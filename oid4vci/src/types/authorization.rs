@@ -1,14 +1,18 @@
 use std::fmt::{self, Display};
 use std::str::FromStr;
 
-use credibil_core::html;
+use chrono::serde::{ts_seconds, ts_seconds_option};
+use chrono::{DateTime, TimeDelta, Utc};
+use credibil_core::{OneMany, html};
+use credibil_jose::KeyBinding;
 use credibil_vdc::FormatProfile;
 use serde::de::{self, Deserializer, Visitor};
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
-use crate::oauth;
 use crate::oauth::{CodeChallengeMethod, ResponseType};
 use crate::types::ClaimsDescription;
+use crate::{generate, oauth};
 
 /// Build an [`AuthorizationRequest`].
 #[derive(Default, Debug)]
@@ -162,6 +166,7 @@ impl AuthorizationRequestBuilder {
 pub struct AuthorizationDetailBuilder<C> {
     credential: C,
     claims: Option<Vec<ClaimsDescription>>,
+    locations: Option<Vec<String>>,
 }
 
 impl Default for AuthorizationDetailBuilder<NoDefinition> {
@@ -169,6 +174,7 @@ impl Default for AuthorizationDetailBuilder<NoDefinition> {
         Self {
             credential: NoDefinition,
             claims: None,
+            locations: None,
         }
     }
 }
@@ -197,6 +203,7 @@ impl AuthorizationDetailBuilder<NoDefinition> {
                 credential_configuration_id: configuration_id.into(),
             }),
             claims: self.claims,
+            locations: self.locations,
         }
     }
 
@@ -206,6 +213,7 @@ impl AuthorizationDetailBuilder<NoDefinition> {
         AuthorizationDetailBuilder {
             credential: HasDefinition(AuthorizationDefinition::FormatProfile(format)),
             claims: self.claims,
+            locations: self.locations,
         }
     }
 }
@@ -221,6 +229,14 @@ impl<C> AuthorizationDetailBuilder<C> {
         self.claims.get_or_insert_with(Vec::new).push(cd);
         self
     }
+
+    /// Specify the Credential Issuer the authorization detail applies to. Used
+    /// when the Credential Issuer relies on separate authorization servers.
+    #[must_use]
+    pub fn location(mut self, credential_issuer: impl Into<String>) -> Self {
+        self.locations.get_or_insert_with(Vec::new).push(credential_issuer.into());
+        self
+    }
 }
 
 impl AuthorizationDetailBuilder<HasDefinition> {
@@ -231,7 +247,7 @@ impl AuthorizationDetailBuilder<HasDefinition> {
             r#type: AuthorizationDetailType::OpenIdCredential,
             credential: self.credential.0,
             claims: self.claims,
-            locations: None,
+            locations: self.locations,
         }
     }
 }
@@ -590,7 +606,6 @@ pub struct AuthorizationDetail {
     #[serde(flatten)]
     pub credential: AuthorizationDefinition,
 
-    // TODO: integrate locations
     /// If the Credential Issuer metadata contains an `authorization_servers`
     /// parameter, the authorization detail's locations field MUST be set to
     /// the Credential Issuer Identifier.
@@ -710,6 +725,134 @@ pub enum ClientAssertion {
     },
 }
 
+/// Claims for a JWT client assertion used to authenticate a confidential
+/// client as defined in [RFC7523].
+///
+/// [RFC7523]: (https://www.rfc-editor.org/rfc/rfc7523.html)
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ClientAssertionClaims {
+    /// The `client_id` of the client issuing the assertion.
+    pub iss: String,
+
+    /// The `client_id` of the client the assertion is for.
+    pub sub: String,
+
+    /// The authorization server the assertion is intended for: its issuer
+    /// identifier or the endpoint the assertion is sent to.
+    pub aud: OneMany<String>,
+
+    /// A unique identifier for the assertion, used to prevent reuse.
+    pub jti: String,
+
+    /// The time at which the assertion expires.
+    #[serde(with = "ts_seconds")]
+    pub exp: DateTime<Utc>,
+
+    /// The time at which the assertion was issued.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(with = "ts_seconds_option", default)]
+    pub iat: Option<DateTime<Utc>>,
+}
+
+impl ClientAssertionClaims {
+    /// Create a new `ClientAssertionClaims` instance for the client, valid
+    /// for 5 minutes.
+    #[must_use]
+    pub fn new(client_id: impl Into<String>, aud: impl Into<String>) -> Self {
+        let client_id = client_id.into();
+        let now = Utc::now();
+        Self {
+            iss: client_id.clone(),
+            sub: client_id,
+            aud: OneMany::One(aud.into()),
+            jti: generate::nonce(),
+            exp: now + TimeDelta::minutes(5),
+            iat: Some(now),
+        }
+    }
+}
+
+/// Claims of an OAuth Client Attestation: a Wallet Provider's attestation of
+/// a Wallet instance (client) and the key it uses to prove possession of the
+/// attestation. See [OAuth 2.0 Attestation-Based Client Authentication].
+///
+/// [OAuth 2.0 Attestation-Based Client Authentication]: (https://datatracker.ietf.org/doc/draft-ietf-oauth-attestation-based-client-auth)
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ClientAttestationClaims {
+    /// The identifier of the Wallet Provider issuing the attestation.
+    pub iss: String,
+
+    /// The `client_id` of the attested Wallet instance.
+    pub sub: String,
+
+    /// The time at which the attestation expires.
+    #[serde(with = "ts_seconds")]
+    pub exp: DateTime<Utc>,
+
+    /// The time at which the attestation was issued.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(with = "ts_seconds_option", default)]
+    pub iat: Option<DateTime<Utc>>,
+
+    /// The key the Wallet instance uses to sign Client Attestation PoPs.
+    pub cnf: KeyBinding,
+
+    /// A human-readable name of the Wallet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet_name: Option<String>,
+
+    /// A URL with further information about the Wallet.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet_link: Option<String>,
+
+    /// Additional Wallet metadata attested to by the Wallet Provider.
+    #[serde(flatten)]
+    pub metadata: Map<String, Value>,
+}
+
+/// Claims of an OAuth Client Attestation PoP: the Wallet instance's proof of
+/// possession of the key bound to its Client Attestation.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ClientAttestationPopClaims {
+    /// The `client_id` of the Wallet instance.
+    pub iss: String,
+
+    /// The issuer identifier of the authorization server.
+    pub aud: String,
+
+    /// A unique identifier for the PoP, used to prevent reuse.
+    pub jti: String,
+
+    /// The time at which the PoP was issued.
+    #[serde(with = "ts_seconds")]
+    pub iat: DateTime<Utc>,
+
+    /// A server-provided nonce.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+impl ClientAttestationPopClaims {
+    /// Create a new `ClientAttestationPopClaims` instance for the client.
+    #[must_use]
+    pub fn new(client_id: impl Into<String>, aud: impl Into<String>) -> Self {
+        Self {
+            iss: client_id.into(),
+            aud: aud.into(),
+            jti: generate::nonce(),
+            iat: Utc::now(),
+            nonce: None,
+        }
+    }
+
+    /// Set the server-provided nonce.
+    #[must_use]
+    pub fn nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.nonce = Some(nonce.into());
+        self
+    }
+}
+
 /// Pushed Authorization Request (PAR) response as defined in [RFC9126].
 ///
 /// [RFC9126]: (https://www.rfc-editor.org/rfc/rfc9126.html)
@@ -0,0 +1,31 @@
+//! # Issuer Configuration
+
+use serde::{Deserialize, Serialize};
+
+/// Issuer and Authorization Server settings that are not published as
+/// metadata.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct IssuerConfiguration {
+    /// The base URI managed Status Lists are published under, with
+    /// individual lists identified as `{status_list_uri}/{n}`. Defaults to
+    /// `{credential_issuer}/statuslists`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_list_uri: Option<String>,
+
+    /// Whether DPoP proofs must include a server-provided nonce. Clients
+    /// presenting a proof without a nonce receive a `use_dpop_nonce` error
+    /// containing a fresh nonce.
+    pub dpop_nonce_required: bool,
+
+    /// Whether the authorization server issues self-contained JWT access
+    /// tokens (`at+jwt`) rather than opaque tokens held in state. JWT access
+    /// tokens are verified using the keys published at `jwks_uri`.
+    pub jwt_access_tokens: bool,
+
+    /// The leeway, in seconds, allowed for clock skew between Wallets and the
+    /// Credential Issuer when checking the `iat` of Credential Request proofs
+    /// and key attestations. Defaults to 60 seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clock_skew: Option<i64>,
+}
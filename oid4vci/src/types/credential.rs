@@ -38,6 +38,9 @@ pub struct NoProofs;
 /// Proof of possession of key material is set.
 #[doc(hidden)]
 pub struct Proofs(Vec<String>);
+/// A key attestation is set.
+#[doc(hidden)]
+pub struct HasAttestation(String);
 
 impl CredentialRequestBuilder<NoCredential, NoProofs> {
     /// Create a new `CreateOfferRequestBuilder`.
@@ -88,6 +91,19 @@ impl<C> CredentialRequestBuilder<C, NoProofs> {
             response_encryption: self.response_encryption,
         }
     }
+
+    /// Specify a key attestation, signed by the Wallet Provider, for the keys
+    /// the issued Credentials are to be bound to.
+    #[must_use]
+    pub fn with_attestation(
+        self, attestation: impl Into<String>,
+    ) -> CredentialRequestBuilder<C, HasAttestation> {
+        CredentialRequestBuilder {
+            credential: self.credential,
+            proofs: HasAttestation(attestation.into()),
+            response_encryption: self.response_encryption,
+        }
+    }
 }
 
 impl<C> CredentialRequestBuilder<C, Proofs> {
@@ -131,6 +147,20 @@ impl CredentialRequestBuilder<HasCredential, Proofs> {
     }
 }
 
+impl CredentialRequestBuilder<HasCredential, HasAttestation> {
+    /// Build the Create Offer request.
+    #[must_use]
+    pub fn build(self) -> CredentialRequest {
+        CredentialRequest {
+            credential: self.credential.0,
+            proof: Some(Proof::Single(SingleProof::Attestation {
+                attestation: self.proofs.0,
+            })),
+            credential_response_encryption: self.response_encryption,
+        }
+    }
+}
+
 /// `CredentialRequest` is used by the Client to make a Credential Request to
 /// the Credential Endpoint.
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
@@ -353,26 +383,29 @@ pub struct AttestationClaims {
 /// Options for asserting the attack potential resistance of `key_storage` and
 /// `user_authentication` parameters.
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
-#[serde(rename_all = "snake_case")]
 pub enum AttackPotentialResistance {
     /// Used when key storage or user authentication is resistant to attack
     /// with attack potential "High", equivalent to VAN.5 according to ISO
     /// 18045.
+    #[serde(rename = "iso_18045_high")]
     Iso18045High,
 
     /// Used when key storage or user authentication is resistant to attack
     /// with attack potential "Moderate", equivalent to VAN.4 according to
     /// ISO 18045.
+    #[serde(rename = "iso_18045_moderate")]
     Iso18045Moderate,
 
     /// Used when key storage or user authentication is resistant to attack
     /// with attack potential "Enhanced-Basic", equivalent to VAN.3 according
     /// to ISO 18045.
+    #[serde(rename = "iso_18045_enhanced-basic")]
     Iso18045EnhancedBasic,
 
     /// Used when key storage or user authentication is resistant to attack
     /// with attack potential "Basic", equivalent to VAN.2 according to ISO
     /// 18045.
+    #[serde(rename = "iso_18045_basic")]
     #[default]
     Iso18045EBasic,
 }
@@ -428,6 +461,10 @@ pub enum CredentialResponse {
     TransactionId {
         /// The Deferred Issuance transaction identifier.
         transaction_id: String,
+
+        /// The minimum amount of time in seconds the Wallet should wait
+        /// between requests to the Deferred Credential Endpoint.
+        interval: i64,
     },
 }
 
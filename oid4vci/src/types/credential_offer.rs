@@ -20,6 +20,7 @@ pub struct CreateOfferRequestBuilder<C, S, P> {
     grant_types: Vec<GrantType>,
     tx_code: bool,
     by_ref: bool,
+    authorization_server: Option<String>,
 }
 
 /// No credential configuration id is set.
@@ -54,6 +55,7 @@ impl CreateOfferRequestBuilder<NoIdentifiers, NoSubjectId, PreAuthorized> {
             grant_types: vec![GrantType::PreAuthorizedCode],
             tx_code: true,
             by_ref: false,
+            authorization_server: None,
         }
     }
 }
@@ -72,6 +74,7 @@ impl<S, P> CreateOfferRequestBuilder<NoIdentifiers, S, P> {
             grant_types: self.grant_types,
             tx_code: self.tx_code,
             by_ref: self.by_ref,
+            authorization_server: self.authorization_server,
         }
     }
 }
@@ -90,6 +93,7 @@ impl<C, P> CreateOfferRequestBuilder<C, NoSubjectId, P> {
             grant_types: self.grant_types,
             tx_code: self.tx_code,
             by_ref: self.by_ref,
+            authorization_server: self.authorization_server,
         }
     }
 }
@@ -106,6 +110,7 @@ impl<C, S> CreateOfferRequestBuilder<C, S, NoPreAuthorized> {
             grant_types: vec![grant],
             tx_code: self.tx_code,
             by_ref: self.by_ref,
+            authorization_server: self.authorization_server,
         }
     }
 }
@@ -125,6 +130,15 @@ impl<C, S, P> CreateOfferRequestBuilder<C, S, P> {
         self.by_ref = by_ref;
         self
     }
+
+    /// Specify the authorization server the Wallet should use to obtain an
+    /// access token. Must be one of the Credential Issuer's
+    /// `authorization_servers`.
+    #[must_use]
+    pub fn authorization_server(mut self, authorization_server: impl Into<String>) -> Self {
+        self.authorization_server = Some(authorization_server.into());
+        self
+    }
 }
 
 impl<S, P> CreateOfferRequestBuilder<HasIdentifiers, S, P> {
@@ -149,6 +163,7 @@ impl CreateOfferRequestBuilder<HasIdentifiers, SubjectId, PreAuthorized> {
             grant_types: Some(self.grant_types),
             tx_code_required: self.tx_code,
             send_by,
+            authorization_server: self.authorization_server,
         }
     }
 }
@@ -165,6 +180,7 @@ impl<P> CreateOfferRequestBuilder<HasIdentifiers, NoSubjectId, P> {
             grant_types: None,
             tx_code_required: self.tx_code,
             send_by,
+            authorization_server: self.authorization_server,
         };
 
         // only use Authorization Code grant type
@@ -210,6 +226,13 @@ pub struct CreateOfferRequest {
     /// The Issuer can specify whether Credential Offer is sent as an object or
     /// a URI.
     pub send_by: SendBy,
+
+    /// The authorization server the Wallet should use to obtain an access
+    /// token. Must be one of the `authorization_servers` in Credential Issuer
+    /// metadata. Defaults to the first listed server. Pre-authorized code
+    /// grants must use the Credential Issuer's own authorization server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_server: Option<String>,
 }
 
 impl CreateOfferRequest {
@@ -0,0 +1,134 @@
+//! # Issuance Events
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::oauth::GrantType;
+use crate::types::NotificationEvent;
+
+/// An issuance lifecycle event, passed to the registered `IssuanceEvents`
+/// observer by the handler in which it occurred.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct IssuanceEvent {
+    /// When the event occurred.
+    pub occurred_at: DateTime<Utc>,
+
+    /// What occurred.
+    #[serde(flatten)]
+    pub kind: IssuanceEventKind,
+}
+
+impl From<IssuanceEventKind> for IssuanceEvent {
+    fn from(kind: IssuanceEventKind) -> Self {
+        Self {
+            occurred_at: Utc::now(),
+            kind,
+        }
+    }
+}
+
+/// The kinds of issuance lifecycle event.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum IssuanceEventKind {
+    /// A Credential Offer was created.
+    OfferCreated {
+        /// The Holder the offer was made to, when pre-authorized.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        subject_id: Option<String>,
+
+        /// The credentials offered.
+        credential_configuration_ids: Vec<String>,
+
+        /// The grant types included in the offer.
+        grant_types: Vec<GrantType>,
+    },
+
+    /// A Credential Offer was retrieved by reference.
+    OfferFetched {
+        /// The credentials offered.
+        credential_configuration_ids: Vec<String>,
+    },
+
+    /// An authorization code was granted.
+    AuthorizationGranted {
+        /// The Holder granting authorization.
+        subject_id: String,
+
+        /// The Wallet authorization was granted to.
+        client_id: String,
+
+        /// The credentials authorized.
+        credential_configuration_ids: Vec<String>,
+    },
+
+    /// An access token was issued.
+    TokenIssued {
+        /// The Holder the token was issued for.
+        subject_id: String,
+
+        /// The Wallet the token was issued to, unless obtained anonymously.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        client_id: Option<String>,
+
+        /// The grant exchanged for the token.
+        grant_type: GrantType,
+
+        /// The credentials the token authorizes.
+        credential_configuration_ids: Vec<String>,
+    },
+
+    /// A credential was issued.
+    CredentialIssued {
+        /// The Holder the credential was issued to.
+        subject_id: String,
+
+        /// The credential configuration the credential was issued under.
+        credential_configuration_id: String,
+
+        /// The credential's format.
+        format: String,
+
+        /// The Status List the credential's status is recorded in.
+        status_list: String,
+
+        /// The index of the credential's status in the Status List.
+        status_index: usize,
+
+        /// The `notification_id` returned with the credential.
+        notification_id: String,
+    },
+
+    /// Issuance of a credential was deferred.
+    Deferred {
+        /// The Holder the credential is to be issued to.
+        subject_id: String,
+
+        /// The credential configuration the credential is to be issued under.
+        credential_configuration_id: String,
+
+        /// The deferred issuance transaction.
+        transaction_id: String,
+    },
+
+    /// A deferred credential was issued.
+    DeferredCompleted {
+        /// The Holder the credential was issued to.
+        subject_id: String,
+
+        /// The deferred issuance transaction.
+        transaction_id: String,
+    },
+
+    /// The Wallet sent a notification for issued credentials.
+    NotificationReceived {
+        /// The Holder the credentials were issued to.
+        subject_id: String,
+
+        /// The `notification_id` of the issued credentials.
+        notification_id: String,
+
+        /// The event the Wallet notified the Credential Issuer of.
+        event: NotificationEvent,
+    },
+}
@@ -5,6 +5,7 @@ use credibil_vdc::FormatProfile;
 use serde::{Deserialize, Serialize};
 
 use crate::oauth::{OAuthClient, OAuthServer};
+use crate::types::AttackPotentialResistance;
 
 /// Request to retrieve the Credential Issuer's configuration.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -167,6 +168,18 @@ impl IssuerMetadata {
             .ok_or_else(|| anyhow!("Credential Configuration not found"))
     }
 
+    /// Returns the `credential_configuration_id` of the configuration a
+    /// `scope` value requests. When several configurations share the scope,
+    /// the first by `credential_configuration_id` is used.
+    #[must_use]
+    pub fn scope_configuration_id(&self, scope: &str) -> Option<&String> {
+        self.credential_configurations_supported
+            .iter()
+            .filter(|(_, cfg)| cfg.scope.as_deref() == Some(scope))
+            .map(|(id, _)| id)
+            .min()
+    }
+
     /// Convenience method to provide the issuer's display name (if configured).
     ///
     /// TODO: The field is optional and contains locale information but because
@@ -176,6 +189,23 @@ impl IssuerMetadata {
     pub fn display_name(&self, _locale: Option<&str>) -> Option<String> {
         self.display.as_ref().map(|d| d.name.clone())
     }
+
+    /// The `locations` to set on authorization details for the issuer's
+    /// credentials: the Credential Issuer Identifier, when the issuer lists
+    /// `authorization_servers`.
+    #[must_use]
+    pub fn locations(&self) -> Option<Vec<String>> {
+        self.authorization_servers.as_ref().map(|_| vec![self.credential_issuer.clone()])
+    }
+
+    /// Whether authorization detail `locations` identify the issuer. When the
+    /// issuer lists `authorization_servers`, `locations` MUST contain the
+    /// Credential Issuer Identifier.
+    #[must_use]
+    pub fn is_located(&self, locations: Option<&[String]>) -> bool {
+        self.authorization_servers.is_none()
+            || locations.is_some_and(|l| l.contains(&self.credential_issuer))
+    }
 }
 
 /// Contains information about whether the Credential Issuer supports encryption
@@ -333,9 +363,35 @@ pub enum BindingMethod {
     #[serde(rename = "did:key")]
     DidKey,
 
+    /// DID JWK.
+    #[serde(rename = "did:jwk")]
+    DidJwk,
+
     /// Public key JWK.
     #[serde(rename = "jwk")]
     Jwk,
+
+    /// Public key as a COSE Key.
+    #[serde(rename = "cose_key")]
+    CoseKey,
+}
+
+impl BindingMethod {
+    /// The binding method for a key identified by a DID URL, if supported.
+    #[must_use]
+    pub fn from_did(did: &str) -> Option<Self> {
+        if did.starts_with("did:web:") {
+            Some(Self::DidWeb)
+        } else if did.starts_with("did:webvh:") {
+            Some(Self::DidWebvh)
+        } else if did.starts_with("did:key:") {
+            Some(Self::DidKey)
+        } else if did.starts_with("did:jwk:") {
+            Some(Self::DidJwk)
+        } else {
+            None
+        }
+    }
 }
 
 impl CredentialConfiguration {
@@ -455,6 +511,62 @@ pub struct ProofTypesSupported {
     /// "proof_signing_alg_values_supported": ["ES256K", "EdDSA"]
     /// ```
     pub proof_signing_alg_values_supported: Vec<String>,
+
+    /// Indicates that key attestations are required for this proof type, and
+    /// the minimum attack potential resistance the attested keys must meet.
+    /// When not present, key attestations are not required.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_attestations_required: Option<KeyAttestationsRequired>,
+}
+
+/// The attack potential resistance required of the key storage and user
+/// authentication of attested keys.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct KeyAttestationsRequired {
+    /// Accepted values for the `key_storage` of a key attestation. When
+    /// present, the key attestation must assert at least one of these values.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_storage: Option<Vec<AttackPotentialResistance>>,
+
+    /// Accepted values for the `user_authentication` of a key attestation.
+    /// When present, the key attestation must assert at least one of these
+    /// values.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_authentication: Option<Vec<AttackPotentialResistance>>,
+}
+
+impl KeyAttestationsRequired {
+    /// Check the attack potential resistance asserted by a key attestation
+    /// meets the requirements.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key attestation does not assert an accepted
+    /// `key_storage` or `user_authentication` value.
+    pub fn check(
+        &self, key_storage: Option<&[AttackPotentialResistance]>,
+        user_authentication: Option<&[AttackPotentialResistance]>,
+    ) -> Result<()> {
+        if !accepted(self.key_storage.as_deref(), key_storage) {
+            return Err(anyhow!("key storage does not meet the required attack resistance"));
+        }
+        if !accepted(self.user_authentication.as_deref(), user_authentication) {
+            return Err(anyhow!(
+                "user authentication does not meet the required attack resistance"
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Whether any `asserted` value is one of the `required` values.
+fn accepted(
+    required: Option<&[AttackPotentialResistance]>, asserted: Option<&[AttackPotentialResistance]>,
+) -> bool {
+    let Some(required) = required else {
+        return true;
+    };
+    asserted.unwrap_or_default().iter().any(|a| required.contains(a))
 }
 
 /// `CredentialDisplay` holds language-based display properties of the supported
@@ -538,7 +650,7 @@ pub struct ServerMetadata {
 
     /// Indicates whether the issuer accepts a Token Request with a
     /// Pre-Authorized Code but without a client id. Defaults to false.
-    #[serde(rename = "pre-authorized_grant_anonymous_access_supported")]
+    #[serde(rename = "pre-authorized_grant_anonymous_access_supported", default)]
     pub pre_authorized_grant_anonymous_access_supported: bool,
 }
 
@@ -1,10 +1,14 @@
 use std::fmt::Debug;
 
 use anyhow::Result;
+use chrono::serde::{ts_seconds, ts_seconds_option};
+use chrono::{DateTime, Utc};
 use credibil_core::html;
 use serde::{Deserialize, Serialize};
 
+use crate::oauth::GrantType;
 use crate::types::{AuthorizationDefinition, AuthorizationDetail, ClientAssertion};
+use crate::{dpop, generate};
 
 impl TokenRequest {
     /// Create a new `TokenRequestBuilder`.
@@ -79,6 +83,16 @@ impl<G> TokenRequestBuilder<G> {
         self.authorization_details.get_or_insert_with(Vec::new).push(authorization_detail);
         self
     }
+
+    /// Specify a signed JWT client assertion used to authenticate the client
+    /// (`private_key_jwt` or `client_secret_jwt`).
+    #[must_use]
+    pub fn client_assertion(mut self, client_assertion: impl Into<String>) -> Self {
+        self.client_assertion = Some(ClientAssertion::JwtBearer {
+            client_assertion: client_assertion.into(),
+        });
+        self
+    }
 }
 
 impl TokenRequestBuilder<Grant> {
@@ -196,6 +210,14 @@ pub enum TokenGrantType {
         #[serde(skip_serializing_if = "Option::is_none")]
         tx_code: Option<String>,
     },
+
+    /// Attributes required for the Refresh Token grant type.
+    #[serde(rename = "refresh_token")]
+    RefreshToken {
+        /// The refresh token issued to the Wallet in a previous Token
+        /// Response.
+        refresh_token: String,
+    },
 }
 
 impl Default for TokenGrantType {
@@ -208,6 +230,16 @@ impl Default for TokenGrantType {
     }
 }
 
+impl From<&TokenGrantType> for GrantType {
+    fn from(grant_type: &TokenGrantType) -> Self {
+        match grant_type {
+            TokenGrantType::AuthorizationCode { .. } => Self::AuthorizationCode,
+            TokenGrantType::PreAuthorizedCode { .. } => Self::PreAuthorizedCode,
+            TokenGrantType::RefreshToken { .. } => Self::RefreshToken,
+        }
+    }
+}
+
 /// Token Response as defined in [RFC6749].
 ///
 /// [RFC6749]: (https://www.rfc-editor.org/rfc/rfc6749.html)
@@ -217,12 +249,19 @@ pub struct TokenResponse {
     /// or more Credentials.
     pub access_token: String,
 
-    /// The type of the token issued. Must be "`Bearer`".
+    /// The type of the token issued: "`DPoP`" when the token is bound to a
+    /// DPoP key, otherwise "`Bearer`".
     pub token_type: TokenType,
 
     /// The lifetime in seconds of the access token.
     pub expires_in: i64,
 
+    /// A single-use token the Wallet can use to obtain a new access token for
+    /// the same authorized credentials. Issued when the authorization server
+    /// supports the `refresh_token` grant type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+
     /// REQUIRED when `authorization_details` parameter is used to request
     /// issuance of a certain Credential type. MUST NOT be used otherwise.
     ///
@@ -230,15 +269,26 @@ pub struct TokenResponse {
     /// populated for use in subsequent Credential Requests.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub authorization_details: Option<Vec<AuthorizedDetail>>,
+
+    /// The `scope` values the Wallet was authorized to request credentials
+    /// with. Credentials authorized using `scope` are requested by their
+    /// `credential_configuration_id`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
 }
 
-/// Access token type as defined in [RFC6749]. Per the specification, the only
-/// value allowed is "`Bearer`".
+/// Access token type as defined in [RFC6749] and [RFC9449].
+///
+/// [RFC9449]: (https://www.rfc-editor.org/rfc/rfc9449.html)
 #[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
 pub enum TokenType {
-    /// The only valid value is "`Bearer`".
+    /// A bearer token.
     #[default]
     Bearer,
+
+    /// A token bound to the key used to sign the DPoP proof presented to the
+    /// token endpoint.
+    DPoP,
 }
 
 /// Authorization Details object specifically for use in successful Access Token
@@ -284,6 +334,226 @@ impl AuthorizedDetail {
     }
 }
 
+/// Claims of a self-contained JWT access token (`at+jwt`) as defined in
+/// [RFC9068].
+///
+/// [RFC9068]: (https://www.rfc-editor.org/rfc/rfc9068.html)
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct AccessTokenClaims {
+    /// The issuer identifier of the authorization server.
+    pub iss: String,
+
+    /// The time at which the token expires.
+    #[serde(with = "ts_seconds")]
+    pub exp: DateTime<Utc>,
+
+    /// The Credential Issuer the token is intended for.
+    pub aud: String,
+
+    /// Identifies the Holder the token was issued for.
+    pub sub: String,
+
+    /// The `client_id` of the Client the token was issued to. Not set when
+    /// the token was obtained using anonymous pre-authorized access.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+
+    /// The time at which the token was issued.
+    #[serde(with = "ts_seconds")]
+    pub iat: DateTime<Utc>,
+
+    /// A unique identifier for the token.
+    pub jti: String,
+
+    /// The authorized credentials, including credential identifiers.
+    pub authorization_details: Vec<AuthorizedDetail>,
+
+    /// The `scope` values of credentials authorized using `scope`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+
+    /// Binds the token to a DPoP key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cnf: Option<TokenConfirmation>,
+
+    /// Identifies the authorization grant the token was issued under. A
+    /// private claim that third-party authorization servers do not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grant_id: Option<String>,
+}
+
+/// Confirmation (`cnf`) claim binding an access token to the key used to sign
+/// DPoP proofs.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TokenConfirmation {
+    /// The JWK SHA-256 thumbprint of the DPoP key.
+    pub jkt: String,
+}
+
+/// Token Introspection Request as defined in [RFC7662]. Used by resource
+/// servers, such as a separately deployed Credential Issuer, to determine the
+/// state of an access or refresh token.
+///
+/// [RFC7662]: (https://www.rfc-editor.org/rfc/rfc7662.html)
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct IntrospectionRequest {
+    /// The token to introspect.
+    pub token: String,
+
+    /// A hint about the type of the token submitted for introspection.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type_hint: Option<TokenTypeHint>,
+
+    /// The `client_id` of the client making the request.
+    pub client_id: String,
+
+    /// Client identity assertion used to authenticate the client.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(flatten)]
+    pub client_assertion: Option<ClientAssertion>,
+}
+
+/// Token Introspection Response as defined in [RFC7662].
+///
+/// Only `active` is returned for tokens that are unknown, expired, or have
+/// been revoked.
+///
+/// [RFC7662]: (https://www.rfc-editor.org/rfc/rfc7662.html)
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct IntrospectionResponse {
+    /// Whether the token is currently active.
+    pub active: bool,
+
+    /// The `client_id` of the Client the token was issued to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+
+    /// Identifies the Holder the token was issued for.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sub: Option<String>,
+
+    /// The issuer identifier of the authorization server.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+
+    /// The time at which the token expires.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(with = "ts_seconds_option", default)]
+    pub exp: Option<DateTime<Utc>>,
+
+    /// The type of the token: "`Bearer`" or "`DPoP`" for access tokens. Not
+    /// set for refresh tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type: Option<TokenType>,
+
+    /// The credentials the token authorizes, including credential
+    /// identifiers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorization_details: Option<Vec<AuthorizedDetail>>,
+
+    /// The `scope` values of credentials authorized using `scope`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+
+    /// The DPoP key the token is bound to.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cnf: Option<TokenConfirmation>,
+}
+
+/// Token Revocation Request as defined in [RFC7009].
+///
+/// [RFC7009]: (https://www.rfc-editor.org/rfc/rfc7009.html)
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RevocationRequest {
+    /// The token to revoke.
+    pub token: String,
+
+    /// A hint about the type of the token submitted for revocation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_type_hint: Option<TokenTypeHint>,
+
+    /// The `client_id` of the client making the request.
+    pub client_id: String,
+
+    /// Client identity assertion used to authenticate the client.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(flatten)]
+    pub client_assertion: Option<ClientAssertion>,
+}
+
+/// Token Revocation Response. The authorization server responds with HTTP
+/// status code 200 whether or not the token was valid.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RevocationResponse;
+
+/// Hint about the type of a token submitted for introspection or revocation.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenTypeHint {
+    /// An access token.
+    AccessToken,
+
+    /// A refresh token.
+    RefreshToken,
+}
+
+/// Claims for a DPoP proof JWT used to demonstrate possession of the key an
+/// access token is, or will be, bound to. See [RFC9449].
+///
+/// [RFC9449]: (https://www.rfc-editor.org/rfc/rfc9449.html)
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DPoPClaims {
+    /// A unique identifier for the proof, used to detect replay.
+    pub jti: String,
+
+    /// The HTTP method of the request the proof is attached to.
+    pub htm: String,
+
+    /// The HTTP URI of the request, without query and fragment parts.
+    pub htu: String,
+
+    /// The time at which the proof was created.
+    #[serde(with = "ts_seconds")]
+    pub iat: DateTime<Utc>,
+
+    /// The base64url-encoded SHA-256 hash of the access token the proof is
+    /// presented with. Required when calling protected endpoints.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ath: Option<String>,
+
+    /// A server-provided nonce.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<String>,
+}
+
+impl DPoPClaims {
+    /// Create a new `DPoPClaims` instance for a `POST` request to `htu`.
+    #[must_use]
+    pub fn new(htu: impl Into<String>) -> Self {
+        Self {
+            jti: generate::nonce(),
+            htm: "POST".to_string(),
+            htu: htu.into(),
+            iat: Utc::now(),
+            ..Self::default()
+        }
+    }
+
+    /// Bind the proof to the access token it will be presented with.
+    #[must_use]
+    pub fn access_token(mut self, access_token: &str) -> Self {
+        self.ath = Some(dpop::access_token_hash(access_token));
+        self
+    }
+
+    /// Set the server-provided nonce.
+    #[must_use]
+    pub fn nonce(mut self, nonce: impl Into<String>) -> Self {
+        self.nonce = Some(nonce.into());
+        self
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
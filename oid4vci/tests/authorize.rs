@@ -1 +1,103 @@
 //! Authorization Code Flow Tests
+
+mod common;
+
+use credibil_oid4vci::types::{
+    AuthorizationRequest, CredentialRequest, PushedAuthorizationRequest, RequestObject, RequestUri,
+    TokenGrantType, TokenRequest,
+};
+use credibil_oid4vci::{CredentialHeaders, pkce};
+use serde_json::json;
+use test_utils::issuer::Issuer;
+use test_utils::wallet::Wallet;
+use tokio::sync::OnceCell;
+
+const ISSUER: &str = "http://localhost:8080";
+const CLIENT_ID: &str = "http://localhost:8082";
+const REDIRECT_URI: &str = "http://localhost:3000/callback";
+const BOB_SUBJECT: &str = "normal_user";
+
+static BOB: OnceCell<Wallet> = OnceCell::const_new();
+async fn bob() -> &'static Wallet {
+    BOB.get_or_init(|| async { Wallet::new("https://authorize.io/bob").await }).await
+}
+
+// Should authorize credentials requested using `scope` in a pushed
+// authorization request, returning the granted `scope` in the token response
+// and issuing credentials requested by `credential_configuration_id`.
+#[tokio::test]
+async fn scope() {
+    let provider = Issuer::new(ISSUER).await;
+    let bob = bob().await;
+
+    // --------------------------------------------------
+    // Bob's wallet pushes an authorization request for a scope
+    // --------------------------------------------------
+    let verifier = pkce::code_verifier();
+    let AuthorizationRequest::Object(request_object) = AuthorizationRequest::builder()
+        .client_id(CLIENT_ID)
+        .redirect_uri(REDIRECT_URI)
+        .code_challenge(pkce::code_challenge(&verifier))
+        .scope("EmployeeID")
+        .subject_id(BOB_SUBJECT)
+        .build()
+    else {
+        panic!("should be a request object");
+    };
+
+    // scope values that do not request credentials are ignored
+    let request = PushedAuthorizationRequest {
+        request: RequestObject {
+            scope: Some("openid EmployeeID offline_access".to_string()),
+            ..request_object
+        },
+        client_assertion: None,
+    };
+    let par = credibil_oid4vci::handle(ISSUER, request, &provider)
+        .await
+        .expect("should accept pushed request");
+
+    // --------------------------------------------------
+    // Bob authorizes issuance and the wallet requests a token
+    // --------------------------------------------------
+    let request = AuthorizationRequest::Uri(RequestUri {
+        request_uri: par.request_uri.clone(),
+    });
+    let authorization =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should authorize");
+
+    let request = TokenRequest::builder()
+        .client_id(CLIENT_ID)
+        .grant_type(TokenGrantType::AuthorizationCode {
+            code: authorization.code.clone(),
+            redirect_uri: Some(REDIRECT_URI.to_string()),
+            code_verifier: Some(verifier),
+        })
+        .build();
+    let token =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+
+    assert_eq!(token.scope.as_deref(), Some("EmployeeID"));
+    assert!(token.authorization_details.is_none());
+
+    // --------------------------------------------------
+    // Bob requests the credential authorized by the scope
+    // --------------------------------------------------
+    let request = CredentialRequest::builder()
+        .credential_configuration_id("EmployeeID_W3C_VC")
+        .with_proof(common::proof(ISSUER, &provider, bob).await)
+        .build();
+    let request = credibil_oid4vci::Request {
+        body: request,
+        headers: CredentialHeaders {
+            authorization: token.access_token.clone(),
+            dpop: None,
+        },
+    };
+    let response = credibil_oid4vci::handle(ISSUER, request, &provider)
+        .await
+        .expect("should return credential");
+
+    let (_, claims) = common::w3c_credential(&provider, &response).await;
+    assert_eq!(claims.get("family_name"), Some(&json!("Person")));
+}
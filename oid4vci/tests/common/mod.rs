@@ -0,0 +1,55 @@
+//! Helpers shared by the issuance tests.
+
+use credibil_oid4vci::identity::Signature;
+use credibil_oid4vci::jose::{JwsBuilder, Jwt, decode_jws};
+use credibil_oid4vci::proof::W3cVcClaims;
+use credibil_oid4vci::types::{Credential, CredentialResponse, NonceRequest, ProofClaims};
+use credibil_oid4vci::{JwtType, OneMany};
+use credibil_proof::resolve_jwk;
+use serde_json::{Map, Value};
+use test_utils::issuer::Issuer;
+use test_utils::wallet::Wallet;
+
+// The `credential_issuer` of every test issuer's metadata.
+const CREDENTIAL_ISSUER: &str = "http://localhost:8080";
+
+// Sign a proof of possession of the wallet's key using a fresh `c_nonce` from
+// the issuer's Nonce Endpoint.
+pub async fn proof(owner: &str, provider: &Issuer, wallet: &Wallet) -> String {
+    let nonce =
+        credibil_oid4vci::handle(owner, NonceRequest, provider).await.expect("should return nonce");
+    let key = wallet
+        .verification_method()
+        .await
+        .expect("should have key")
+        .try_into()
+        .expect("should map key to key binding");
+    let jws = JwsBuilder::new()
+        .typ(JwtType::ProofJwt)
+        .payload(ProofClaims::new().credential_issuer(CREDENTIAL_ISSUER).nonce(&nonce.c_nonce))
+        .key_ref(&key)
+        .add_signer(wallet)
+        .build()
+        .await
+        .expect("builds JWS");
+    jws.encode().expect("encodes JWS")
+}
+
+// Decode the single `jwt_vc_json` credential in the response, returning the
+// encoded credential and the claims of its subject.
+pub async fn w3c_credential(
+    provider: &Issuer, response: &CredentialResponse,
+) -> (String, Map<String, Value>) {
+    let CredentialResponse::Credentials { credentials, .. } = response else {
+        panic!("expected single credential");
+    };
+    let Credential { credential } = credentials.first().expect("should have credential");
+    let credential = credential.as_str().expect("should be a string");
+
+    let resolver = async |kid: String| resolve_jwk(&kid, provider).await;
+    let jwt: Jwt<W3cVcClaims> = decode_jws(credential, resolver).await.expect("should decode");
+    let OneMany::One(subject) = jwt.claims.vc.credential_subject else {
+        panic!("should be a single credential subject");
+    };
+    (credential.to_string(), subject.claims)
+}
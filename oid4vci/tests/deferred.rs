@@ -1,16 +1,18 @@
 //! Deferred Issuance Tests
 
+mod common;
+
 use std::collections::HashMap;
 
 use credibil_oid4vci::datastore::Datastore;
 use credibil_oid4vci::identity::{Signature, VerifyBy};
-use credibil_oid4vci::jose::{JwsBuilder, Jwt, decode_jws};
+use credibil_oid4vci::jose::{JwsBuilder, Jwt, KeyBinding, decode_jws};
 use credibil_oid4vci::proof::W3cVcClaims;
 use credibil_oid4vci::types::{
-    CreateOfferRequest, Credential, CredentialRequest, CredentialResponse, Dataset,
-    DeferredCredentialRequest, NonceRequest, ProofClaims, TokenGrantType, TokenRequest,
+    AccessTokenClaims, CreateOfferRequest, Credential, CredentialRequest, CredentialResponse,
+    Dataset, DeferredCredentialRequest, NonceRequest, ProofClaims, TokenGrantType, TokenRequest,
 };
-use credibil_oid4vci::{CredentialHeaders, DeferredHeaders, JwtType, OneMany};
+use credibil_oid4vci::{CredentialHeaders, DeferredHeaders, Error, JwtType, OneMany};
 use credibil_proof::resolve_jwk;
 use serde_json::json;
 use test_utils::issuer::Issuer;
@@ -95,6 +97,7 @@ async fn deferred() {
         body: request,
         headers: CredentialHeaders {
             authorization: token.access_token.clone(),
+            dpop: None,
         },
     };
 
@@ -102,36 +105,83 @@ async fn deferred() {
         .await
         .expect("should return credential");
 
+    let CredentialResponse::TransactionId {
+        transaction_id,
+        interval,
+    } = &*response
+    else {
+        panic!("expected transaction_id");
+    };
+    assert_eq!(*interval, 5);
+
     // --------------------------------------------------
-    // Alice approves issuance of the credential
-    // HACK: update subject's pending state
+    // Bob retries before issuance is approved
     // --------------------------------------------------
-    let credential_identifier = &details[0].credential_identifiers[0];
+    let request = credibil_oid4vci::Request {
+        body: DeferredCredentialRequest {
+            transaction_id: transaction_id.clone(),
+        },
+        headers: DeferredHeaders {
+            authorization: token.access_token.clone(),
+            dpop: None,
+        },
+    };
+    let Err(Error::IssuancePending(5)) = credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should be pending");
+    };
 
-    let data = Datastore::get(&provider, ISSUER, "SUBJECT", CAROL_SUBJECT).await.unwrap().unwrap();
-    let mut subject: HashMap<String, Dataset> = serde_json::from_slice(&data).unwrap();
+    // --------------------------------------------------
+    // Another subject's token cannot redeem the transaction
+    // --------------------------------------------------
+    let request = CreateOfferRequest::builder()
+        .subject_id("normal_user")
+        .with_credential("EmployeeID_W3C_VC")
+        .build();
+    let response =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should create offer");
+    let offer = response.offer_type.as_object().expect("should have offer").clone();
+    let grants = offer.grants.expect("should have grant");
+    let grant = grants.pre_authorized_code.expect("should have pre-authorized code grant");
+    let request = TokenRequest::builder()
+        .grant_type(TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: grant.pre_authorized_code,
+            tx_code: response.tx_code.clone(),
+        })
+        .build();
+    let other =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
 
-    let mut credential: Dataset = subject.get(credential_identifier).unwrap().clone();
-    credential.pending = false;
-    subject.insert(credential_identifier.to_string(), credential);
+    let request = credibil_oid4vci::Request {
+        body: DeferredCredentialRequest {
+            transaction_id: transaction_id.clone(),
+        },
+        headers: DeferredHeaders {
+            authorization: other.access_token.clone(),
+            dpop: None,
+        },
+    };
+    let Err(Error::InvalidTransactionId(_)) =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject another subject's token");
+    };
 
-    let data = serde_json::to_vec(&subject).unwrap();
-    Datastore::delete(&provider, ISSUER, "SUBJECT", CAROL_SUBJECT).await.unwrap();
-    Datastore::put(&provider, ISSUER, "SUBJECT", CAROL_SUBJECT, &data).await.unwrap();
+    // --------------------------------------------------
+    // Alice approves issuance of the credential
+    // --------------------------------------------------
+    approve(&provider, ISSUER, &details[0].credential_identifiers[0]).await;
 
     // --------------------------------------------------
     // After a brief wait Bob retrieves the credential
     // --------------------------------------------------
-    let CredentialResponse::TransactionId { transaction_id } = &*response else {
-        panic!("expected transaction_id");
-    };
-
     let request = credibil_oid4vci::Request {
         body: DeferredCredentialRequest {
             transaction_id: transaction_id.clone(),
         },
         headers: DeferredHeaders {
             authorization: token.access_token.clone(),
+            dpop: None,
         },
     };
     let response = credibil_oid4vci::handle(ISSUER, request, &provider)
@@ -167,3 +217,122 @@ async fn deferred() {
     assert_eq!(subject.id, Some(carol_did.to_string()));
     assert_eq!(subject.claims.get("family_name"), Some(&json!("Person")));
 }
+
+// Should issue a deferred credential requested by `credential_configuration_id`
+// using an access token that does not identify the subject's datasets.
+#[tokio::test]
+async fn configuration_id() {
+    // a separate issuer so approving issuance does not affect other tests
+    const OWNER: &str = "http://localhost:8095";
+
+    let provider = Issuer::new(OWNER).await;
+    provider.use_jwt_access_tokens(OWNER).await.expect("should update metadata");
+    let carol = carol().await;
+
+    let request = CreateOfferRequest::builder()
+        .subject_id(CAROL_SUBJECT)
+        .with_credential("EmployeeID_W3C_VC")
+        .build();
+    let response =
+        credibil_oid4vci::handle(OWNER, request, &provider).await.expect("should create offer");
+    let offer = response.offer_type.as_object().expect("should have offer").clone();
+    let grants = offer.grants.expect("should have grant");
+    let pre_auth_grant = grants.pre_authorized_code.expect("should have pre-authorized code grant");
+
+    let request = TokenRequest::builder()
+        .grant_type(TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: pre_auth_grant.pre_authorized_code,
+            tx_code: response.tx_code.clone(),
+        })
+        .build();
+    let token =
+        credibil_oid4vci::handle(OWNER, request, &provider).await.expect("should return token");
+    let details = token.authorization_details.as_ref().expect("should have authorization details");
+    let credential_identifier = details[0].credential_identifiers[0].clone();
+
+    // --------------------------------------------------
+    // An authorization server issues a token without `credential_identifiers`
+    // --------------------------------------------------
+    let resolver = async |kid: String| resolve_jwk(&kid, &provider).await;
+    let jwt: Jwt<AccessTokenClaims> =
+        decode_jws(&token.access_token, resolver).await.expect("should decode");
+    let mut claims = AccessTokenClaims {
+        jti: "unidentified-token".to_string(),
+        ..jwt.claims
+    };
+    for detail in &mut claims.authorization_details {
+        detail.credential_identifiers.clear();
+    }
+
+    let key: KeyBinding = provider
+        .verification_method()
+        .await
+        .expect("should have key")
+        .try_into()
+        .expect("should map key to key binding");
+    let access_token = JwsBuilder::new()
+        .typ(JwtType::AccessToken)
+        .payload(claims)
+        .key_ref(&key)
+        .add_signer(&provider)
+        .build()
+        .await
+        .expect("builds JWS")
+        .encode()
+        .expect("should encode JWS");
+
+    // --------------------------------------------------
+    // Carol requests the credential by its configuration and is deferred
+    // --------------------------------------------------
+    let request = credibil_oid4vci::Request {
+        body: CredentialRequest::builder()
+            .credential_configuration_id("EmployeeID_W3C_VC")
+            .with_proof(common::proof(OWNER, &provider, carol).await)
+            .build(),
+        headers: CredentialHeaders {
+            authorization: access_token.clone(),
+            dpop: None,
+        },
+    };
+    let response = credibil_oid4vci::handle(OWNER, request, &provider)
+        .await
+        .expect("should return transaction id");
+    let CredentialResponse::TransactionId { transaction_id, .. } = &*response else {
+        panic!("expected transaction_id");
+    };
+
+    // --------------------------------------------------
+    // Once approved, the same token retrieves the credential
+    // --------------------------------------------------
+    approve(&provider, OWNER, &credential_identifier).await;
+
+    let request = credibil_oid4vci::Request {
+        body: DeferredCredentialRequest {
+            transaction_id: transaction_id.clone(),
+        },
+        headers: DeferredHeaders {
+            authorization: access_token,
+            dpop: None,
+        },
+    };
+    let response = credibil_oid4vci::handle(OWNER, request, &provider)
+        .await
+        .expect("should return credential");
+    let (_, claims) = common::w3c_credential(&provider, &response).await;
+    assert_eq!(claims.get("family_name"), Some(&json!("Person")));
+}
+
+// Mark the subject's dataset as ready to issue.
+// HACK: update subject's pending state
+async fn approve(provider: &Issuer, owner: &str, credential_identifier: &str) {
+    let data = Datastore::get(provider, owner, "SUBJECT", CAROL_SUBJECT).await.unwrap().unwrap();
+    let mut subject: HashMap<String, Dataset> = serde_json::from_slice(&data).unwrap();
+
+    let mut credential: Dataset = subject.get(credential_identifier).unwrap().clone();
+    credential.pending = false;
+    subject.insert(credential_identifier.to_string(), credential);
+
+    let data = serde_json::to_vec(&subject).unwrap();
+    Datastore::delete(provider, owner, "SUBJECT", CAROL_SUBJECT).await.unwrap();
+    Datastore::put(provider, owner, "SUBJECT", CAROL_SUBJECT, &data).await.unwrap();
+}
@@ -2,17 +2,31 @@
 
 //! Pre-Authorized Code Flow Tests
 
+mod common;
+
+use std::time::Duration;
+
 use base64ct::{Base64UrlUnpadded, Encoding};
-use credibil_jose::{JwsBuilder, Jwt, decode_jws};
+use chrono::{TimeDelta, Utc};
+use credibil_core::Kind;
+use credibil_jose::{JwsBuilder, Jwt, KeyBinding, decode_jws, encode_jws};
 use credibil_oid4vci::identity::{Signature, VerifyBy};
 use credibil_oid4vci::proof::W3cVcClaims;
+use credibil_oid4vci::status::{
+    CredentialSelector, RevokeRequest, StatusListAggregationRequest, StatusListClaims,
+    StatusListRequest, StatusListResponse,
+};
 use credibil_oid4vci::types::{
-    CreateOfferRequest, Credential, CredentialRequest, CredentialResponse, NonceRequest,
-    ProofClaims, TokenGrantType, TokenRequest,
+    AccessTokenClaims, AttackPotentialResistance, AttestationClaims, CreateOfferRequest,
+    Credential, CredentialRequest, CredentialResponse, DPoPClaims, NonceRequest, ProofClaims,
+    RevocationRequest, TokenGrantType, TokenRequest, TokenType,
 };
 use credibil_oid4vci::vdc::sd_jwt::SdJwtClaims;
-use credibil_oid4vci::{CredentialHeaders, JwtType, OneMany};
+use credibil_oid4vci::vdc::w3c_vc::{self, VerifiablePresentation, W3cVpClaims};
+use credibil_oid4vci::{CredentialHeaders, DpopNonce, Error, JwtType, OneMany, TokenHeaders};
 use credibil_proof::resolve_jwk;
+use http::StatusCode;
+use http::header::WWW_AUTHENTICATE;
 use serde_json::json;
 use sha2::{Digest, Sha256};
 use test_utils::issuer::Issuer;
@@ -21,6 +35,8 @@ use tokio::sync::OnceCell;
 
 const ISSUER: &str = "http://localhost:8080";
 const BOB_SUBJECT: &str = "normal_user";
+const VERIFIER: &str = "https://verifier.io";
+const WALLET_ID: &str = "http://localhost:8082";
 
 static BOB: OnceCell<Wallet> = OnceCell::const_new();
 async fn bob() -> &'static Wallet {
@@ -114,6 +130,7 @@ async fn two_proofs() {
         body: request,
         headers: CredentialHeaders {
             authorization: token.access_token.clone(),
+            dpop: None,
         },
     };
 
@@ -230,6 +247,7 @@ async fn sd_jwt() {
         body: request,
         headers: CredentialHeaders {
             authorization: token.access_token.clone(),
+            dpop: None,
         },
     };
 
@@ -240,7 +258,11 @@ async fn sd_jwt() {
     // --------------------------------------------------
     // Bob extracts and verifies the received credentials
     // --------------------------------------------------
-    let CredentialResponse::Credentials { credentials, .. } = &*response else {
+    let CredentialResponse::Credentials {
+        credentials,
+        notification_id,
+    } = &*response
+    else {
         panic!("expected single credential");
     };
 
@@ -272,4 +294,989 @@ async fn sd_jwt() {
         let sd_hash = Base64UrlUnpadded::encode_string(Sha256::digest(d).as_slice());
         assert!(jwt.claims.sd.contains(&sd_hash), "disclosure not found");
     }
+
+    // --------------------------------------------------
+    // Alice revokes Bob's credential
+    // --------------------------------------------------
+    let issued_at = Utc::now();
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let notification_id = notification_id.clone().expect("should have notification id");
+    let request = RevokeRequest {
+        credentials: CredentialSelector::NotificationId(notification_id),
+    };
+    let response =
+        credibil_oid4vci::status::handle(ISSUER, request, &provider).await.expect("should revoke");
+
+    let status = jwt.claims.status.expect("should have status");
+    assert_eq!(response.updated, vec![status.status_list.clone()]);
+
+    let request = StatusListRequest {
+        id: Some(status.status_list.uri.clone()),
+        time: None,
+    };
+    let response = credibil_oid4vci::status::handle(ISSUER, request, &provider)
+        .await
+        .expect("should return token");
+    let StatusListResponse::Jwt(token) = &*response else {
+        panic!("should be a JWT");
+    };
+    let resolver = async |kid: String| resolve_jwk(&kid, &provider).await;
+    let token: Jwt<StatusListClaims> = decode_jws(token, resolver).await.expect("should decode");
+
+    assert!(!token.claims.status_list.is_valid(status.status_list.idx).expect("should check"));
+
+    // --------------------------------------------------
+    // The credential was valid at the time it was issued
+    // --------------------------------------------------
+    let request = StatusListRequest {
+        id: Some(status.status_list.uri.clone()),
+        time: Some(issued_at),
+    };
+    let response = credibil_oid4vci::status::handle(ISSUER, request, &provider)
+        .await
+        .expect("should return token");
+    let StatusListResponse::Jwt(token) = &*response else {
+        panic!("should be a JWT");
+    };
+    let resolver = async |kid: String| resolve_jwk(&kid, &provider).await;
+    let token: Jwt<StatusListClaims> = decode_jws(token, resolver).await.expect("should decode");
+
+    assert!(token.claims.status_list.is_valid(status.status_list.idx).expect("should check"));
+
+    // --------------------------------------------------
+    // The Status List Token can be discovered using the aggregation endpoint
+    // --------------------------------------------------
+    let aggregation_uri = format!("{ISSUER}/statuslists");
+    assert_eq!(token.claims.status_list.aggregation_uri, Some(aggregation_uri));
+
+    let response =
+        credibil_oid4vci::status::handle(ISSUER, StatusListAggregationRequest, &provider)
+            .await
+            .expect("should return aggregation");
+    assert!(response.status_lists.contains(&status.status_list.uri));
+}
+
+// Should issue a W3C credential referencing the issuer's Bitstring Status List
+// Credentials, and a verifier should reject it once revoked.
+#[tokio::test]
+async fn w3c_status() {
+    let provider = Issuer::new(ISSUER).await;
+    let bob = bob().await;
+
+    // --------------------------------------------------
+    // Bob is issued a `jwt_vc_json` credential
+    // --------------------------------------------------
+    let request = CreateOfferRequest::builder()
+        .subject_id(BOB_SUBJECT)
+        .with_credential("EmployeeID_W3C_VC")
+        .build();
+    let response =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should create offer");
+    let offer = response.offer_type.as_object().expect("should have offer").clone();
+    let grants = offer.grants.expect("should have grant");
+    let pre_auth_grant = grants.pre_authorized_code.expect("should have pre-authorized code grant");
+
+    let request = TokenRequest::builder()
+        .grant_type(TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: pre_auth_grant.pre_authorized_code,
+            tx_code: response.tx_code.clone(),
+        })
+        .build();
+    let token =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+
+    let details = &token.authorization_details.as_ref().expect("should have authorization details");
+    let request = CredentialRequest::builder()
+        .credential_identifier(&details[0].credential_identifiers[0])
+        .with_proof(common::proof(ISSUER, &provider, bob).await)
+        .build();
+    let request = credibil_oid4vci::Request {
+        body: request,
+        headers: CredentialHeaders {
+            authorization: token.access_token.clone(),
+            dpop: None,
+        },
+    };
+    let response = credibil_oid4vci::handle(ISSUER, request, &provider)
+        .await
+        .expect("should return credential");
+    let CredentialResponse::Credentials {
+        credentials,
+        notification_id,
+    } = &*response
+    else {
+        panic!("expected single credential");
+    };
+    let Credential { credential } = credentials.first().expect("should have credential");
+    let credential = credential.as_str().expect("should be a string");
+
+    let resolver = async |kid: String| resolve_jwk(&kid, &provider).await;
+    let jwt: Jwt<W3cVcClaims> = decode_jws(credential, resolver).await.expect("should decode");
+    let statuses = jwt.claims.vc.credential_status.expect("should have status").to_vec();
+    assert_eq!(statuses.len(), 2);
+
+    // --------------------------------------------------
+    // A verifier accepts Bob's presentation of the credential
+    // --------------------------------------------------
+    let vp = presentation(bob, credential).await;
+    w3c_vc::verify_vp(&vp, "nonce", VERIFIER, &provider).await.expect("should verify");
+
+    // --------------------------------------------------
+    // Alice revokes the credential and the verifier rejects it
+    // --------------------------------------------------
+    let request = RevokeRequest {
+        credentials: CredentialSelector::NotificationId(
+            notification_id.clone().expect("should have notification id"),
+        ),
+    };
+    credibil_oid4vci::status::handle(ISSUER, request, &provider).await.expect("should revoke");
+
+    let vp = presentation(bob, credential).await;
+    let Err(e) = w3c_vc::verify_vp(&vp, "nonce", VERIFIER, &provider).await else {
+        panic!("should reject revoked credential");
+    };
+    assert!(e.to_string().contains("revoked"));
+}
+
+// Should reference Status Lists published under the configured base URI.
+#[tokio::test]
+async fn status_list_uri() {
+    // a separate issuer so other tests continue to use the default base URI
+    const CONFIGURED_ISSUER: &str = "http://localhost:8093";
+    const STATUS_LIST_URI: &str = "https://status.io/lists";
+
+    let provider = Issuer::new(CONFIGURED_ISSUER).await;
+    provider
+        .configure(CONFIGURED_ISSUER, "status_list_uri", json!(STATUS_LIST_URI))
+        .await
+        .expect("should update configuration");
+    let bob = bob().await;
+
+    let request = CreateOfferRequest::builder()
+        .subject_id(BOB_SUBJECT)
+        .with_credential("EmployeeID_W3C_VC")
+        .build();
+    let response = credibil_oid4vci::handle(CONFIGURED_ISSUER, request, &provider)
+        .await
+        .expect("should create offer");
+    let offer = response.offer_type.as_object().expect("should have offer").clone();
+    let grants = offer.grants.expect("should have grant");
+    let pre_auth_grant = grants.pre_authorized_code.expect("should have pre-authorized code grant");
+
+    let request = TokenRequest::builder()
+        .grant_type(TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: pre_auth_grant.pre_authorized_code,
+            tx_code: response.tx_code.clone(),
+        })
+        .build();
+    let token = credibil_oid4vci::handle(CONFIGURED_ISSUER, request, &provider)
+        .await
+        .expect("should return token");
+
+    let details = &token.authorization_details.as_ref().expect("should have authorization details");
+    let request = CredentialRequest::builder()
+        .credential_identifier(&details[0].credential_identifiers[0])
+        .with_proof(common::proof(CONFIGURED_ISSUER, &provider, bob).await)
+        .build();
+    let request = credibil_oid4vci::Request {
+        body: request,
+        headers: CredentialHeaders {
+            authorization: token.access_token.clone(),
+            dpop: None,
+        },
+    };
+    let response = credibil_oid4vci::handle(CONFIGURED_ISSUER, request, &provider)
+        .await
+        .expect("should return credential");
+
+    let (credential, _) = common::w3c_credential(&provider, &response).await;
+    let resolver = async |kid: String| resolve_jwk(&kid, &provider).await;
+    let jwt: Jwt<W3cVcClaims> = decode_jws(&credential, resolver).await.expect("should decode");
+    let statuses = jwt.claims.vc.credential_status.expect("should have status").to_vec();
+    assert_eq!(statuses.len(), 2);
+    for status in statuses {
+        let id = status.id.expect("should have id");
+        assert!(id.starts_with(&format!("{STATUS_LIST_URI}/")), "unexpected status list {id}");
+    }
+}
+
+// Should bind a SD-JWT credential to the JWK in the proof header, and reject
+// JWK-bound proofs for configurations that only support DIDs.
+#[tokio::test]
+async fn jwk_proof() {
+    let provider = Issuer::new(ISSUER).await;
+    let bob = bob().await;
+
+    let VerifyBy::KeyId(bob_kid) = bob.verification_method().await.unwrap() else {
+        panic!("should have did");
+    };
+    let bob_jwk = resolve_jwk(&bob_kid, bob).await.expect("should resolve JWK");
+    let bob_key = KeyBinding::Jwk(bob_jwk.clone());
+
+    for (configuration_id, supported) in [("EmployeeID_W3C_VC", false), ("Identity_SD_JWT", true)] {
+        let request = CreateOfferRequest::builder()
+            .subject_id(BOB_SUBJECT)
+            .with_credential(configuration_id)
+            .build();
+        let response = credibil_oid4vci::handle(ISSUER, request, &provider)
+            .await
+            .expect("should create offer");
+
+        let offer = response.offer_type.as_object().expect("should have offer").clone();
+        let grants = offer.grants.expect("should have grant");
+        let pre_auth_grant =
+            grants.pre_authorized_code.expect("should have pre-authorized code grant");
+
+        let request = TokenRequest::builder()
+            .grant_type(TokenGrantType::PreAuthorizedCode {
+                pre_authorized_code: pre_auth_grant.pre_authorized_code,
+                tx_code: response.tx_code.clone(),
+            })
+            .build();
+        let token = credibil_oid4vci::handle(ISSUER, request, &provider)
+            .await
+            .expect("should return token");
+
+        let nonce = credibil_oid4vci::handle(ISSUER, NonceRequest, &provider)
+            .await
+            .expect("should return nonce");
+        let jws = JwsBuilder::new()
+            .typ(JwtType::ProofJwt)
+            .payload(ProofClaims::new().credential_issuer(ISSUER).nonce(&nonce.c_nonce))
+            .key_ref(&bob_key)
+            .add_signer(bob)
+            .build()
+            .await
+            .expect("builds JWS");
+
+        let details =
+            &token.authorization_details.as_ref().expect("should have authorization details");
+        let request = credibil_oid4vci::Request {
+            body: CredentialRequest::builder()
+                .credential_identifier(&details[0].credential_identifiers[0])
+                .with_proof(jws.encode().expect("should encode JWS"))
+                .build(),
+            headers: CredentialHeaders {
+                authorization: token.access_token.clone(),
+                dpop: None,
+            },
+        };
+        let result = credibil_oid4vci::handle(ISSUER, request, &provider).await;
+
+        if !supported {
+            let Err(Error::InvalidProof(_)) = result else {
+                panic!("should reject JWK binding");
+            };
+            continue;
+        }
+
+        let response = result.expect("should return credential");
+        let CredentialResponse::Credentials { credentials, .. } = &*response else {
+            panic!("expected single credential");
+        };
+        let Credential { credential } = &credentials[0];
+        let sd_jwt = credential.as_str().expect("should be a string");
+        let (token, _) = sd_jwt.split_once('~').expect("should split");
+
+        let resolver = async |kid: String| resolve_jwk(&kid, &provider).await;
+        let jwt: Jwt<SdJwtClaims> = decode_jws(token, resolver).await.expect("should decode");
+
+        let Some(KeyBinding::Jwk(jwk)) = jwt.claims.cnf else {
+            panic!("should be bound to a JWK");
+        };
+        assert_eq!(jwk, bob_jwk);
+    }
+}
+
+// Should reject stale proofs and proofs with an unknown `c_nonce`.
+#[tokio::test]
+async fn proof_checks() {
+    let provider = Issuer::new(ISSUER).await;
+    let bob = bob().await;
+
+    let request = CreateOfferRequest::builder()
+        .subject_id(BOB_SUBJECT)
+        .with_credential("EmployeeID_W3C_VC")
+        .build();
+    let response =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should create offer");
+
+    let offer = response.offer_type.as_object().expect("should have offer").clone();
+    let grants = offer.grants.expect("should have grant");
+    let pre_auth_grant = grants.pre_authorized_code.expect("should have pre-authorized code grant");
+
+    let request = TokenRequest::builder()
+        .grant_type(TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: pre_auth_grant.pre_authorized_code,
+            tx_code: response.tx_code.clone(),
+        })
+        .build();
+    let token =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+
+    let nonce = credibil_oid4vci::handle(ISSUER, NonceRequest, &provider)
+        .await
+        .expect("should return nonce");
+    let bob_key = bob
+        .verification_method()
+        .await
+        .expect("should have key")
+        .try_into()
+        .expect("should map key to key binding");
+
+    let details = &token.authorization_details.as_ref().expect("should have authorization details");
+    let request = async |claims: ProofClaims| {
+        let jws = JwsBuilder::new()
+            .typ(JwtType::ProofJwt)
+            .payload(claims)
+            .key_ref(&bob_key)
+            .add_signer(bob)
+            .build()
+            .await
+            .expect("builds JWS");
+        credibil_oid4vci::Request {
+            body: CredentialRequest::builder()
+                .credential_identifier(&details[0].credential_identifiers[0])
+                .with_proof(jws.encode().expect("should encode JWS"))
+                .build(),
+            headers: CredentialHeaders {
+                authorization: token.access_token.clone(),
+                dpop: None,
+            },
+        }
+    };
+
+    // proof created before the `c_nonce` could have been issued
+    let claims = ProofClaims {
+        iat: Utc::now() - TimeDelta::hours(1),
+        ..ProofClaims::new().credential_issuer(ISSUER).nonce(&nonce.c_nonce)
+    };
+    let Err(Error::InvalidProof(_)) =
+        credibil_oid4vci::handle(ISSUER, request(claims).await, &provider).await
+    else {
+        panic!("should reject stale proof");
+    };
+
+    // `c_nonce` not issued by the Credential Issuer
+    let claims = ProofClaims::new().credential_issuer(ISSUER).nonce("unknown");
+    let Err(Error::InvalidNonce(_)) =
+        credibil_oid4vci::handle(ISSUER, request(claims).await, &provider).await
+    else {
+        panic!("should reject unknown nonce");
+    };
+
+    // a fresh proof is accepted
+    let claims = ProofClaims::new().credential_issuer(ISSUER).nonce(&nonce.c_nonce);
+    credibil_oid4vci::handle(ISSUER, request(claims).await, &provider)
+        .await
+        .expect("should return credential");
+}
+
+// Should bind a SD-JWT credential to a key attested to by a trusted Wallet
+// Provider.
+#[tokio::test]
+async fn key_attestation() {
+    let provider = Issuer::new(ISSUER).await;
+    let bob = bob().await;
+
+    let wallet_provider = Wallet::new("https://issuance.io/wallet_provider").await;
+    let VerifyBy::KeyId(wp_kid) = wallet_provider.verification_method().await.unwrap() else {
+        panic!("should have did");
+    };
+    let wp_did = wp_kid.split('#').next().expect("should have did");
+    provider.trust_wallet_provider(ISSUER, wp_did).await.expect("should trust wallet provider");
+
+    // --------------------------------------------------
+    // Alice creates a credential offer for Bob
+    // --------------------------------------------------
+    let request = CreateOfferRequest::builder()
+        .subject_id(BOB_SUBJECT)
+        .with_credential("Identity_SD_JWT")
+        .build();
+    let response =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should create offer");
+
+    // --------------------------------------------------
+    // Bob receives the offer and requests a token
+    // --------------------------------------------------
+    let offer = response.offer_type.as_object().expect("should have offer").clone();
+    let grants = offer.grants.expect("should have grant");
+    let pre_auth_grant = grants.pre_authorized_code.expect("should have pre-authorized code grant");
+
+    let request = TokenRequest::builder()
+        .grant_type(TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: pre_auth_grant.pre_authorized_code,
+            tx_code: response.tx_code.clone(),
+        })
+        .build();
+    let token =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+
+    // --------------------------------------------------
+    // Bob's Wallet Provider attests to Bob's key
+    // --------------------------------------------------
+    let nonce = credibil_oid4vci::handle(ISSUER, NonceRequest, &provider)
+        .await
+        .expect("should return nonce");
+
+    let VerifyBy::KeyId(bob_kid) = bob.verification_method().await.unwrap() else {
+        panic!("should have did");
+    };
+    let bob_jwk = resolve_jwk(&bob_kid, bob).await.expect("should resolve JWK");
+    let wp_key = wallet_provider
+        .verification_method()
+        .await
+        .expect("should have key")
+        .try_into()
+        .expect("should map key to key binding");
+
+    let attestation = async |key_storage: AttackPotentialResistance| {
+        JwsBuilder::new()
+            .typ(JwtType::KeyAttestation)
+            .payload(AttestationClaims {
+                iat: Utc::now(),
+                exp: Utc::now() + TimeDelta::hours(1),
+                attested_keys: vec![bob_jwk.clone()],
+                key_storage: Some(vec![key_storage]),
+                nonce: Some(nonce.c_nonce.clone()),
+                ..AttestationClaims::default()
+            })
+            .key_ref(&wp_key)
+            .add_signer(&wallet_provider)
+            .build()
+            .await
+            .expect("builds JWS")
+            .encode()
+            .expect("should encode JWS")
+    };
+
+    let details = &token.authorization_details.as_ref().expect("should have authorization details");
+    let credential_request = |attestation: String| credibil_oid4vci::Request {
+        body: CredentialRequest::builder()
+            .credential_identifier(&details[0].credential_identifiers[0])
+            .with_attestation(attestation)
+            .build(),
+        headers: CredentialHeaders {
+            authorization: token.access_token.clone(),
+            dpop: None,
+        },
+    };
+
+    // --------------------------------------------------
+    // Keys with insufficient attack resistance are rejected
+    // --------------------------------------------------
+    let request = credential_request(attestation(AttackPotentialResistance::Iso18045EBasic).await);
+    credibil_oid4vci::handle(ISSUER, request, &provider)
+        .await
+        .expect_err("should reject key storage");
+
+    // --------------------------------------------------
+    // Bob requests a credential bound to the attested key
+    // --------------------------------------------------
+    let request = credential_request(attestation(AttackPotentialResistance::Iso18045High).await);
+    let response = credibil_oid4vci::handle(ISSUER, request, &provider)
+        .await
+        .expect("should return credential");
+
+    let CredentialResponse::Credentials { credentials, .. } = &*response else {
+        panic!("expected single credential");
+    };
+    assert_eq!(credentials.len(), 1);
+
+    let Credential { credential } = &credentials[0];
+    let sd_jwt = credential.as_str().expect("should be a string");
+    let (token, _) = sd_jwt.split_once('~').expect("should split");
+
+    let resolver = async |kid: String| resolve_jwk(&kid, &provider).await;
+    let jwt: Jwt<SdJwtClaims> = decode_jws(token, resolver).await.expect("should decode");
+
+    let Some(KeyBinding::Jwk(jwk)) = jwt.claims.cnf else {
+        panic!("should be bound to a JWK");
+    };
+    assert_eq!(jwk, bob_jwk);
+    assert!(jwt.claims.sub.expect("should have sub").starts_with("did:jwk:"));
+}
+
+// Should bind the access token to the DPoP proof's key and require a matching
+// DPoP proof at the Credential Endpoint.
+#[tokio::test]
+async fn dpop() {
+    let provider = Issuer::new(ISSUER).await;
+    let bob = bob().await;
+
+    let VerifyBy::KeyId(bob_kid) = bob.verification_method().await.unwrap() else {
+        panic!("should have did");
+    };
+    let bob_jwk = resolve_jwk(&bob_kid, bob).await.expect("should resolve JWK");
+    let dpop_key = KeyBinding::Jwk(bob_jwk);
+
+    let request = CreateOfferRequest::builder()
+        .subject_id(BOB_SUBJECT)
+        .with_credential("EmployeeID_W3C_VC")
+        .build();
+    let response =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should create offer");
+    let offer = response.offer_type.as_object().expect("should have offer").clone();
+    let grants = offer.grants.expect("should have grant");
+    let pre_auth_grant = grants.pre_authorized_code.expect("should have pre-authorized code grant");
+
+    // --------------------------------------------------
+    // Bob requests a DPoP-bound token
+    // --------------------------------------------------
+    let claims = DPoPClaims::new(format!("{ISSUER}/token"));
+    let request = credibil_oid4vci::Request {
+        body: TokenRequest::builder()
+            .grant_type(TokenGrantType::PreAuthorizedCode {
+                pre_authorized_code: pre_auth_grant.pre_authorized_code,
+                tx_code: response.tx_code.clone(),
+            })
+            .build(),
+        headers: TokenHeaders {
+            dpop: Some(dpop_proof(bob, &dpop_key, claims).await),
+            ..TokenHeaders::default()
+        },
+    };
+    let token =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+    assert_eq!(token.token_type, TokenType::DPoP);
+
+    let details = &token.authorization_details.as_ref().expect("should have authorization details");
+    let credential_request = CredentialRequest::builder()
+        .credential_identifier(&details[0].credential_identifiers[0])
+        .with_proof(common::proof(ISSUER, &provider, bob).await)
+        .build();
+
+    // --------------------------------------------------
+    // The Credential Endpoint rejects missing and mismatched DPoP proofs
+    // --------------------------------------------------
+    let credential_endpoint = format!("{ISSUER}/credential");
+    let access_token = &token.access_token;
+
+    let unbound = DPoPClaims::new(&credential_endpoint);
+    let wrong_uri = DPoPClaims::new(format!("{ISSUER}/deferred")).access_token(access_token);
+    for dpop in [
+        None,
+        Some(dpop_proof(bob, &dpop_key, unbound).await),
+        Some(dpop_proof(bob, &dpop_key, wrong_uri).await),
+    ] {
+        let request = credibil_oid4vci::Request {
+            body: credential_request.clone(),
+            headers: CredentialHeaders {
+                authorization: access_token.clone(),
+                dpop,
+            },
+        };
+        let Err(Error::InvalidDpopProof(_)) =
+            credibil_oid4vci::handle(ISSUER, request, &provider).await
+        else {
+            panic!("should reject DPoP proof");
+        };
+    }
+
+    // --------------------------------------------------
+    // Bob requests a credential with a valid DPoP proof
+    // --------------------------------------------------
+    let claims = DPoPClaims::new(&credential_endpoint).access_token(access_token);
+    let proof = dpop_proof(bob, &dpop_key, claims).await;
+    let request = credibil_oid4vci::Request {
+        body: credential_request.clone(),
+        headers: CredentialHeaders {
+            authorization: access_token.clone(),
+            dpop: Some(proof.clone()),
+        },
+    };
+    let response = credibil_oid4vci::handle(ISSUER, request, &provider)
+        .await
+        .expect("should return credential");
+    let (_, claims) = common::w3c_credential(&provider, &response).await;
+    assert_eq!(claims.get("family_name"), Some(&json!("Person")));
+
+    // a replayed proof is rejected
+    let request = credibil_oid4vci::Request {
+        body: credential_request,
+        headers: CredentialHeaders {
+            authorization: access_token.clone(),
+            dpop: Some(proof),
+        },
+    };
+    let Err(Error::InvalidDpopProof(_)) =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject replayed DPoP proof");
+    };
+
+    // --------------------------------------------------
+    // The refresh token can only be used with the DPoP key
+    // --------------------------------------------------
+    let refresh_token = token.refresh_token.clone().expect("should have refresh token");
+    let refresh = || {
+        TokenRequest::builder()
+            .grant_type(TokenGrantType::RefreshToken {
+                refresh_token: refresh_token.clone(),
+            })
+            .build()
+    };
+
+    let Err(Error::InvalidDpopProof(_)) =
+        credibil_oid4vci::handle(ISSUER, refresh(), &provider).await
+    else {
+        panic!("should reject a refresh request without a DPoP proof");
+    };
+
+    let claims = DPoPClaims::new(format!("{ISSUER}/token"));
+    let request = credibil_oid4vci::Request {
+        body: refresh(),
+        headers: TokenHeaders {
+            dpop: Some(dpop_proof(bob, &dpop_key, claims).await),
+            ..TokenHeaders::default()
+        },
+    };
+    let refreshed =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+    assert_eq!(refreshed.token_type, TokenType::DPoP);
+}
+
+// Should require DPoP proofs to include a server-provided nonce when
+// configured, returning a fresh nonce to use.
+#[tokio::test]
+async fn dpop_nonce() {
+    // a separate issuer so other tests do not require DPoP nonces
+    const NONCE_ISSUER: &str = "http://localhost:8094";
+
+    let provider = Issuer::new(NONCE_ISSUER).await;
+    provider
+        .configure(NONCE_ISSUER, "dpop_nonce_required", json!(true))
+        .await
+        .expect("should update configuration");
+    let bob = bob().await;
+
+    let VerifyBy::KeyId(bob_kid) = bob.verification_method().await.unwrap() else {
+        panic!("should have did");
+    };
+    let bob_jwk = resolve_jwk(&bob_kid, bob).await.expect("should resolve JWK");
+    let dpop_key = KeyBinding::Jwk(bob_jwk);
+
+    let request = CreateOfferRequest::builder()
+        .subject_id(BOB_SUBJECT)
+        .with_credential("EmployeeID_W3C_VC")
+        .build();
+    let response = credibil_oid4vci::handle(NONCE_ISSUER, request, &provider)
+        .await
+        .expect("should create offer");
+    let offer = response.offer_type.as_object().expect("should have offer").clone();
+    let grants = offer.grants.expect("should have grant");
+    let pre_auth_grant = grants.pre_authorized_code.expect("should have pre-authorized code grant");
+    let token_request = TokenRequest::builder()
+        .grant_type(TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: pre_auth_grant.pre_authorized_code,
+            tx_code: response.tx_code.clone(),
+        })
+        .build();
+
+    // --------------------------------------------------
+    // The token endpoint requires a nonce in the DPoP proof
+    // --------------------------------------------------
+    let token_endpoint = format!("{NONCE_ISSUER}/token");
+    let request = credibil_oid4vci::Request {
+        body: token_request.clone(),
+        headers: TokenHeaders {
+            dpop: Some(dpop_proof(bob, &dpop_key, DPoPClaims::new(&token_endpoint)).await),
+            ..TokenHeaders::default()
+        },
+    };
+    let Err(e) = credibil_oid4vci::handle(NONCE_ISSUER, request, &provider).await else {
+        panic!("should require a DPoP nonce");
+    };
+    let (status, headers) = e.dpop_nonce_response().expect("should be a DPoP nonce error");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(headers.get(WWW_AUTHENTICATE).is_none());
+    let Error::UseDpopNonce(DpopNonce { nonce, .. }) = e else {
+        panic!("should be a DPoP nonce error");
+    };
+    assert_eq!(headers.get("dpop-nonce").and_then(|h| h.to_str().ok()), Some(nonce.as_str()));
+
+    // a `c_nonce` is not a DPoP nonce
+    let c_nonce = credibil_oid4vci::handle(NONCE_ISSUER, NonceRequest, &provider)
+        .await
+        .expect("should return nonce")
+        .c_nonce
+        .clone();
+    let request = credibil_oid4vci::Request {
+        body: token_request.clone(),
+        headers: TokenHeaders {
+            dpop: Some(
+                dpop_proof(bob, &dpop_key, DPoPClaims::new(&token_endpoint).nonce(c_nonce)).await,
+            ),
+            ..TokenHeaders::default()
+        },
+    };
+    let Err(Error::UseDpopNonce(_)) =
+        credibil_oid4vci::handle(NONCE_ISSUER, request, &provider).await
+    else {
+        panic!("should reject a `c_nonce` as a DPoP nonce");
+    };
+
+    let request = credibil_oid4vci::Request {
+        body: token_request,
+        headers: TokenHeaders {
+            dpop: Some(
+                dpop_proof(bob, &dpop_key, DPoPClaims::new(&token_endpoint).nonce(&nonce)).await,
+            ),
+            ..TokenHeaders::default()
+        },
+    };
+    let token = credibil_oid4vci::handle(NONCE_ISSUER, request, &provider)
+        .await
+        .expect("should return token");
+    assert_eq!(token.token_type, TokenType::DPoP);
+
+    // --------------------------------------------------
+    // The Credential Endpoint challenges for a nonce
+    // --------------------------------------------------
+    let details = &token.authorization_details.as_ref().expect("should have authorization details");
+    let credential_request = CredentialRequest::builder()
+        .credential_identifier(&details[0].credential_identifiers[0])
+        .with_proof(common::proof(NONCE_ISSUER, &provider, bob).await)
+        .build();
+    let credential_endpoint = format!("{NONCE_ISSUER}/credential");
+    let access_token = &token.access_token;
+
+    let claims = DPoPClaims::new(&credential_endpoint).access_token(access_token);
+    let request = credibil_oid4vci::Request {
+        body: credential_request.clone(),
+        headers: CredentialHeaders {
+            authorization: access_token.clone(),
+            dpop: Some(dpop_proof(bob, &dpop_key, claims).await),
+        },
+    };
+    let Err(e) = credibil_oid4vci::handle(NONCE_ISSUER, request, &provider).await else {
+        panic!("should require a DPoP nonce");
+    };
+    let (status, headers) = e.dpop_nonce_response().expect("should be a DPoP nonce error");
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let challenge = headers.get(WWW_AUTHENTICATE).and_then(|h| h.to_str().ok());
+    assert!(challenge.is_some_and(|c| c.starts_with(r#"DPoP error="use_dpop_nonce""#)));
+    let Error::UseDpopNonce(DpopNonce { nonce, .. }) = e else {
+        panic!("should be a DPoP nonce error");
+    };
+
+    let claims = DPoPClaims::new(&credential_endpoint).access_token(access_token).nonce(nonce);
+    let request = credibil_oid4vci::Request {
+        body: credential_request,
+        headers: CredentialHeaders {
+            authorization: access_token.clone(),
+            dpop: Some(dpop_proof(bob, &dpop_key, claims).await),
+        },
+    };
+    let response = credibil_oid4vci::handle(NONCE_ISSUER, request, &provider)
+        .await
+        .expect("should return credential");
+    let (_, claims) = common::w3c_credential(&provider, &response).await;
+    assert_eq!(claims.get("family_name"), Some(&json!("Person")));
+}
+
+// Should issue a self-contained JWT access token the Credential Endpoint
+// verifies by signature rather than retrieving from state, and accept tokens
+// issued by any of the issuer's authorization servers.
+#[tokio::test]
+async fn jwt_access_token() {
+    // a separate issuer so other tests continue to use opaque tokens
+    const JWT_ISSUER: &str = "http://localhost:8090";
+    const EXTERNAL: &str = "https://auth.example.com";
+
+    let provider = Issuer::new(JWT_ISSUER).await;
+    provider.use_jwt_access_tokens(JWT_ISSUER).await.expect("should update metadata");
+    provider
+        .use_authorization_servers(JWT_ISSUER, &[ISSUER, EXTERNAL])
+        .await
+        .expect("should set authorization servers");
+    let bob = bob().await;
+
+    let request = CreateOfferRequest::builder()
+        .subject_id(BOB_SUBJECT)
+        .with_credential("EmployeeID_W3C_VC")
+        .build();
+    let response = credibil_oid4vci::handle(JWT_ISSUER, request, &provider)
+        .await
+        .expect("should create offer");
+    let offer = response.offer_type.as_object().expect("should have offer").clone();
+    let grants = offer.grants.expect("should have grant");
+    let pre_auth_grant = grants.pre_authorized_code.expect("should have pre-authorized code grant");
+
+    // --------------------------------------------------
+    // Bob receives a JWT access token
+    // --------------------------------------------------
+    let request = TokenRequest::builder()
+        .client_id(WALLET_ID)
+        .grant_type(TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: pre_auth_grant.pre_authorized_code,
+            tx_code: response.tx_code.clone(),
+        })
+        .build();
+    let token = credibil_oid4vci::handle(JWT_ISSUER, request, &provider)
+        .await
+        .expect("should return token");
+
+    let resolver = async |kid: String| resolve_jwk(&kid, &provider).await;
+    let jwt: Jwt<AccessTokenClaims> =
+        decode_jws(&token.access_token, resolver).await.expect("should decode");
+    assert_eq!(jwt.header.typ, JwtType::AccessToken.to_string());
+    assert_eq!(jwt.claims.aud, JWT_ISSUER);
+    assert_eq!(jwt.claims.sub, BOB_SUBJECT);
+    assert_eq!(Some(jwt.claims.authorization_details.clone()), token.authorization_details);
+
+    // --------------------------------------------------
+    // Bob requests a credential using the JWT access token
+    // --------------------------------------------------
+    let details = &token.authorization_details.as_ref().expect("should have authorization details");
+    let credential_request = CredentialRequest::builder()
+        .credential_identifier(&details[0].credential_identifiers[0])
+        .with_proof(common::proof(JWT_ISSUER, &provider, bob).await)
+        .build();
+
+    // a token with an invalid signature is rejected
+    let (signing_input, signature) = token.access_token.rsplit_once('.').expect("should be a JWS");
+    let first = if signature.starts_with('A') { 'B' } else { 'A' };
+    let request = credibil_oid4vci::Request {
+        body: credential_request.clone(),
+        headers: CredentialHeaders {
+            authorization: format!("{signing_input}.{first}{}", &signature[1..]),
+            dpop: None,
+        },
+    };
+    let Err(Error::AccessDenied(_)) =
+        credibil_oid4vci::handle(JWT_ISSUER, request, &provider).await
+    else {
+        panic!("should reject an access token with an invalid signature");
+    };
+
+    let request = credibil_oid4vci::Request {
+        body: credential_request.clone(),
+        headers: CredentialHeaders {
+            authorization: token.access_token.clone(),
+            dpop: None,
+        },
+    };
+    let response = credibil_oid4vci::handle(JWT_ISSUER, request, &provider)
+        .await
+        .expect("should return credential");
+    let (_, claims) = common::w3c_credential(&provider, &response).await;
+    assert_eq!(claims.get("family_name"), Some(&json!("Person")));
+
+    // --------------------------------------------------
+    // Tokens issued by a listed third-party authorization server are accepted
+    // --------------------------------------------------
+    let key: KeyBinding = provider
+        .verification_method()
+        .await
+        .expect("should have key")
+        .try_into()
+        .expect("should map key to key binding");
+    let reissue = async |iss: &str| {
+        let claims = AccessTokenClaims {
+            iss: iss.to_string(),
+            jti: format!("{iss}-token"),
+            ..jwt.claims.clone()
+        };
+        JwsBuilder::new()
+            .typ(JwtType::AccessToken)
+            .payload(claims)
+            .key_ref(&key)
+            .add_signer(&provider)
+            .build()
+            .await
+            .expect("builds JWS")
+            .encode()
+            .expect("should encode JWS")
+    };
+
+    let request = credibil_oid4vci::Request {
+        body: credential_request.clone(),
+        headers: CredentialHeaders {
+            authorization: reissue("https://unknown.example.com").await,
+            dpop: None,
+        },
+    };
+    let Err(Error::AccessDenied(_)) =
+        credibil_oid4vci::handle(JWT_ISSUER, request, &provider).await
+    else {
+        panic!("should reject a token issued by an unlisted authorization server");
+    };
+
+    let request = credibil_oid4vci::Request {
+        body: CredentialRequest::builder()
+            .credential_identifier(&details[0].credential_identifiers[0])
+            .with_proof(common::proof(JWT_ISSUER, &provider, bob).await)
+            .build(),
+        headers: CredentialHeaders {
+            authorization: reissue(EXTERNAL).await,
+            dpop: None,
+        },
+    };
+    let response = credibil_oid4vci::handle(JWT_ISSUER, request, &provider)
+        .await
+        .expect("should return credential");
+    let (_, claims) = common::w3c_credential(&provider, &response).await;
+    assert_eq!(claims.get("family_name"), Some(&json!("Person")));
+
+    // --------------------------------------------------
+    // The revoked JWT access token is rejected
+    // --------------------------------------------------
+    let request = RevocationRequest {
+        token: token.access_token.clone(),
+        client_id: WALLET_ID.to_string(),
+        ..RevocationRequest::default()
+    };
+    credibil_oid4vci::handle(JWT_ISSUER, request, &provider).await.expect("should revoke");
+
+    let request = credibil_oid4vci::Request {
+        body: credential_request,
+        headers: CredentialHeaders {
+            authorization: token.access_token.clone(),
+            dpop: None,
+        },
+    };
+    let Err(Error::AccessDenied(e)) =
+        credibil_oid4vci::handle(JWT_ISSUER, request, &provider).await
+    else {
+        panic!("should reject a revoked access token");
+    };
+    assert!(e.contains("revoked"));
+}
+
+// Present the credential to the verifier as a `jwt_vc_json` presentation.
+async fn presentation(wallet: &Wallet, credential: &str) -> String {
+    let VerifyBy::KeyId(kid) = wallet.verification_method().await.unwrap() else {
+        panic!("should have did");
+    };
+    let holder = kid.split('#').next().expect("should have did");
+
+    let vp = VerifiablePresentation {
+        context: vec![Kind::String("https://www.w3.org/2018/credentials/v1".to_string())],
+        r#type: OneMany::One("VerifiablePresentation".to_string()),
+        verifiable_credential: Some(vec![Kind::String(credential.to_string())]),
+        holder: Some(holder.to_string()),
+        ..VerifiablePresentation::default()
+    };
+    let mut claims = W3cVpClaims::from(vp);
+    claims.aud = VERIFIER.to_string();
+    claims.nonce = "nonce".to_string();
+
+    let key = wallet.verification_method().await.unwrap().try_into().expect("should map key");
+    encode_jws(&claims, &key, wallet).await.expect("should encode presentation")
+}
+
+// Sign a DPoP proof JWT using the wallet's key.
+async fn dpop_proof(wallet: &Wallet, key: &KeyBinding, claims: DPoPClaims) -> String {
+    JwsBuilder::new()
+        .typ(JwtType::DPoP)
+        .payload(claims)
+        .key_ref(key)
+        .add_signer(wallet)
+        .build()
+        .await
+        .expect("builds JWS")
+        .encode()
+        .expect("should encode JWS")
 }
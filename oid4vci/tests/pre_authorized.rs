@@ -1,18 +1,31 @@
 //! Pre-Authorized Code Flow Tests
 
+mod common;
+
 use std::collections::HashMap;
 
+use base64ct::{Base64UrlUnpadded, Encoding};
+use chrono::{TimeDelta, Utc};
 use credibil_oid4vci::identity::{Signature, VerifyBy};
-use credibil_oid4vci::jose::{JwsBuilder, Jwt, decode_jws};
+use credibil_oid4vci::jose::{JwsBuilder, Jwt, KeyBinding, decode_jws};
+use credibil_oid4vci::oauth::TokenEndpointAuth;
 use credibil_oid4vci::proof::W3cVcClaims;
+use credibil_oid4vci::provider::Metadata;
 use credibil_oid4vci::types::{
-    AuthorizationDetail, CreateOfferRequest, Credential, CredentialOfferRequest, CredentialRequest,
-    CredentialResponse, NonceRequest, NotificationEvent, NotificationRequest, ProofClaims,
-    TokenGrantType, TokenRequest,
+    AuthorizationDetail, ChallengeRequest, ClientAssertion, ClientAssertionClaims,
+    ClientAttestationClaims, ClientAttestationPopClaims, CreateOfferRequest, Credential,
+    CredentialOfferRequest, CredentialRequest, CredentialResponse, GrantType, IntrospectionRequest,
+    IntrospectionResponse, IssuanceEventKind, NonceRequest, NotificationEvent, NotificationRequest,
+    ProofClaims, RevocationRequest, TokenGrantType, TokenRequest, TokenTypeHint,
+};
+use credibil_oid4vci::{
+    ClientAttestationHeaders, CredentialHeaders, Error, JwtType, NotificationHeaders, OneMany,
+    TokenHeaders,
 };
-use credibil_oid4vci::{CredentialHeaders, JwtType, NotificationHeaders, OneMany};
 use credibil_proof::resolve_jwk;
+use hmac::{Hmac, Mac};
 use serde_json::json;
+use sha2::Sha256;
 use test_utils::issuer::Issuer;
 use test_utils::wallet::Wallet;
 use tokio::sync::OnceCell;
@@ -96,6 +109,7 @@ async fn offer_val() {
         body: request,
         headers: CredentialHeaders {
             authorization: token.access_token.clone(),
+            dpop: None,
         },
     };
     let response = credibil_oid4vci::handle(ISSUER, request, &provider)
@@ -129,6 +143,64 @@ async fn offer_val() {
     assert_eq!(subject.claims.get("family_name"), Some(&json!("Person")));
 }
 
+// Should return a credential requested by `credential_configuration_id` rather
+// than `credential_identifier`.
+#[tokio::test]
+async fn configuration_id() {
+    let provider = Issuer::new(ISSUER).await;
+    let bob = bob().await;
+
+    // --------------------------------------------------
+    // Alice creates a credential offer for Bob
+    // --------------------------------------------------
+    let request = CreateOfferRequest::builder()
+        .subject_id(BOB_SUBJECT)
+        .with_credential("EmployeeID_W3C_VC")
+        .build();
+    let response =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should create offer");
+
+    // --------------------------------------------------
+    // Bob receives the offer and requests a token
+    // --------------------------------------------------
+    let offer = response.offer_type.as_object().expect("should have offer").clone();
+    let grants = offer.grants.expect("should have grant");
+    let grant = grants.pre_authorized_code.expect("should have pre-authorized code grant");
+
+    let request = TokenRequest::builder()
+        .grant_type(TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: grant.pre_authorized_code,
+            tx_code: response.tx_code.clone(),
+        })
+        .build();
+    let token =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+
+    // --------------------------------------------------
+    // Bob requests the credential by its configuration
+    // --------------------------------------------------
+    let request = CredentialRequest::builder()
+        .credential_configuration_id("EmployeeID_W3C_VC")
+        .with_proof(common::proof(ISSUER, &provider, bob).await)
+        .build();
+    let request = credibil_oid4vci::Request {
+        body: request,
+        headers: CredentialHeaders {
+            authorization: token.access_token.clone(),
+            dpop: None,
+        },
+    };
+    let response = credibil_oid4vci::handle(ISSUER, request, &provider)
+        .await
+        .expect("should return credential");
+
+    // --------------------------------------------------
+    // Bob receives a credential populated from his dataset
+    // --------------------------------------------------
+    let (_, claims) = common::w3c_credential(&provider, &response).await;
+    assert_eq!(claims.get("family_name"), Some(&json!("Person")));
+}
+
 // Should return a credential when using the pre-authorized code flow and the
 // credential offer to the Wallet is made by reference.
 #[tokio::test]
@@ -242,6 +314,7 @@ async fn two_datasets() {
             body: request,
             headers: CredentialHeaders {
                 authorization: token.access_token.clone(),
+                dpop: None,
             },
         };
 
@@ -353,6 +426,7 @@ async fn reduce_credentials() {
         body: request,
         headers: CredentialHeaders {
             authorization: token.access_token.clone(),
+            dpop: None,
         },
     };
 
@@ -458,6 +532,7 @@ async fn reduce_claims() {
         body: request,
         headers: CredentialHeaders {
             authorization: token.access_token.clone(),
+            dpop: None,
         },
     };
 
@@ -564,6 +639,7 @@ async fn notify_accepted() {
         body: request,
         headers: CredentialHeaders {
             authorization: token.access_token.clone(),
+            dpop: None,
         },
     };
 
@@ -578,18 +654,768 @@ async fn notify_accepted() {
         panic!("should have notification id");
     };
 
-    let request = NotificationRequest::builder()
-        .notification_id(notification_id.as_ref().unwrap())
-        .event(NotificationEvent::CredentialAccepted)
-        .event_description("Credential accepted")
+    let notify = |notification_id: &str| credibil_oid4vci::Request {
+        body: NotificationRequest::builder()
+            .notification_id(notification_id)
+            .event(NotificationEvent::CredentialAccepted)
+            .event_description("Credential accepted")
+            .build(),
+        headers: NotificationHeaders {
+            authorization: token.access_token.clone(),
+            dpop: None,
+        },
+    };
+
+    let notification_id = notification_id.as_ref().expect("should have notification id");
+    credibil_oid4vci::handle(ISSUER, notify(notification_id), &provider)
+        .await
+        .expect("response is ok");
+
+    // repeated notifications are idempotent
+    credibil_oid4vci::handle(ISSUER, notify(notification_id), &provider)
+        .await
+        .expect("response is ok");
+
+    // a token from another grant for Bob cannot be used
+    let mut request = token_request(&provider, "", None).await;
+    request.client_id = None;
+    let other =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+    let mut request = notify(notification_id);
+    request.headers.authorization = other.access_token.clone();
+    let Err(Error::InvalidNotificationId(_)) =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject a token from another grant");
+    };
+
+    let Err(Error::InvalidNotificationId(_)) =
+        credibil_oid4vci::handle(ISSUER, notify("unknown"), &provider).await
+    else {
+        panic!("should reject an unknown notification id");
+    };
+}
+
+// Should issue a new access token, for the same credentials, in exchange for a
+// refresh token and detect reuse of rotated refresh tokens.
+#[tokio::test]
+async fn refresh_token() {
+    let provider = Issuer::new(ISSUER).await;
+    let client_id = "http://localhost:8082";
+
+    let request = token_request(&provider, client_id, None).await;
+    let token =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+    let refresh_token = token.refresh_token.clone().expect("should have refresh token");
+
+    let refresh = |refresh_token: &str| {
+        TokenRequest::builder()
+            .client_id(client_id)
+            .grant_type(TokenGrantType::RefreshToken {
+                refresh_token: refresh_token.to_string(),
+            })
+            .build()
+    };
+
+    // a rejected request does not use the refresh token
+    let request = TokenRequest::builder()
+        .client_id("http://localhost:8083")
+        .grant_type(TokenGrantType::RefreshToken {
+            refresh_token: refresh_token.clone(),
+        })
         .build();
+    let Err(Error::InvalidGrant(_)) = credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject a refresh token issued to another client");
+    };
+
+    // --------------------------------------------------
+    // Bob exchanges the refresh token for a new access token
+    // --------------------------------------------------
+    let refreshed = credibil_oid4vci::handle(ISSUER, refresh(&refresh_token), &provider)
+        .await
+        .expect("should return token");
+    assert_ne!(refreshed.access_token, token.access_token);
+    assert_eq!(refreshed.authorization_details, token.authorization_details);
+    let rotated = refreshed.refresh_token.clone().expect("should have refresh token");
+    assert_ne!(rotated, refresh_token);
+
+    // --------------------------------------------------
+    // Reusing a refresh token revokes its rotated successors
+    // --------------------------------------------------
+    let Err(Error::InvalidGrant(_)) =
+        credibil_oid4vci::handle(ISSUER, refresh(&refresh_token), &provider).await
+    else {
+        panic!("should reject a reused refresh token");
+    };
+    let Err(Error::InvalidGrant(_)) =
+        credibil_oid4vci::handle(ISSUER, refresh(&rotated), &provider).await
+    else {
+        panic!("should revoke the rotated refresh token");
+    };
+}
+
+// Should only exchange a refresh token once when it is presented by concurrent
+// requests.
+#[tokio::test]
+async fn concurrent_refresh() {
+    let provider = Issuer::new(ISSUER).await;
+    let client_id = "http://localhost:8082";
+
+    let request = token_request(&provider, client_id, None).await;
+    let token =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+    let refresh_token = token.refresh_token.expect("should have refresh token");
+
+    let refresh = || {
+        TokenRequest::builder()
+            .client_id(client_id)
+            .grant_type(TokenGrantType::RefreshToken {
+                refresh_token: refresh_token.clone(),
+            })
+            .build()
+    };
+
+    let (first, second) = tokio::join!(
+        credibil_oid4vci::handle(ISSUER, refresh(), &provider),
+        credibil_oid4vci::handle(ISSUER, refresh(), &provider)
+    );
+    assert!(first.is_ok() != second.is_ok(), "only one request should succeed");
+}
+
+// Should authenticate confidential clients using `private_key_jwt` and
+// `client_secret_jwt` client assertions.
+#[tokio::test]
+async fn client_assertion() {
+    let provider = Issuer::new(ISSUER).await;
+    let bob = bob().await;
+    let token_endpoint = format!("{ISSUER}/token");
+
+    let VerifyBy::KeyId(bob_kid) = bob.verification_method().await.unwrap() else {
+        panic!("should have did");
+    };
+    let mut bob_jwk =
+        serde_json::to_value(resolve_jwk(&bob_kid, bob).await.expect("should resolve JWK"))
+            .expect("should serialize");
+    bob_jwk["kid"] = json!(&bob_kid);
+
+    let wallet = Metadata::client(&provider, ISSUER, "http://localhost:8082")
+        .await
+        .expect("should have client");
+
+    // --------------------------------------------------
+    // private_key_jwt
+    // --------------------------------------------------
+    let mut client = wallet.clone();
+    client.oauth.token_endpoint_auth_method = Some(TokenEndpointAuth::PrivateKeyJwt);
+    client.oauth.jwks = Some(json!({"keys": [bob_jwk]}).to_string());
+    let client = Metadata::register(&provider, ISSUER, &client).await.expect("should register");
+    let client_id = &client.oauth.client_id;
+
+    let Err(Error::InvalidClient(_)) = credibil_oid4vci::handle(
+        ISSUER,
+        token_request(&provider, client_id, None).await,
+        &provider,
+    )
+    .await
+    else {
+        panic!("should require a client assertion");
+    };
+
+    let assertion = JwsBuilder::new()
+        .typ(JwtType::Jwt)
+        .payload(ClientAssertionClaims::new(client_id, &token_endpoint))
+        .key_ref(&KeyBinding::Kid(bob_kid.clone()))
+        .add_signer(bob)
+        .build()
+        .await
+        .expect("builds JWS")
+        .encode()
+        .expect("should encode JWS");
+    let request = token_request(&provider, client_id, Some(&assertion)).await;
+    credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+
+    let request = token_request(&provider, client_id, Some(&assertion)).await;
+    let Err(Error::InvalidClient(_)) = credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject a reused client assertion");
+    };
+
+    // --------------------------------------------------
+    // client_secret_jwt
+    // --------------------------------------------------
+    let mut client = wallet;
+    client.oauth.token_endpoint_auth_method = Some(TokenEndpointAuth::ClientSecretJwt);
+    client.oauth.client_secret = Some("s3cr3t".to_string());
+    let client = Metadata::register(&provider, ISSUER, &client).await.expect("should register");
+    let client_id = &client.oauth.client_id;
+
+    // long-lived assertions are rejected
+    let claims = ClientAssertionClaims {
+        exp: Utc::now() + TimeDelta::hours(1),
+        ..ClientAssertionClaims::new(client_id, &token_endpoint)
+    };
+    let request = token_request(&provider, client_id, Some(&hs256(&claims, "s3cr3t"))).await;
+    let Err(Error::InvalidClient(_)) = credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject an assertion with a distant `exp`");
+    };
+
+    let claims = ClientAssertionClaims::new(client_id, &token_endpoint);
+    let assertion = hs256(&claims, "wrong");
+    let request = token_request(&provider, client_id, Some(&assertion)).await;
+    let Err(Error::InvalidClient(_)) = credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject an assertion using the wrong secret");
+    };
+
+    let assertion = hs256(&claims, "s3cr3t");
+    let request = token_request(&provider, client_id, Some(&assertion)).await;
+    credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+}
+
+// Should authenticate Wallets using a client attestation issued by a trusted
+// Wallet Provider.
+#[tokio::test]
+async fn client_attestation() {
+    let provider = Issuer::new(ISSUER).await;
+    let bob = bob().await;
+
+    let wallet_provider = Wallet::new("https://pre_auth.io/wallet_provider").await;
+    let VerifyBy::KeyId(wp_kid) = wallet_provider.verification_method().await.unwrap() else {
+        panic!("should have did");
+    };
+    let wp_did = wp_kid.split('#').next().expect("should have did");
 
+    let VerifyBy::KeyId(bob_kid) = bob.verification_method().await.unwrap() else {
+        panic!("should have did");
+    };
+    let bob_jwk = resolve_jwk(&bob_kid, bob).await.expect("should resolve JWK");
+
+    let mut client = Metadata::client(&provider, ISSUER, "http://localhost:8082")
+        .await
+        .expect("should have client");
+    client.oauth.token_endpoint_auth_method = Some(TokenEndpointAuth::AttestJwtClientAuth);
+    let client = Metadata::register(&provider, ISSUER, &client).await.expect("should register");
+    let client_id = &client.oauth.client_id;
+
+    let attest = async |iss: &str| {
+        JwsBuilder::new()
+            .typ(JwtType::ClientAttestation)
+            .payload(ClientAttestationClaims {
+                iss: iss.to_string(),
+                sub: client_id.clone(),
+                exp: Utc::now() + TimeDelta::hours(1),
+                iat: Some(Utc::now()),
+                cnf: KeyBinding::Jwk(bob_jwk.clone()),
+                wallet_name: Some("Bob's Wallet".to_string()),
+                wallet_link: None,
+                metadata: serde_json::Map::new(),
+            })
+            .key_ref(&KeyBinding::Kid(wp_kid.clone()))
+            .add_signer(&wallet_provider)
+            .build()
+            .await
+            .expect("builds JWS")
+            .encode()
+            .expect("should encode JWS")
+    };
+    let attestation = attest(wp_did).await;
+    let pop_with = async |nonce: Option<&str>| {
+        let mut claims = ClientAttestationPopClaims::new(client_id, ISSUER);
+        if let Some(nonce) = nonce {
+            claims = claims.nonce(nonce);
+        }
+        JwsBuilder::new()
+            .typ(JwtType::ClientAttestationPop)
+            .payload(claims)
+            .key_ref(&KeyBinding::Kid(bob_kid.clone()))
+            .add_signer(bob)
+            .build()
+            .await
+            .expect("builds JWS")
+            .encode()
+            .expect("should encode JWS")
+    };
+    let pop = async || pop_with(None).await;
+    let headers = |pop: &str| TokenHeaders {
+        attestation: ClientAttestationHeaders {
+            client_attestation: Some(attestation.clone()),
+            client_attestation_pop: Some(pop.to_string()),
+        },
+        ..TokenHeaders::default()
+    };
+
+    // --------------------------------------------------
+    // The Wallet Provider is not trusted
+    // --------------------------------------------------
     let request = credibil_oid4vci::Request {
-        body: request,
+        body: token_request(&provider, client_id, None).await,
+        headers: headers(&pop().await),
+    };
+    let Err(Error::InvalidClient(_)) = credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject an attestation from an untrusted wallet provider");
+    };
+
+    // --------------------------------------------------
+    // The client must present an attestation
+    // --------------------------------------------------
+    provider.trust_wallet_provider(ISSUER, wp_did).await.expect("should trust wallet provider");
+
+    let request = token_request(&provider, client_id, None).await;
+    let Err(Error::InvalidClient(_)) = credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should require a client attestation");
+    };
+
+    // --------------------------------------------------
+    // The attestation must be issued by the Wallet Provider signing it
+    // --------------------------------------------------
+    let request = credibil_oid4vci::Request {
+        body: token_request(&provider, client_id, None).await,
+        headers: TokenHeaders {
+            attestation: ClientAttestationHeaders {
+                client_attestation: Some(attest("did:example:other").await),
+                client_attestation_pop: Some(pop().await),
+            },
+            ..TokenHeaders::default()
+        },
+    };
+    let Err(Error::InvalidClient(_)) = credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject an attestation with a mismatched `iss`");
+    };
+
+    // --------------------------------------------------
+    // A valid attestation and PoP
+    // --------------------------------------------------
+    let pop = pop().await;
+    let request = credibil_oid4vci::Request {
+        body: token_request(&provider, client_id, None).await,
+        headers: headers(&pop),
+    };
+    credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+
+    let request = credibil_oid4vci::Request {
+        body: token_request(&provider, client_id, None).await,
+        headers: headers(&pop),
+    };
+    let Err(Error::InvalidClient(_)) = credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject a reused client attestation PoP");
+    };
+
+    // --------------------------------------------------
+    // A `c_nonce` cannot be used as an attestation challenge
+    // --------------------------------------------------
+    let c_nonce = credibil_oid4vci::handle(ISSUER, NonceRequest, &provider)
+        .await
+        .expect("should return nonce")
+        .body
+        .c_nonce;
+    let request = credibil_oid4vci::Request {
+        body: token_request(&provider, client_id, None).await,
+        headers: headers(&pop_with(Some(&c_nonce)).await),
+    };
+    let Err(Error::InvalidClient(_)) = credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject a `c_nonce` as the client attestation PoP nonce");
+    };
+
+    // --------------------------------------------------
+    // An attestation challenge can be used once
+    // --------------------------------------------------
+    let challenge = credibil_oid4vci::handle(ISSUER, ChallengeRequest, &provider)
+        .await
+        .expect("should return challenge")
+        .body
+        .attestation_challenge;
+    let request = credibil_oid4vci::Request {
+        body: token_request(&provider, client_id, None).await,
+        headers: headers(&pop_with(Some(&challenge)).await),
+    };
+    credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+
+    let request = credibil_oid4vci::Request {
+        body: token_request(&provider, client_id, None).await,
+        headers: headers(&pop_with(Some(&challenge)).await),
+    };
+    let Err(Error::InvalidClient(_)) = credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject a reused attestation challenge");
+    };
+}
+
+// Should allow confidential clients to introspect tokens and clients to
+// revoke tokens issued to them.
+#[tokio::test]
+async fn introspect_revoke() {
+    let provider = Issuer::new(ISSUER).await;
+    let wallet_id = "http://localhost:8082";
+
+    let request = token_request(&provider, wallet_id, None).await;
+    let token =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should return token");
+    let refresh_token = token.refresh_token.clone().expect("should have refresh token");
+
+    // an administration console authenticating using `client_secret_jwt`
+    let mut client =
+        Metadata::client(&provider, ISSUER, wallet_id).await.expect("should have client");
+    client.oauth.token_endpoint_auth_method = Some(TokenEndpointAuth::ClientSecretJwt);
+    client.oauth.client_secret = Some("s3cr3t".to_string());
+    let client = Metadata::register(&provider, ISSUER, &client).await.expect("should register");
+    let admin_id = client.oauth.client_id.clone();
+    let assertion = |endpoint: &str| {
+        let claims = ClientAssertionClaims::new(&admin_id, format!("{ISSUER}/{endpoint}"));
+        Some(ClientAssertion::JwtBearer {
+            client_assertion: hs256(&claims, "s3cr3t"),
+        })
+    };
+
+    // --------------------------------------------------
+    // Only confidential clients may introspect tokens
+    // --------------------------------------------------
+    let request = IntrospectionRequest {
+        token: token.access_token.clone(),
+        client_id: wallet_id.to_string(),
+        ..IntrospectionRequest::default()
+    };
+    let Err(Error::UnauthorizedClient(_)) =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await
+    else {
+        panic!("should reject introspection by a public client");
+    };
+
+    let request = IntrospectionRequest {
+        token: token.access_token.clone(),
+        token_type_hint: None,
+        client_id: admin_id.clone(),
+        client_assertion: assertion("introspect"),
+    };
+    let response =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should introspect");
+    assert!(response.active);
+    assert_eq!(response.client_id.as_deref(), Some(wallet_id));
+    assert_eq!(response.sub.as_deref(), Some(BOB_SUBJECT));
+    assert_eq!(response.authorization_details, token.authorization_details);
+
+    let request = IntrospectionRequest {
+        token: refresh_token.clone(),
+        token_type_hint: Some(TokenTypeHint::RefreshToken),
+        client_id: admin_id.clone(),
+        client_assertion: assertion("introspect"),
+    };
+    let response =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should introspect");
+    assert!(response.active);
+
+    // --------------------------------------------------
+    // The Wallet revokes its access token
+    // --------------------------------------------------
+    let request = RevocationRequest {
+        token: token.access_token.clone(),
+        client_id: wallet_id.to_string(),
+        ..RevocationRequest::default()
+    };
+    credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should revoke");
+
+    let request = IntrospectionRequest {
+        token: token.access_token.clone(),
+        token_type_hint: None,
+        client_id: admin_id.clone(),
+        client_assertion: assertion("introspect"),
+    };
+    let response =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should introspect");
+    assert_eq!(*response, IntrospectionResponse::default());
+
+    // --------------------------------------------------
+    // The administration console revokes the refresh token
+    // --------------------------------------------------
+    let refresh = |refresh_token: &str| {
+        TokenRequest::builder()
+            .client_id(wallet_id)
+            .grant_type(TokenGrantType::RefreshToken {
+                refresh_token: refresh_token.to_string(),
+            })
+            .build()
+    };
+    let refreshed = credibil_oid4vci::handle(ISSUER, refresh(&refresh_token), &provider)
+        .await
+        .expect("should return token");
+    let rotated = refreshed.refresh_token.clone().expect("should have refresh token");
+
+    // revoking the original refresh token revokes its family
+    let request = RevocationRequest {
+        token: refresh_token.clone(),
+        token_type_hint: Some(TokenTypeHint::RefreshToken),
+        client_id: admin_id.clone(),
+        client_assertion: assertion("revoke"),
+    };
+    credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should revoke");
+
+    let Err(Error::InvalidGrant(_)) =
+        credibil_oid4vci::handle(ISSUER, refresh(&rotated), &provider).await
+    else {
+        panic!("should reject a revoked refresh token");
+    };
+
+    let request = IntrospectionRequest {
+        token: refreshed.access_token.clone(),
+        token_type_hint: None,
+        client_id: admin_id.clone(),
+        client_assertion: assertion("introspect"),
+    };
+    let response =
+        credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("should introspect");
+    assert_eq!(*response, IntrospectionResponse::default());
+}
+
+// Should offer credentials using one of the issuer's authorization servers
+// and require authorization details to be located at the issuer.
+#[tokio::test]
+async fn authorization_servers() {
+    const OWNER: &str = "http://localhost:8091";
+    const EXTERNAL: &str = "https://auth.example.com";
+
+    let provider = Issuer::new(OWNER).await;
+    provider
+        .use_authorization_servers(OWNER, &[ISSUER, EXTERNAL])
+        .await
+        .expect("should set authorization servers");
+
+    let offer = |authorization_server: Option<&str>| {
+        let builder = CreateOfferRequest::builder()
+            .subject_id(BOB_SUBJECT)
+            .with_credential("EmployeeID_W3C_VC");
+        match authorization_server {
+            Some(server) => builder.authorization_server(server).build(),
+            None => builder.build(),
+        }
+    };
+
+    // --------------------------------------------------
+    // Unlisted authorization servers are rejected
+    // --------------------------------------------------
+    let Err(Error::InvalidRequest(_)) =
+        credibil_oid4vci::handle(OWNER, offer(Some("https://unknown.example.com")), &provider)
+            .await
+    else {
+        panic!("should reject an unlisted authorization server");
+    };
+
+    // --------------------------------------------------
+    // A listed third-party authorization server is carried in the offer
+    // --------------------------------------------------
+    let mut request = offer(Some(EXTERNAL));
+    request.subject_id = None;
+    request.grant_types = Some(vec![GrantType::AuthorizationCode]);
+    let response =
+        credibil_oid4vci::handle(OWNER, request, &provider).await.expect("should create offer");
+    let grants = response.offer_type.as_object().expect("should have offer").grants.clone();
+    let grant = grants.and_then(|g| g.authorization_code).expect("should have grant");
+    assert_eq!(grant.authorization_server.as_deref(), Some(EXTERNAL));
+
+    // pre-authorized codes can only be redeemed at the issuer's own server
+    let Err(Error::InvalidRequest(_)) =
+        credibil_oid4vci::handle(OWNER, offer(Some(EXTERNAL)), &provider).await
+    else {
+        panic!("should reject a pre-authorized code grant for a third-party server");
+    };
+
+    // --------------------------------------------------
+    // The first listed authorization server is used by default
+    // --------------------------------------------------
+    let response =
+        credibil_oid4vci::handle(OWNER, offer(None), &provider).await.expect("should create offer");
+    let grants = response.offer_type.as_object().expect("should have offer").grants.clone();
+    let grant = grants.and_then(|g| g.pre_authorized_code).expect("should have grant");
+    assert_eq!(grant.authorization_server.as_deref(), Some(ISSUER));
+
+    // --------------------------------------------------
+    // Authorization details must contain the issuer in `locations`
+    // --------------------------------------------------
+    let request = TokenRequest::builder()
+        .grant_type(TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: grant.pre_authorized_code,
+            tx_code: response.tx_code.clone(),
+        })
+        .with_authorization_detail(
+            AuthorizationDetail::builder().configuration_id("EmployeeID_W3C_VC").build(),
+        )
+        .build();
+    let Err(Error::InvalidAuthorizationDetails(_)) =
+        credibil_oid4vci::handle(OWNER, request, &provider).await
+    else {
+        panic!("should require `locations`");
+    };
+
+    let response =
+        credibil_oid4vci::handle(OWNER, offer(None), &provider).await.expect("should create offer");
+    let grants = response.offer_type.as_object().expect("should have offer").grants.clone();
+    let grant = grants.and_then(|g| g.pre_authorized_code).expect("should have grant");
+
+    let request = TokenRequest::builder()
+        .grant_type(TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: grant.pre_authorized_code,
+            tx_code: response.tx_code.clone(),
+        })
+        .with_authorization_detail(
+            AuthorizationDetail::builder()
+                .configuration_id("EmployeeID_W3C_VC")
+                .location(ISSUER)
+                .build(),
+        )
+        .build();
+    let token =
+        credibil_oid4vci::handle(OWNER, request, &provider).await.expect("should return token");
+    let details = token.authorization_details.expect("should have authorization details");
+    assert_eq!(details[0].authorization_detail.locations, Some(vec![ISSUER.to_string()]));
+}
+
+// Should record each step of the issuance lifecycle with the provider.
+#[tokio::test]
+async fn issuance_events() {
+    // a separate issuer so events from other tests are not recorded
+    const OWNER: &str = "http://localhost:8092";
+
+    let provider = Issuer::new(OWNER).await;
+    let bob = bob().await;
+
+    let request = CreateOfferRequest::builder()
+        .subject_id(BOB_SUBJECT)
+        .with_credential("EmployeeID_W3C_VC")
+        .by_ref(true)
+        .build();
+    let create_offer =
+        credibil_oid4vci::handle(OWNER, request, &provider).await.expect("should create offer");
+
+    let uri = create_offer.offer_type.as_uri().expect("should have offer");
+    let id = uri.rsplit('/').next().expect("should have offer id");
+    let request = CredentialOfferRequest { id: id.to_string() };
+    let offer =
+        credibil_oid4vci::handle(OWNER, request, &provider).await.expect("should fetch offer");
+    let grants = offer.0.clone().grants.expect("should have grant");
+    let grant = grants.pre_authorized_code.expect("should have pre-authorized code grant");
+
+    let request = TokenRequest::builder()
+        .grant_type(TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: grant.pre_authorized_code,
+            tx_code: create_offer.tx_code.clone(),
+        })
+        .build();
+    let token =
+        credibil_oid4vci::handle(OWNER, request, &provider).await.expect("should return token");
+
+    let nonce = credibil_oid4vci::handle(OWNER, NonceRequest, &provider)
+        .await
+        .expect("should return nonce");
+    let bob_key = bob
+        .verification_method()
+        .await
+        .expect("should have key")
+        .try_into()
+        .expect("should map key to key binding");
+    let jws = JwsBuilder::new()
+        .typ(JwtType::ProofJwt)
+        .payload(ProofClaims::new().credential_issuer(ISSUER).nonce(&nonce.c_nonce))
+        .key_ref(&bob_key)
+        .add_signer(bob)
+        .build()
+        .await
+        .expect("builds JWS");
+    let details = &token.authorization_details.as_ref().expect("should have authorization details");
+    let request = credibil_oid4vci::Request {
+        body: CredentialRequest::builder()
+            .credential_identifier(&details[0].credential_identifiers[0])
+            .with_proof(jws.encode().expect("should encode JWS"))
+            .build(),
+        headers: CredentialHeaders {
+            authorization: token.access_token.clone(),
+            dpop: None,
+        },
+    };
+    let response = credibil_oid4vci::handle(OWNER, request, &provider)
+        .await
+        .expect("should return credential");
+    let CredentialResponse::Credentials { notification_id, .. } = &*response else {
+        panic!("expected credentials");
+    };
+    let notification_id = notification_id.clone().expect("should have notification id");
+
+    let request = credibil_oid4vci::Request {
+        body: NotificationRequest::builder()
+            .notification_id(&notification_id)
+            .event(NotificationEvent::CredentialAccepted)
+            .build(),
         headers: NotificationHeaders {
             authorization: token.access_token.clone(),
+            dpop: None,
         },
     };
+    credibil_oid4vci::handle(OWNER, request, &provider).await.expect("response is ok");
+
+    // --------------------------------------------------
+    // Each step has been recorded, in order
+    // --------------------------------------------------
+    let events = provider.events(OWNER).expect("should have events");
+    let kinds = events.into_iter().map(|e| e.kind).collect::<Vec<_>>();
+    let [
+        IssuanceEventKind::OfferCreated { .. },
+        IssuanceEventKind::OfferFetched { .. },
+        IssuanceEventKind::TokenIssued { grant_type, .. },
+        IssuanceEventKind::CredentialIssued {
+            format,
+            notification_id: issued_id,
+            ..
+        },
+        IssuanceEventKind::NotificationReceived { event, .. },
+    ] = kinds.as_slice()
+    else {
+        panic!("unexpected events: {kinds:?}");
+    };
+    assert_eq!(grant_type, &GrantType::PreAuthorizedCode);
+    assert_eq!(format, "jwt_vc_json");
+    assert_eq!(issued_id, &notification_id);
+    assert_eq!(event, &NotificationEvent::CredentialAccepted);
+}
+
+// Create a pre-authorized offer for Bob and build a token request for it.
+async fn token_request(
+    provider: &Issuer, client_id: &str, assertion: Option<&str>,
+) -> TokenRequest {
+    let request = CreateOfferRequest::builder()
+        .subject_id(BOB_SUBJECT)
+        .with_credential("EmployeeID_W3C_VC")
+        .build();
+    let response =
+        credibil_oid4vci::handle(ISSUER, request, provider).await.expect("should create offer");
+    let offer = response.offer_type.as_object().expect("should have offer").clone();
+    let grants = offer.grants.expect("should have grant");
+    let pre_auth_grant = grants.pre_authorized_code.expect("should have pre-authorized code grant");
+
+    let builder = TokenRequest::builder().client_id(client_id).grant_type(
+        TokenGrantType::PreAuthorizedCode {
+            pre_authorized_code: pre_auth_grant.pre_authorized_code,
+            tx_code: response.tx_code.clone(),
+        },
+    );
+    match assertion {
+        Some(assertion) => builder.client_assertion(assertion).build(),
+        None => builder.build(),
+    }
+}
 
-    credibil_oid4vci::handle(ISSUER, request, &provider).await.expect("response is ok");
+// MAC a client assertion (HS256) using the client secret.
+fn hs256(claims: &ClientAssertionClaims, secret: &str) -> String {
+    let header = Base64UrlUnpadded::encode_string(br#"{"alg":"HS256","typ":"JWT"}"#);
+    let payload =
+        Base64UrlUnpadded::encode_string(&serde_json::to_vec(claims).expect("should serialize"));
+    let signing_input = format!("{header}.{payload}");
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("should create HMAC");
+    mac.update(signing_input.as_bytes());
+    let signature = Base64UrlUnpadded::encode_string(&mac.finalize().into_bytes());
+    format!("{signing_input}.{signature}")
 }
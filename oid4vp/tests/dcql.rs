@@ -3,7 +3,7 @@
 use credibil_oid4vp::datastore::Datastore;
 use credibil_oid4vp::identity::{Signature, VerifyBy};
 use credibil_oid4vp::jose::PublicKeyJwk;
-use credibil_oid4vp::status::{StatusClaim, StatusList, TokenBuilder};
+use credibil_oid4vp::status::{BitsPerToken, StatusClaim, StatusListRecord, TokenBuilder};
 use credibil_oid4vp::vdc::{
     DcqlQuery, MdocBuilder, SdJwtVcBuilder, W3cVcBuilder, mso_mdoc, sd_jwt, w3c_vc,
 };
@@ -494,11 +494,13 @@ async fn populate(owner: &str) -> Wallet {
 
     // create a status list token
     let statuslist_id = format!("{ISSUER_ID}/statuslists/1");
-    let mut status_list = StatusList::new().expect("should create status list");
-    let status_claim = status_list.add_entry(&statuslist_id).unwrap();
+    let mut record =
+        StatusListRecord::new(&statuslist_id, 16, BitsPerToken::One).expect("should create record");
+    let status_claim = record.allocate().expect("should allocate entry");
     let token = TokenBuilder::new()
-        .status_list(status_list.clone())
-        .uri("https://example.com/statuslists/1")
+        .status_list(record.status_list.clone())
+        .uri(&statuslist_id)
+        .issuer(ISSUER_ID)
         .signer(issuer)
         .build()
         .await